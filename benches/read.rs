@@ -0,0 +1,49 @@
+//! Compares reading every sample of each format via `Reader::samples` against
+//! `Reader::read_into`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::io::Cursor;
+
+const SAMPLES: [(&str, &str); 4] = [
+    ("flac", "samples/sine_440hz_stereo.flac"),
+    ("ogg_vorbis", "samples/sine_440hz_stereo.ogg"),
+    ("wav", "samples/sine_440hz_stereo.wav"),
+    ("caf_alac", "samples/sine_440hz_stereo.caf"),
+];
+
+fn open(bytes: &[u8]) -> audrey::Reader<Cursor<&[u8]>> {
+    audrey::Reader::new(Cursor::new(bytes)).unwrap()
+}
+
+fn read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    for &(name, path) in SAMPLES.iter() {
+        let bytes = std::fs::read(path).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("samples", name), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut reader = open(bytes);
+                reader.samples::<f32>().map(Result::unwrap).sum::<f32>()
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("read_into", name), &bytes, |b, bytes| {
+            let mut buf = vec![0.0f32; 4096];
+            b.iter(|| {
+                let mut reader = open(bytes);
+                let mut sum = 0.0;
+                loop {
+                    let read = reader.read_into(&mut buf).unwrap();
+                    sum += buf[..read].iter().sum::<f32>();
+                    if read < buf.len() {
+                        break sum;
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read);
+criterion_main!(benches);
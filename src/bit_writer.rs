@@ -0,0 +1,78 @@
+//! A bit-level writer shared by the encoders.
+
+/// A big-endian bit sink.
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            acc: 0,
+            bits: 0,
+        }
+    }
+
+    /// The total number of bits written.
+    pub fn len(&self) -> u64 {
+        self.bytes.len() as u64 * 8 + u64::from(self.bits)
+    }
+
+    /// The completed bytes written so far.
+    #[cfg(feature = "flac")]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Writes the `bits` least significant bits of `value`, where `bits` is at most 32.
+    pub fn write(&mut self, value: u64, bits: u32) {
+        debug_assert!(bits <= 32);
+        if bits == 0 {
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1 << self.bits) - 1;
+    }
+
+    /// Writes `value` in two's complement using `bits` bits, where `bits` is at most 64.
+    pub fn write_signed(&mut self, value: i64, bits: u32) {
+        if bits > 32 {
+            self.write((value >> 32) as u64, bits - 32);
+            self.write(value as u64, 32);
+        } else {
+            self.write(value as u64, bits);
+        }
+    }
+
+    /// Appends all bits written to `other`.
+    pub fn append(&mut self, other: &BitWriter) {
+        if self.bits == 0 {
+            self.bytes.extend_from_slice(&other.bytes);
+        } else {
+            for &byte in &other.bytes {
+                self.write(u64::from(byte), 8);
+            }
+        }
+        self.write(other.acc, other.bits);
+    }
+
+    /// Writes zero bits up until the next byte boundary.
+    pub fn pad_to_byte(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.pad_to_byte();
+        self.bytes
+    }
+}
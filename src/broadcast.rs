@@ -0,0 +1,207 @@
+//! Items for the Broadcast Wave Format (BWF) extension to WAV files, used to exchange audio
+//! between broadcast and post-production tools.
+
+/// The length of the fields preceding the coding history of a `bext` chunk.
+#[cfg(feature = "wav")]
+const BEXT_LEN: usize = 602;
+
+/// The value of a loudness field of a `bext` chunk that has not been measured.
+#[cfg(feature = "wav")]
+const LOUDNESS_UNSET: i16 = 0x7FFF;
+
+/// The contents of the `bext` chunk of a Broadcast Wave Format file, as defined by EBU Tech 3285.
+///
+/// Text fields are limited to the length given for each, in bytes, and are truncated when written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BroadcastExtension {
+    /// A description of the audio, of up to 256 bytes.
+    pub description: String,
+    /// The name of the organisation or software that created the audio, of up to 32 bytes.
+    pub originator: String,
+    /// A unique reference given to the audio by its originator, of up to 32 bytes.
+    pub originator_reference: String,
+    /// The date on which the audio was created, of the form `yyyy-mm-dd`.
+    pub origination_date: String,
+    /// The time at which the audio was created, of the form `hh:mm:ss`.
+    pub origination_time: String,
+    /// The position of the first frame of the audio as a number of frames since midnight, used to
+    /// place the audio on a timeline.
+    pub time_reference: u64,
+    /// The version of the `bext` chunk, where version `1` adds the UMID and version `2` the
+    /// loudness fields.
+    pub version: u16,
+    /// The SMPTE Unique Material Identifier of the audio, which is all zeroes where there is none.
+    pub umid: [u8; 64],
+    /// The integrated loudness of the audio in LUFS.
+    pub loudness_value: Option<f32>,
+    /// The loudness range of the audio in LU.
+    pub loudness_range: Option<f32>,
+    /// The maximum true peak level of the audio in dBTP.
+    pub max_true_peak_level: Option<f32>,
+    /// The maximum momentary loudness of the audio in LUFS.
+    pub max_momentary_loudness: Option<f32>,
+    /// The maximum short-term loudness of the audio in LUFS.
+    pub max_short_term_loudness: Option<f32>,
+    /// The history of the processes applied to the audio, one per line.
+    pub coding_history: String,
+}
+
+impl Default for BroadcastExtension {
+    /// A version `2` extension with empty fields and no loudness measurements.
+    fn default() -> Self {
+        BroadcastExtension {
+            description: String::new(),
+            originator: String::new(),
+            originator_reference: String::new(),
+            origination_date: String::new(),
+            origination_time: String::new(),
+            time_reference: 0,
+            version: 2,
+            umid: [0; 64],
+            loudness_value: None,
+            loudness_range: None,
+            max_true_peak_level: None,
+            max_momentary_loudness: None,
+            max_short_term_loudness: None,
+            coding_history: String::new(),
+        }
+    }
+}
+
+impl BroadcastExtension {
+    /// The `time_reference` as the time of day at the given sample rate.
+    ///
+    /// Returns `None` if the `sample_rate` is `0`.
+    pub fn time_reference_duration(&self, sample_rate: u32) -> Option<std::time::Duration> {
+        let sample_rate = u64::from(sample_rate);
+        if sample_rate == 0 {
+            return None;
+        }
+        let secs = self.time_reference / sample_rate;
+        let nanos = (self.time_reference % sample_rate) * 1_000_000_000 / sample_rate;
+        Some(std::time::Duration::new(secs, nanos as u32))
+    }
+}
+
+// Reads the contents of a `bext` chunk.
+//
+// Loudness fields are only read from version 2 onwards, being reserved by earlier versions.
+#[cfg(feature = "wav")]
+pub(crate) fn read_bext(bext: &[u8]) -> Option<BroadcastExtension> {
+    let text = |bytes: &[u8]| {
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+
+    let fields = bext.get(..BEXT_LEN)?;
+    let mut time_reference = [0; 8];
+    time_reference.copy_from_slice(&fields[338..346]);
+    let version = u16::from_le_bytes([fields[346], fields[347]]);
+    let mut umid = [0; 64];
+    umid.copy_from_slice(&fields[348..412]);
+    let loudness = |index: usize| {
+        let position = 412 + index * 2;
+        let value = i16::from_le_bytes([fields[position], fields[position + 1]]);
+        if version < 2 || value == LOUDNESS_UNSET {
+            return None;
+        }
+        Some(f32::from(value) / 100.0)
+    };
+    Some(BroadcastExtension {
+        description: text(&fields[..256]),
+        originator: text(&fields[256..288]),
+        originator_reference: text(&fields[288..320]),
+        origination_date: text(&fields[320..330]),
+        origination_time: text(&fields[330..338]),
+        time_reference: u64::from_le_bytes(time_reference),
+        version,
+        umid,
+        loudness_value: loudness(0),
+        loudness_range: loudness(1),
+        max_true_peak_level: loudness(2),
+        max_momentary_loudness: loudness(3),
+        max_short_term_loudness: loudness(4),
+        coding_history: text(&bext[BEXT_LEN..]),
+    })
+}
+
+// The contents of a `bext` chunk describing `extension`.
+//
+// The version is raised to 2 where any loudness is given, as earlier versions cannot hold it.
+#[cfg(feature = "wav")]
+pub(crate) fn bext(extension: &BroadcastExtension) -> Vec<u8> {
+    let text = |bext: &mut Vec<u8>, text: &str, len: usize| {
+        let bytes = &text.as_bytes()[..text.len().min(len)];
+        bext.extend_from_slice(bytes);
+        bext.resize(bext.len() + len - bytes.len(), 0);
+    };
+
+    let loudness = [
+        extension.loudness_value,
+        extension.loudness_range,
+        extension.max_true_peak_level,
+        extension.max_momentary_loudness,
+        extension.max_short_term_loudness,
+    ];
+    let version = if loudness.iter().any(Option::is_some) {
+        extension.version.max(2)
+    } else {
+        extension.version
+    };
+
+    let mut bext = Vec::with_capacity(BEXT_LEN + extension.coding_history.len());
+    text(&mut bext, &extension.description, 256);
+    text(&mut bext, &extension.originator, 32);
+    text(&mut bext, &extension.originator_reference, 32);
+    text(&mut bext, &extension.origination_date, 10);
+    text(&mut bext, &extension.origination_time, 8);
+    bext.extend_from_slice(&extension.time_reference.to_le_bytes());
+    bext.extend_from_slice(&version.to_le_bytes());
+    bext.extend_from_slice(&extension.umid);
+    for value in &loudness {
+        let value = match *value {
+            Some(value) => (value * 100.0)
+                .round()
+                .clamp(f32::from(i16::MIN), f32::from(LOUDNESS_UNSET - 1))
+                as i16,
+            None if version >= 2 => LOUDNESS_UNSET,
+            None => 0,
+        };
+        bext.extend_from_slice(&value.to_le_bytes());
+    }
+    // The reserved bytes.
+    bext.resize(BEXT_LEN, 0);
+    bext.extend_from_slice(extension.coding_history.as_bytes());
+    pad_text(&mut bext);
+    bext
+}
+
+// The contents of an `iXML` chunk holding the given text.
+#[cfg(feature = "wav")]
+pub(crate) fn ixml(ixml: &str) -> Vec<u8> {
+    let mut contents = ixml.as_bytes().to_vec();
+    pad_text(&mut contents);
+    contents
+}
+
+// Pads text to an even length with a nul byte within the chunk, rather than relying on the pad
+// byte following the chunk, which some readers, including hound, do not skip.
+#[cfg(feature = "wav")]
+fn pad_text(contents: &mut Vec<u8>) {
+    if contents.len() % 2 == 1 {
+        contents.push(0);
+    }
+}
+
+// The text of an `iXML` chunk, without any trailing nul bytes.
+#[cfg(feature = "wav")]
+pub(crate) fn read_ixml(ixml: &[u8]) -> String {
+    let end = ixml
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |end| end + 1);
+    String::from_utf8_lossy(&ixml[..end]).into_owned()
+}
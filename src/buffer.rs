@@ -0,0 +1,198 @@
+//! Items for reading entire audio files into memory and writing them back out.
+
+use crate::read::{self, Description, FormatError, ReadError, Reader};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use crate::write::{self, Spec, WriteError, Writer};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use crate::{Format, SampleFormat};
+
+/// Audio read into memory in its entirety, along with the `Description` of its source.
+///
+/// Samples are stored interleaved, while planar access is available via `channel` and
+/// `to_planar`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioBuffer<S> {
+    samples: Vec<S>,
+    description: Description,
+}
+
+/// Reads the entire audio file at the given path into an `AudioBuffer`, converting its samples to
+/// the sample type `S`.
+///
+/// The buffer is allocated up front from the frame count within the file's header where known.
+pub fn read_to_buffer<S, P>(file_path: P) -> Result<AudioBuffer<S>, ReadError>
+where
+    S: read::Sample,
+    P: AsRef<std::path::Path>,
+{
+    let mut reader = read::open(file_path)?;
+    let buffer = AudioBuffer::read(&mut reader)?;
+    Ok(buffer)
+}
+
+/// Encodes the given `buffer` as a new audio file of the given `format` at the given path.
+///
+/// The channel count and sample rate are taken from the buffer's `Description`. The samples are
+/// encoded in the given `sample_format`, converting them from the buffer's sample type `S`, which
+/// otherwise plays no part in choosing the encoding. Without a `sample_format`, samples are
+/// written in the native sample format of the buffer's `Description` where `format` supports it,
+/// otherwise in the nearest integer sample format, as with `convert`.
+///
+/// Returns `WriteError::UnsupportedSpec` without creating the file if `format` cannot represent
+/// the resulting spec. If a file already exists at the path, it will be truncated.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub fn write_buffer<S, P>(
+    buffer: &AudioBuffer<S>,
+    file_path: P,
+    format: Format,
+    sample_format: Option<SampleFormat>,
+) -> Result<(), WriteError>
+where
+    S: write::Sample,
+    P: AsRef<std::path::Path>,
+{
+    let description = buffer.description();
+    let spec = Spec {
+        channel_count: description.channel_count(),
+        sample_rate: description.sample_rate(),
+        sample_format: description.sample_format(),
+    };
+    let spec = Spec {
+        sample_format: match sample_format {
+            Some(sample_format) => sample_format,
+            None => crate::convert::output_sample_format(format, spec),
+        },
+        ..spec
+    };
+    if !write::BufFileWriter::supports(format, &spec) {
+        return Err(WriteError::UnsupportedSpec(spec));
+    }
+    let file = std::fs::File::create(file_path)?;
+    let mut writer = Writer::new(std::io::BufWriter::new(file), format, spec)?;
+    for &sample in buffer.samples() {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()
+}
+
+impl<S> AudioBuffer<S>
+where
+    S: read::Sample,
+{
+    /// Reads the remainder of the audio from the given `reader` into a new `AudioBuffer`.
+    ///
+    /// If the audio ends part way through a frame, `FormatError::PartialFrame` is returned.
+    pub fn read<R>(reader: &mut Reader<R>) -> Result<Self, FormatError>
+    where
+        R: std::io::Read + std::io::Seek,
+    {
+        // The number of samples by which the buffer grows once the expected length is exceeded.
+        const CHUNK_LEN: usize = 4096;
+        // Limits the allocation made up front in case the header is corrupt.
+        const MAX_PREALLOCATED_LEN: u64 = 1 << 28;
+
+        let description = reader.description();
+        let channels = description.channel_count() as usize;
+        let expected_len = description
+            .frame_count()
+            .map(|frames| frames.saturating_mul(channels as u64))
+            .unwrap_or(0)
+            .min(MAX_PREALLOCATED_LEN) as usize;
+
+        let mut samples = vec![<S as dasp_sample::Sample>::EQUILIBRIUM; expected_len];
+        let mut len = 0;
+        loop {
+            if len == samples.len() {
+                samples.resize(len + CHUNK_LEN, <S as dasp_sample::Sample>::EQUILIBRIUM);
+            }
+            let read = reader.read_into(&mut samples[len..])?;
+            len += read;
+            if len < samples.len() {
+                break;
+            }
+        }
+        samples.truncate(len);
+
+        if channels > 0 && len % channels != 0 {
+            return Err(FormatError::PartialFrame {
+                expected: channels as u32,
+                found: (len % channels) as u32,
+                frames_read: len / channels,
+            });
+        }
+        Ok(AudioBuffer {
+            samples,
+            description,
+        })
+    }
+}
+
+impl<S> AudioBuffer<S> {
+    /// The description of the audio from which the buffer was read.
+    pub fn description(&self) -> Description {
+        self.description
+    }
+
+    /// The number of channels of audio.
+    pub fn channel_count(&self) -> u32 {
+        self.description.channel_count()
+    }
+
+    /// The rate in Hertz at which each channel of the audio is sampled.
+    pub fn sample_rate(&self) -> u32 {
+        self.description.sample_rate()
+    }
+
+    /// The number of frames of audio held by the buffer.
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / self.frame_len()
+    }
+
+    /// The interleaved samples of every channel.
+    pub fn samples(&self) -> &[S] {
+        &self.samples
+    }
+
+    /// The interleaved samples of every channel, allowing them to be processed in place.
+    pub fn samples_mut(&mut self) -> &mut [S] {
+        &mut self.samples
+    }
+
+    /// Consumes the buffer, returning its interleaved samples.
+    pub fn into_samples(self) -> Vec<S> {
+        self.samples
+    }
+
+    /// An iterator yielding a slice of samples for each frame, one sample per channel.
+    pub fn frames(&self) -> std::slice::Chunks<'_, S> {
+        self.samples.chunks(self.frame_len())
+    }
+
+    /// An iterator yielding the samples of the channel at the given `index`.
+    ///
+    /// **Panics** if `index` is not less than the `channel_count`.
+    pub fn channel(&self, index: usize) -> std::iter::StepBy<std::slice::Iter<'_, S>> {
+        assert!(
+            index < self.channel_count() as usize,
+            "channel out of range"
+        );
+        let samples = self.samples.get(index..).unwrap_or(&[]);
+        samples.iter().step_by(self.frame_len())
+    }
+
+    /// Copies the samples into a buffer per channel, in channel order.
+    pub fn to_planar(&self) -> Vec<Vec<S>>
+    where
+        S: Copy,
+    {
+        (0..self.channel_count() as usize)
+            .map(|index| self.channel(index).cloned().collect())
+            .collect()
+    }
+
+    // The number of samples per frame, treating audio without channels as mono to avoid dividing
+    // by zero.
+    fn frame_len(&self) -> usize {
+        (self.channel_count() as usize).max(1)
+    }
+}
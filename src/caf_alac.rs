@@ -1,14 +1,46 @@
 use super::read::FormatError;
+use crate::bit_writer::BitWriter;
+use crate::layout::{self, ChannelLayout};
+use crate::marker::{self, Marker};
+use crate::metadata::{self, Metadata};
+use crate::sampler::{self, SamplerInfo};
+use crate::write::Spec;
+use crate::SampleFormat;
 use alac::{Decoder, StreamInfo};
-use caf::{chunks::CafChunk, CafPacketReader, ChunkType, FormatType};
-use std::io::{Read, Seek};
+use caf::chunks::{AudioDescription, CafChunk, PacketTable};
+use caf::{CafError, CafPacketReader, ChunkType, FormatType};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
+// The type of the `strg` chunk, which `ChunkType::Strings` misidentifies as `stBg`.
+const STRINGS: ChunkType = ChunkType::Other(u32::from_be_bytes(*b"strg"));
+
+/// Decodes the ALAC packets of a CAF file, keeping the most recently decoded packet so that
+/// reading may resume exactly where it left off, including after a seek.
 pub struct AlacReader<T>
 where
     T: Read + Seek,
 {
-    pub caf_reader: CafPacketReader<T>,
+    reader: T,
+    /// The `desc` chunk, describing the sample rate, channel count and packet size of the stream.
+    pub audio_desc: AudioDescription,
+    /// The `pakt` chunk, if present.
+    pub packet_table: Option<PacketTable>,
+    /// The decoded `kuki` chunk, along with any `chan` and `info` chunks.
+    pub chunks: Vec<CafChunk>,
+    /// The decoder, configured by the magic cookie of the `kuki` chunk.
     pub alac_decoder: Decoder,
+    // The type and contents of the chunks that the caf crate cannot decode, e.g. `mark`.
+    undecoded_chunks: Vec<(ChunkType, Vec<u8>)>,
+    // The position of the first packet within the stream.
+    data_position: u64,
+    frame_count: Option<u64>,
+    // The index of the decoded channel placed at each index of a frame, if reordering.
+    channel_order: Option<Vec<usize>>,
+    // The index of the next packet to be read.
+    packet_index: usize,
+    // The samples of the most recently decoded packet and the index of the next sample.
+    buffer: Vec<i32>,
+    index: usize,
 }
 
 impl<T> AlacReader<T>
@@ -20,8 +52,22 @@ where
     /// Returns Err(..) on IO errors, or if the stream is not CAF.
     /// Returns Ok(Some(..)) if the format inside is ALAC,
     /// None if its not ALAC.
-    pub fn new(rdr: T) -> Result<Option<Self>, FormatError> {
-        let caf_reader = CafPacketReader::new(rdr, vec![ChunkType::MagicCookie])?;
+    pub fn new(mut rdr: T) -> Result<Option<Self>, FormatError> {
+        // The packet reader fails unless every chunk it is asked for is present, so optional
+        // chunks are read separately.
+        let optional_chunks = read_optional_chunks(
+            &mut rdr,
+            &[
+                ChunkType::ChannelLayout,
+                ChunkType::Info,
+                ChunkType::Marker,
+                STRINGS,
+                ChunkType::Instrument,
+                ChunkType::Region,
+            ],
+        )?;
+        rdr.seek(SeekFrom::Start(0)).map_err(CafError::Io)?;
+        let caf_reader = CafPacketReader::new(&mut rdr, vec![ChunkType::MagicCookie])?;
         if caf_reader.audio_desc.format_id != FormatType::AppleLossless {
             return Ok(None);
         }
@@ -35,25 +81,993 @@ where
             .next()
             .unwrap();
         let stream_info = StreamInfo::from_cookie(&cookie).map_err(|_| FormatError::Alac(()))?;
-        Ok(Some(AlacReader {
-            caf_reader,
+        // The packet reader leaves the stream positioned at the first packet.
+        let audio_desc = caf_reader.audio_desc;
+        let packet_table = caf_reader.packet_table;
+        let mut chunks = caf_reader.chunks;
+        let mut undecoded_chunks = Vec::new();
+        for (chunk_type, contents) in optional_chunks {
+            match chunk_type {
+                // Malformed chunks are skipped rather than preventing the audio from being read.
+                ChunkType::ChannelLayout | ChunkType::Info => {
+                    chunks.extend(caf::chunks::decode_chunk(chunk_type, contents).ok())
+                }
+                _ => undecoded_chunks.push((chunk_type, contents)),
+            }
+        }
+        let data_position = rdr.stream_position().map_err(CafError::Io)?;
+        let mut reader = AlacReader {
+            reader: rdr,
+            audio_desc,
+            packet_table,
+            chunks,
             alac_decoder: Decoder::new(stream_info),
-        }))
+            undecoded_chunks,
+            data_position,
+            frame_count: None,
+            channel_order: None,
+            packet_index: 0,
+            buffer: Vec::new(),
+            index: 0,
+        };
+        reader.frame_count = reader.read_frame_count().map_err(CafError::Io)?;
+        Ok(Some(reader))
     }
+
+    /// Decodes the next packet of the stream into interleaved samples, or returns `None` at the
+    /// end of the stream.
+    ///
+    /// Reading packets directly bypasses any samples buffered by `next_sample` and `read_into`.
     pub fn read_packet(&mut self) -> Result<Option<Vec<i32>>, FormatError> {
         let mut output_buf: Vec<i32> = vec![
             0;
-            (self.caf_reader.audio_desc.frames_per_packet
-                * self.caf_reader.audio_desc.channels_per_frame)
+            (self.audio_desc.frames_per_packet * self.audio_desc.channels_per_frame)
                 as usize
         ];
-        let packet = match self.caf_reader.next_packet()? {
-            Some(pck) => pck,
+        let len = match self.packet_lengths().get(self.packet_index) {
+            Some(&len) => len as usize,
             None => return Ok(None),
         };
-        self.alac_decoder
+        let mut packet = vec![0; len];
+        self.reader.read_exact(&mut packet).map_err(CafError::Io)?;
+        self.packet_index += 1;
+        let len = self
+            .alac_decoder
             .decode_packet(&packet, &mut output_buf)
-            .map_err(|_| FormatError::Alac(()))?;
+            .map_err(|_| FormatError::Alac(()))?
+            .len();
+        // The final packet of a stream may contain fewer frames than the others.
+        output_buf.truncate(len);
+        if let Some(ref order) = self.channel_order {
+            output_buf = layout::reorder_interleaved(&output_buf, order);
+        }
         Ok(Some(output_buf))
     }
+
+    /// The number of frames yielded by the decoder.
+    pub fn frame_count(&self) -> Option<u64> {
+        self.frame_count
+    }
+
+    /// The speaker position of each channel, as described by the `chan` chunk if recognised or
+    /// by the ALAC default for the channel count otherwise.
+    ///
+    /// Channels reordered via `set_channel_order` are described in their new order.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        let channel_count = self.audio_desc.channels_per_frame as usize;
+        let layout = self
+            .chunks
+            .iter()
+            .filter_map(|chunk| match *chunk {
+                CafChunk::ChanLayout(ref layout) => {
+                    layout::from_caf_channel_layout(layout, channel_count)
+                }
+                _ => None,
+            })
+            .next()
+            .unwrap_or_else(|| layout::alac_default(channel_count));
+        match self.channel_order {
+            Some(ref order) => {
+                ChannelLayout::new(layout::reorder_interleaved(layout.speakers(), order))
+            }
+            None => layout,
+        }
+    }
+
+    /// The tags of every `info` chunk.
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+        for chunk in &self.chunks {
+            if let CafChunk::Info(ref info) = *chunk {
+                metadata::read_caf_info(info, &mut metadata);
+            }
+        }
+        metadata
+    }
+
+    /// The markers of the `mark` chunk, labelled by the `strg` chunk.
+    pub fn markers(&self) -> Vec<Marker> {
+        match self.undecoded_chunk(ChunkType::Marker) {
+            Some(mark) => marker::read_caf_markers(mark, self.undecoded_chunk(STRINGS)),
+            None => Vec::new(),
+        }
+    }
+
+    /// The pitch of the `inst` chunk and the loops of the looping regions of the `regn` chunk.
+    pub fn sampler_info(&self) -> Option<SamplerInfo> {
+        sampler::read_caf_instrument(
+            self.undecoded_chunk(ChunkType::Instrument),
+            self.undecoded_chunk(ChunkType::Region),
+        )
+    }
+
+    // The contents of the first chunk of the given type that the caf crate cannot decode.
+    fn undecoded_chunk(&self, chunk_type: ChunkType) -> Option<&[u8]> {
+        self.undecoded_chunks
+            .iter()
+            .find(|(t, _)| *t == chunk_type)
+            .map(|(_, contents)| &contents[..])
+    }
+
+    /// Reorders the channels of each frame read from here on, placing the decoded channel at
+    /// `order[i]` at index `i`.
+    ///
+    /// Any remaining samples of the current frame are reordered too, with reading resuming from
+    /// the same index within the frame.
+    pub fn set_channel_order(&mut self, order: Vec<usize>) {
+        // The samples of the current packet are already in the order currently in use.
+        let relative: Vec<usize> = match self.channel_order {
+            Some(ref current) => order
+                .iter()
+                .map(|&channel| {
+                    current
+                        .iter()
+                        .position(|&c| c == channel)
+                        .unwrap_or(channel)
+                })
+                .collect(),
+            None => order.clone(),
+        };
+        let frame_start = self.index - self.index % order.len().max(1);
+        self.buffer = layout::reorder_interleaved(&self.buffer[frame_start..], &relative);
+        self.index -= frame_start;
+        self.channel_order = Some(order);
+    }
+
+    /// Reads the next interleaved, left-justified sample, decoding the next packet if necessary.
+    pub fn next_sample(&mut self) -> Option<Result<i32, FormatError>> {
+        while self.index >= self.buffer.len() {
+            match self.read_packet() {
+                Ok(Some(packet)) => {
+                    self.buffer = packet;
+                    self.index = 0;
+                }
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        let sample = self.buffer[self.index];
+        self.index += 1;
+        Some(Ok(sample))
+    }
+
+    /// Reads interleaved, left-justified samples into `buf`, converting each via `convert` and
+    /// decoding packets as necessary.
+    ///
+    /// Returns the number of samples read, which is less than `buf.len()` only at the end of the
+    /// stream.
+    pub fn read_into<S, F>(&mut self, buf: &mut [S], mut convert: F) -> Result<usize, FormatError>
+    where
+        F: FnMut(i32) -> S,
+    {
+        let mut read = 0;
+        while read < buf.len() {
+            if self.index >= self.buffer.len() {
+                match self.read_packet()? {
+                    Some(packet) => {
+                        self.buffer = packet;
+                        self.index = 0;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            let samples = &self.buffer[self.index..];
+            let len = samples.len().min(buf.len() - read);
+            for (sample, &decoded) in buf[read..read + len].iter_mut().zip(samples) {
+                *sample = convert(decoded);
+            }
+            self.index += len;
+            read += len;
+        }
+        Ok(read)
+    }
+
+    /// Positions the reader so that the next sample read is the first of the frame at `frame`.
+    ///
+    /// The packet containing the frame is located via the packet table. Seeking beyond the end of
+    /// the stream positions the reader at the end.
+    pub fn seek(&mut self, frame: u64) -> Result<(), FormatError> {
+        let frames_per_packet = u64::from(self.audio_desc.frames_per_packet.max(1));
+        let packet_count = self.packet_lengths().len();
+        let packet_index = (frame / frames_per_packet).min(packet_count as u64) as usize;
+        let offset: u64 = self.packet_lengths()[..packet_index].iter().sum();
+        self.reader
+            .seek(SeekFrom::Start(self.data_position + offset))
+            .map_err(CafError::Io)?;
+        self.packet_index = packet_index;
+        self.buffer.clear();
+        self.index = 0;
+        if let Some(packet) = self.read_packet()? {
+            let channels = self.audio_desc.channels_per_frame as usize;
+            self.index = (frame % frames_per_packet) as usize * channels;
+            self.buffer = packet;
+        }
+        Ok(())
+    }
+
+    // Determines the number of frames from the number of packets within the packet table and the
+    // header of the final packet, leaving the stream positioned at the first packet.
+    //
+    // The number of valid frames within the packet table is not used, as some encoders write the
+    // total size of all packets there, including the unused remainder of the final packet.
+    fn read_frame_count(&mut self) -> io::Result<Option<u64>> {
+        let lengths = self.packet_lengths();
+        let last_len = match lengths.last() {
+            Some(&len) => len as usize,
+            None => return Ok(None),
+        };
+        let offset: u64 = lengths[..lengths.len() - 1].iter().sum();
+        let full_packets = (lengths.len() - 1) as u64;
+        self.reader
+            .seek(SeekFrom::Start(self.data_position + offset))?;
+        // The element header, followed by the frame count if the packet is not full.
+        let mut header = [0; 8];
+        self.reader.read_exact(&mut header[..last_len.min(8)])?;
+        self.reader.seek(SeekFrom::Start(self.data_position))?;
+        let header = u64::from_be_bytes(header);
+        let frames_per_packet = u64::from(self.audio_desc.frames_per_packet);
+        let has_size = header >> (63 - 19) & 1 == 1;
+        let last_frames = if has_size {
+            header >> 9 & 0xFFFF_FFFF
+        } else {
+            frames_per_packet
+        };
+        Ok(Some(full_packets * frames_per_packet + last_frames))
+    }
+
+    // The size in bytes of each packet within the audio data.
+    fn packet_lengths(&self) -> &[u64] {
+        self.packet_table
+            .as_ref()
+            .map(|table| &table.lengths[..])
+            .unwrap_or(&[])
+    }
+}
+
+/// The number of frames encoded within each ALAC packet.
+const FRAMES_PER_PACKET: u32 = 4096;
+
+/// Rice coding parameters stored within the magic cookie, using the reference encoder's defaults.
+const RICE_HISTORY_MULT: u32 = 40;
+const RICE_INITIAL_HISTORY: u32 = 10;
+const RICE_LIMIT: u32 = 14;
+const PB_FACTOR: u32 = 4;
+
+/// The right shift applied to predictions, i.e. the fractional precision of LPC coefficients.
+const LPC_QUANT: u32 = 9;
+
+/// The LPC orders tried for each channel in addition to plain delta coding.
+const LPC_ORDERS: [usize; 2] = [4, 8];
+
+/// Audio element tags.
+const ID_SCE: u64 = 0;
+const ID_CPE: u64 = 1;
+const ID_END: u64 = 7;
+
+/// Encodes interleaved integer samples as ALAC packets within a CAF file.
+///
+/// The file is laid out as `desc`, `kuki`, `data` and `pakt` chunks, with a `chan` chunk following
+/// the `kuki` chunk for audio of more than 2 channels. The sizes within the `kuki` and `data`
+/// chunks are updated and the `pakt` chunk rewritten on `flush` and `finalize`.
+pub struct AlacWriter<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    spec: Spec,
+    // Interleaved samples of the packet that is currently being filled.
+    buffer: Vec<i32>,
+    packet_sizes: Vec<u32>,
+    frame_count: u64,
+    cookie_position: u64,
+    metadata: Metadata,
+    sampler_info: Option<SamplerInfo>,
+    // Position of the contents of the `data` chunk, or `None` until its header has been written.
+    data_position: Option<u64>,
+    data_len: u64,
+    finalized: bool,
+}
+
+impl<W> AlacWriter<W>
+where
+    W: Write + Seek,
+{
+    /// Writes the CAF file header along with the `desc`, `kuki` and `chan` chunks.
+    ///
+    /// Any tags and sampler info are written in `info`, `inst` and `regn` chunks before the `data`
+    /// chunk once the first packet is written.
+    /// The `spec` is expected to have been validated via `AlacWriter::supports`.
+    pub(crate) fn new(mut writer: W, spec: Spec) -> io::Result<Self> {
+        writer.write_all(b"caff")?;
+        writer.write_all(&1u16.to_be_bytes())?;
+        writer.write_all(&0u16.to_be_bytes())?;
+
+        // The audio description. ALAC packets vary in size so `bytes_per_packet` is 0.
+        let format_flags: u32 = match spec.sample_format {
+            SampleFormat::I16 => 1,
+            SampleFormat::I24 => 3,
+            _ => 4,
+        };
+        write_chunk_header(&mut writer, b"desc", 32)?;
+        writer.write_all(&f64::from(spec.sample_rate).to_be_bytes())?;
+        writer.write_all(b"alac")?;
+        for &field in &[format_flags, 0, FRAMES_PER_PACKET, spec.channel_count, 0] {
+            writer.write_all(&field.to_be_bytes())?;
+        }
+
+        write_chunk_header(&mut writer, b"kuki", 24)?;
+        let cookie_position = writer.stream_position()?;
+        writer.write_all(&magic_cookie(&spec, 0, 0))?;
+
+        // Channels are written in the default WAV and FLAC order rather than that of ALAC, so the
+        // layout is described via a channel bitmap.
+        if spec.channel_count > 2 {
+            const USE_CHANNEL_BITMAP: u32 = 1 << 16;
+            let channel_bitmap = ChannelLayout::default_for(spec.channel_count as usize)
+                .channel_mask()
+                .unwrap_or(0);
+            write_chunk_header(&mut writer, b"chan", 12)?;
+            // The layout tag and bitmap, followed by the number of channel descriptions.
+            for &field in &[USE_CHANNEL_BITMAP, channel_bitmap, 0] {
+                writer.write_all(&field.to_be_bytes())?;
+            }
+        }
+
+        Ok(AlacWriter {
+            writer,
+            spec,
+            metadata: Metadata::new(),
+            sampler_info: None,
+            buffer: Vec::with_capacity((FRAMES_PER_PACKET * spec.channel_count) as usize),
+            packet_sizes: Vec::new(),
+            frame_count: 0,
+            cookie_position,
+            data_position: None,
+            data_len: 4,
+            finalized: false,
+        })
+    }
+
+    /// Whether or not the encoder can represent audio of the given `spec`.
+    pub fn supports(spec: &Spec) -> bool {
+        let sample_format = match spec.sample_format {
+            SampleFormat::I16 | SampleFormat::I24 | SampleFormat::I32 => true,
+            SampleFormat::I8 | SampleFormat::F32 | SampleFormat::F64 | SampleFormat::LossyFloat => {
+                false
+            }
+        };
+        sample_format && (1..=8).contains(&spec.channel_count) && spec.sample_rate > 0
+    }
+
+    /// A description of the audio being written.
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// Sets the tags that are written in an `info` chunk before the audio.
+    ///
+    /// This is expected to be called before any samples are written, see
+    /// `AlacWriter::has_started`.
+    pub fn set_metadata(&mut self, metadata: &Metadata) {
+        self.metadata = metadata.clone();
+    }
+
+    /// Sets the pitch and loop points that are written in `inst` and `regn` chunks before the
+    /// audio.
+    ///
+    /// This is expected to be called before any samples are written, see
+    /// `AlacWriter::has_started`.
+    pub fn set_sampler_info(&mut self, sampler_info: &SamplerInfo) {
+        self.sampler_info = Some(sampler_info.clone());
+    }
+
+    /// Whether or not any samples have been written or the writer has been flushed, after which
+    /// the chunks preceding the audio can no longer be changed.
+    pub fn has_started(&self) -> bool {
+        self.data_position.is_some() || !self.buffer.is_empty()
+    }
+
+    /// Buffers the given sample, encoding a packet each time one has been filled.
+    ///
+    /// The sample is expected to be within the range of the spec's sample format.
+    pub fn write_sample(&mut self, sample: i32) -> io::Result<()> {
+        self.buffer.push(sample);
+        if self.buffer.len() == (FRAMES_PER_PACKET * self.spec.channel_count) as usize {
+            self.write_packet()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the packet table and chunk sizes reflecting the packets written so far and flushes
+    /// the inner writer.
+    ///
+    /// Samples of an incomplete packet remain buffered, as only the final packet of the stream
+    /// may be shorter than the others.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let data_position = self.data_position()?;
+        let end = self.writer.stream_position()?;
+
+        // The packet table follows the audio data and is overwritten by subsequent packets.
+        let mut packet_table = Vec::new();
+        let packet_count = self.packet_sizes.len() as u64;
+        let remainder = packet_count * u64::from(FRAMES_PER_PACKET) - self.frame_count;
+        packet_table.extend_from_slice(&(packet_count as i64).to_be_bytes());
+        packet_table.extend_from_slice(&(self.frame_count as i64).to_be_bytes());
+        packet_table.extend_from_slice(&0i32.to_be_bytes());
+        packet_table.extend_from_slice(&(remainder as i32).to_be_bytes());
+        for &size in &self.packet_sizes {
+            write_vlq(&mut packet_table, u64::from(size));
+        }
+        write_chunk_header(&mut self.writer, b"pakt", packet_table.len() as i64)?;
+        self.writer.write_all(&packet_table)?;
+
+        let max_packet_size = self.packet_sizes.iter().cloned().max().unwrap_or(0);
+        let bit_rate = match self.frame_count {
+            0 => 0,
+            frames => (self.data_len - 4) * 8 * u64::from(self.spec.sample_rate) / frames,
+        };
+        self.writer.seek(SeekFrom::Start(self.cookie_position))?;
+        let cookie = magic_cookie(
+            &self.spec,
+            max_packet_size,
+            bit_rate.min(u64::from(u32::MAX)) as u32,
+        );
+        self.writer.write_all(&cookie)?;
+        self.writer.seek(SeekFrom::Start(data_position - 8))?;
+        self.writer
+            .write_all(&(self.data_len as i64).to_be_bytes())?;
+
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// Encodes any remaining samples as the final packet and completes the file.
+    pub fn finalize(&mut self) -> io::Result<()> {
+        if self.finalized {
+            return Ok(());
+        }
+        self.finalized = true;
+        // Only whole frames can be encoded, so discard any trailing partial frame.
+        let channels = self.spec.channel_count as usize;
+        let len = self.buffer.len() / channels * channels;
+        self.buffer.truncate(len);
+        if !self.buffer.is_empty() {
+            self.write_packet()?;
+        }
+        self.flush()
+    }
+
+    // The position of the contents of the `data` chunk, writing the chunks that precede it if
+    // they have not yet been written.
+    fn data_position(&mut self) -> io::Result<u64> {
+        if let Some(position) = self.data_position {
+            return Ok(position);
+        }
+        if !self.metadata.is_empty() {
+            let info = metadata::caf_info(&self.metadata);
+            write_chunk_header(&mut self.writer, b"info", info.len() as i64)?;
+            self.writer.write_all(&info)?;
+        }
+        if let Some(ref sampler_info) = self.sampler_info {
+            let (inst, regn) = sampler::caf_instrument(sampler_info);
+            write_chunk_header(&mut self.writer, b"inst", inst.len() as i64)?;
+            self.writer.write_all(&inst)?;
+            write_chunk_header(&mut self.writer, b"regn", regn.len() as i64)?;
+            self.writer.write_all(&regn)?;
+        }
+        // The size of the data chunk is updated as packets are written.
+        write_chunk_header(&mut self.writer, b"data", 4)?;
+        let position = self.writer.stream_position()?;
+        // The edit count.
+        self.writer.write_all(&0u32.to_be_bytes())?;
+        self.data_position = Some(position);
+        Ok(position)
+    }
+
+    // Encodes the buffered samples as a single packet.
+    fn write_packet(&mut self) -> io::Result<()> {
+        self.data_position()?;
+        let channels = self.spec.channel_count as usize;
+        let frames = self.buffer.len() / channels;
+        let bit_depth = self.spec.sample_format.bits_per_sample();
+
+        let mut channel_samples = vec![Vec::with_capacity(frames); channels];
+        for frame in self.buffer.chunks(channels) {
+            for (samples, &sample) in channel_samples.iter_mut().zip(frame) {
+                samples.push(sample);
+            }
+        }
+        self.buffer.clear();
+
+        let mut out = BitWriter::new();
+        let mut channel = 0;
+        for &tag in element_tags(channels) {
+            let element_channels = if tag == ID_CPE { 2 } else { 1 };
+            let samples = &channel_samples[channel..channel + element_channels];
+            let compressed = encode_element(tag, samples, bit_depth);
+            let uncompressed = encode_uncompressed_element(tag, samples, bit_depth);
+            if compressed.len() < uncompressed.len() {
+                out.append(&compressed);
+            } else {
+                out.append(&uncompressed);
+            }
+            channel += element_channels;
+        }
+        out.write(ID_END, 3);
+        let packet = out.into_bytes();
+
+        self.writer.write_all(&packet)?;
+        self.packet_sizes.push(packet.len() as u32);
+        self.frame_count += frames as u64;
+        self.data_len += packet.len() as u64;
+        Ok(())
+    }
+}
+
+impl<W> Drop for AlacWriter<W>
+where
+    W: Write + Seek,
+{
+    fn drop(&mut self) {
+        // Errors cannot be reported from `drop`, so they are ignored.
+        let _ = self.finalize();
+    }
+}
+
+// Reads the contents of every chunk of the given types, in the order of the file.
+//
+// Reading stops at the end of the file, including part way through a chunk, or at a chunk of
+// unspecified size.
+fn read_optional_chunks<R>(
+    reader: &mut R,
+    types: &[ChunkType],
+) -> Result<Vec<(ChunkType, Vec<u8>)>, CafError>
+where
+    R: Read + Seek,
+{
+    let mut chunks = Vec::new();
+    let mut header = [0; 12];
+    reader.seek(SeekFrom::Start(8))?;
+    loop {
+        match reader.read_exact(&mut header) {
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            result => result?,
+        }
+        let mut chunk_type = [0; 4];
+        chunk_type.copy_from_slice(&header[0..4]);
+        let chunk_type = ChunkType::from(u32::from_be_bytes(chunk_type));
+        let mut size = [0; 8];
+        size.copy_from_slice(&header[4..12]);
+        let size = i64::from_be_bytes(size);
+        if size < 0 {
+            break;
+        }
+        if !types.contains(&chunk_type) {
+            reader.seek(SeekFrom::Current(size))?;
+            continue;
+        }
+        let mut contents = Vec::new();
+        reader
+            .by_ref()
+            .take(size as u64)
+            .read_to_end(&mut contents)?;
+        if (contents.len() as u64) < size as u64 {
+            break;
+        }
+        chunks.push((chunk_type, contents));
+    }
+    Ok(chunks)
+}
+
+fn write_chunk_header<W>(writer: &mut W, chunk_type: &[u8; 4], size: i64) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(chunk_type)?;
+    writer.write_all(&size.to_be_bytes())
+}
+
+// The variable length quantities of the packet table, big-endian with 7 bits per byte.
+fn write_vlq(out: &mut Vec<u8>, value: u64) {
+    let mut shift = (63 - (value | 1).leading_zeros()) / 7 * 7;
+    while shift > 0 {
+        out.push(0x80 | ((value >> shift) & 0x7F) as u8);
+        shift -= 7;
+    }
+    out.push((value & 0x7F) as u8);
+}
+
+// The 24 byte `ALACSpecificConfig` stored within the `kuki` chunk.
+fn magic_cookie(spec: &Spec, max_packet_size: u32, bit_rate: u32) -> Vec<u8> {
+    let mut cookie = Vec::with_capacity(24);
+    cookie.extend_from_slice(&FRAMES_PER_PACKET.to_be_bytes());
+    cookie.push(0); // The compatible version.
+    cookie.push(spec.sample_format.bits_per_sample() as u8);
+    cookie.push(RICE_HISTORY_MULT as u8);
+    cookie.push(RICE_INITIAL_HISTORY as u8);
+    cookie.push(RICE_LIMIT as u8);
+    cookie.push(spec.channel_count as u8);
+    cookie.extend_from_slice(&255u16.to_be_bytes()); // The maximum run, unused.
+    cookie.extend_from_slice(&max_packet_size.to_be_bytes());
+    cookie.extend_from_slice(&bit_rate.to_be_bytes());
+    cookie.extend_from_slice(&spec.sample_rate.to_be_bytes());
+    cookie
+}
+
+// The audio elements used for each channel count, following the reference encoder.
+fn element_tags(channels: usize) -> &'static [u64] {
+    match channels {
+        1 => &[ID_SCE],
+        2 => &[ID_CPE],
+        3 => &[ID_SCE, ID_CPE],
+        4 => &[ID_SCE, ID_CPE, ID_SCE],
+        5 => &[ID_SCE, ID_CPE, ID_CPE],
+        6 => &[ID_SCE, ID_CPE, ID_CPE, ID_SCE],
+        7 => &[ID_SCE, ID_CPE, ID_CPE, ID_SCE, ID_SCE],
+        _ => &[ID_SCE, ID_CPE, ID_CPE, ID_CPE, ID_SCE],
+    }
+}
+
+fn write_element_header(
+    out: &mut BitWriter,
+    tag: u64,
+    frames: usize,
+    shift_bytes: u32,
+    uncompressed: bool,
+) {
+    let partial = frames != FRAMES_PER_PACKET as usize;
+    out.write(tag, 3);
+    // The element instance tag followed by 12 unused bits.
+    out.write(0, 4);
+    out.write(0, 12);
+    out.write(partial as u64, 1);
+    out.write(u64::from(shift_bytes), 2);
+    out.write(uncompressed as u64, 1);
+    if partial {
+        out.write(frames as u64, 32);
+    }
+}
+
+// An element storing the samples of each channel as-is.
+fn encode_uncompressed_element(tag: u64, channels: &[Vec<i32>], bit_depth: u32) -> BitWriter {
+    let mut out = BitWriter::new();
+    let frames = channels[0].len();
+    write_element_header(&mut out, tag, frames, 0, true);
+    for i in 0..frames {
+        for samples in channels {
+            out.write_signed(i64::from(samples[i]), bit_depth);
+        }
+    }
+    out
+}
+
+// An element storing the adaptive LPC residual of each channel, choosing the smallest of the
+// available stereo mixes for channel pairs.
+fn encode_element(tag: u64, channels: &[Vec<i32>], bit_depth: u32) -> BitWriter {
+    let frames = channels[0].len();
+
+    // The low bytes of samples wider than 16 bits are stored verbatim, with only the upper 16
+    // bits being predicted.
+    let shift_bytes = bit_depth.saturating_sub(16) / 8;
+    let shift = shift_bytes * 8;
+    let chan_bits = bit_depth - shift + channels.len() as u32 - 1;
+    let high: Vec<Vec<i32>> = channels
+        .iter()
+        .map(|samples| samples.iter().map(|&s| s >> shift).collect())
+        .collect();
+
+    // Each mix is described by its `mix_bits` and `mix_res`, where `(0, 0)` leaves the channels
+    // independent, `(1, 1)` produces mid/side and `(0, 1)` produces left/side.
+    let mixes: &[(u32, i32)] = if channels.len() == 2 {
+        &[(0, 0), (1, 1), (0, 1)]
+    } else {
+        &[(0, 0)]
+    };
+
+    let mut best: Option<(BitWriter, u32, i32)> = None;
+    for &(mix_bits, mix_res) in mixes {
+        let mixed = if mix_res == 0 {
+            high.clone()
+        } else {
+            let (u, v) = high[0]
+                .iter()
+                .zip(&high[1])
+                .map(|(&l, &r)| {
+                    let v = l - r;
+                    (r + ((v * mix_res) >> mix_bits), v)
+                })
+                .unzip();
+            vec![u, v]
+        };
+
+        let mut out = BitWriter::new();
+        let mut residuals = BitWriter::new();
+        for samples in &mixed {
+            let (coefs, residual) = best_predictor(samples, chan_bits);
+            // The LPC mode, quantization, rice history factor and order.
+            out.write(0, 4);
+            out.write(u64::from(LPC_QUANT), 4);
+            out.write(u64::from(PB_FACTOR), 3);
+            out.write(coefs.len() as u64, 5);
+            // Coefficients are stored in the reverse of the order in which they are applied.
+            for &coef in coefs.iter().rev() {
+                out.write_signed(i64::from(coef), 16);
+            }
+            residuals.append(&rice_encode(&residual, chan_bits));
+        }
+        if shift > 0 {
+            for i in 0..frames {
+                for samples in channels {
+                    out.write(samples[i] as u64, shift);
+                }
+            }
+        }
+        out.append(&residuals);
+
+        let smaller = match best {
+            Some((ref best, _, _)) => out.len() < best.len(),
+            None => true,
+        };
+        if smaller {
+            best = Some((out, mix_bits, mix_res));
+        }
+    }
+
+    let (body, mix_bits, mix_res) = best.expect("no stereo mix");
+    let mut out = BitWriter::new();
+    write_element_header(&mut out, tag, frames, shift_bytes, false);
+    out.write(u64::from(mix_bits), 8);
+    out.write(mix_res as u64, 8);
+    out.append(&body);
+    out
+}
+
+// Chooses the initial LPC coefficients that produce the smallest rice coded residual.
+fn best_predictor(samples: &[i32], chan_bits: u32) -> (Vec<i16>, Vec<i32>) {
+    // Order 0 codes the difference between consecutive samples and can never overflow.
+    let mut best = (
+        Vec::new(),
+        lpc_residual(samples, chan_bits, &[]).expect("delta overflow"),
+    );
+    let mut best_bits = rice_encode(&best.1, chan_bits).len();
+    for &order in LPC_ORDERS.iter() {
+        if samples.len() <= order + 1 {
+            continue;
+        }
+        let coefs = match lpc_coefficients(samples, order) {
+            Some(coefs) => coefs,
+            None => continue,
+        };
+        if let Some(residual) = lpc_residual(samples, chan_bits, &coefs) {
+            let bits = rice_encode(&residual, chan_bits).len() + 16 * order as u64;
+            if bits < best_bits {
+                best = (coefs, residual);
+                best_bits = bits;
+            }
+        }
+    }
+    best
+}
+
+// Finds the least squares coefficients for predicting each sample from the `order` preceding
+// samples, where all samples are relative to the sample before those.
+fn lpc_coefficients(samples: &[i32], order: usize) -> Option<Vec<i16>> {
+    let mut a = vec![vec![0.0f64; order]; order];
+    let mut b = vec![0.0f64; order];
+    for i in order + 1..samples.len() {
+        let mean = f64::from(samples[i - order - 1]);
+        let x: Vec<f64> = samples[i - order..i]
+            .iter()
+            .map(|&s| f64::from(s) - mean)
+            .collect();
+        let y = f64::from(samples[i]) - mean;
+        for j in 0..order {
+            for k in 0..order {
+                a[j][k] += x[j] * x[k];
+            }
+            b[j] += x[j] * y;
+        }
+    }
+
+    // Solve via Gaussian elimination with partial pivoting, lightly regularised so that
+    // silent or perfectly predictable blocks remain solvable.
+    for (j, row) in a.iter_mut().enumerate() {
+        row[j] += row[j] * 1e-9 + 1e-9;
+    }
+    for col in 0..order {
+        let pivot = (col..order)
+            .max_by(|&x, &y| a[x][col].abs().partial_cmp(&a[y][col].abs()).unwrap())
+            .unwrap_or(col);
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        if a[col][col] == 0.0 {
+            return None;
+        }
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (offset, row) in lower.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            for (x, &p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * p;
+            }
+            b[col + 1 + offset] -= factor * b[col];
+        }
+    }
+    let mut coefs = vec![0.0; order];
+    for row in (0..order).rev() {
+        let sum: f64 = (row + 1..order).map(|k| a[row][k] * coefs[k]).sum();
+        coefs[row] = (b[row] - sum) / a[row][row];
+    }
+
+    let scale = f64::from(1 << LPC_QUANT);
+    let quantized = coefs
+        .iter()
+        .map(|&c| (c * scale).round())
+        .take_while(|c| c.is_finite())
+        .map(|c| c.max(f64::from(i16::MIN)).min(f64::from(i16::MAX)) as i16)
+        .collect::<Vec<_>>();
+    if quantized.len() == order {
+        Some(quantized)
+    } else {
+        None
+    }
+}
+
+// Sign extends the `bits` least significant bits of `value`.
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+// Computes the residual of the adaptive predictor used by the decoder, updating the coefficients
+// in the same manner as the decoder. Returns `None` if decoding would overflow.
+fn lpc_residual(samples: &[i32], chan_bits: u32, coefs: &[i16]) -> Option<Vec<i32>> {
+    let order = coefs.len();
+    let mut coefs = coefs.to_vec();
+    let mut residual = Vec::with_capacity(samples.len());
+    residual.push(samples[0]);
+    for i in 1..(order + 1).min(samples.len()) {
+        residual.push(sign_extend(
+            samples[i].wrapping_sub(samples[i - 1]),
+            chan_bits,
+        ));
+    }
+
+    for i in order + 1..samples.len() {
+        let mean = samples[i - order - 1];
+        let window = &samples[i - order..i];
+        let mut predicted = 0i32;
+        for (&x, &coef) in window.iter().zip(&coefs) {
+            predicted = predicted.checked_add((x - mean).checked_mul(i32::from(coef))?)?;
+        }
+        let predicted = predicted.checked_add(1 << (LPC_QUANT - 1))? >> LPC_QUANT;
+        let error = sign_extend(
+            samples[i].wrapping_sub(predicted).wrapping_sub(mean),
+            chan_bits,
+        );
+        predicted.checked_add(mean)?.checked_add(error)?;
+        residual.push(error);
+
+        if error != 0 {
+            let error_sign = error.signum();
+            let mut prediction_error = error_sign * error;
+            for (j, coef) in coefs.iter_mut().enumerate() {
+                let predicted = window[j] - mean;
+                let sign = predicted.signum() * error_sign;
+                *coef = coef.checked_add(sign as i16)?;
+                let delta = ((predicted * sign) >> LPC_QUANT).checked_mul(j as i32 + 1)?;
+                prediction_error = prediction_error.checked_sub(error_sign.checked_mul(delta)?)?;
+                if prediction_error <= 0 {
+                    break;
+                }
+            }
+        }
+    }
+    Some(residual)
+}
+
+// Rice codes the residual using the adaptive parameters and zero runs of the decoder.
+fn rice_encode(residual: &[i32], chan_bits: u32) -> BitWriter {
+    let mut out = BitWriter::new();
+    let history_mult = RICE_HISTORY_MULT * PB_FACTOR / 4;
+    let mut history = RICE_INITIAL_HISTORY;
+    let mut sign_modifier = 0;
+    let mut i = 0;
+    while i < residual.len() {
+        let k = (31 - ((history >> 9) + 3).leading_zeros()).min(RICE_LIMIT);
+        let value = ((residual[i] << 1) ^ (residual[i] >> 31)) as u32;
+        write_rice_symbol(&mut out, value - sign_modifier, k, chan_bits);
+        sign_modifier = 0;
+
+        if value > 0xFFFF {
+            history = 0xFFFF;
+        } else {
+            history = (history + value * history_mult) - ((history * history_mult) >> 9);
+        }
+
+        // Runs of zeros are coded as a single count when the history is low.
+        if history < 128 && i + 1 < residual.len() {
+            let k = history.leading_zeros() - 24 + ((history + 16) >> 6);
+            let zeros = residual[i + 1..]
+                .iter()
+                .take(0xFFFF)
+                .take_while(|&&r| r == 0)
+                .count();
+            write_rice_symbol_with_modulus(&mut out, zeros as u32, k, (1 << k) - 1, 16);
+            i += zeros;
+            sign_modifier = 1;
+            history = 0;
+        }
+        i += 1;
+    }
+    out
+}
+
+fn write_rice_symbol(out: &mut BitWriter, value: u32, k: u32, bits: u32) {
+    write_rice_symbol_with_modulus(out, value, k, (1 << k) - 1, bits);
+}
+
+// Codes `value` as a unary quotient and a remainder of the modulus `m`, escaping to `bits` raw
+// bits when the quotient is too large.
+fn write_rice_symbol_with_modulus(out: &mut BitWriter, value: u32, k: u32, m: u32, bits: u32) {
+    let q = value / m;
+    if q >= 9 {
+        out.write(0x1FF, 9);
+        out.write(u64::from(value), bits);
+        return;
+    }
+    // The quotient in unary as ones terminated by a zero.
+    out.write(((1 << q) - 1) << 1, q + 1);
+    if k > 1 {
+        let r = value - q * m;
+        if r == 0 {
+            out.write(0, k - 1);
+        } else {
+            out.write(u64::from(r + 1), k);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_vlq;
+
+    #[test]
+    fn vlq_minimal_encoding() {
+        let cases: &[(u64, &[u8])] = &[
+            (0, &[0x00]),
+            (100, &[0x64]),
+            (127, &[0x7F]),
+            (128, &[0x81, 0x00]),
+            (5_000, &[0xA7, 0x08]),
+            (16_383, &[0xFF, 0x7F]),
+            (16_384, &[0x81, 0x80, 0x00]),
+        ];
+        for &(value, expected) in cases {
+            let mut out = Vec::new();
+            write_vlq(&mut out, value);
+            assert_eq!(out, expected, "{}", value);
+        }
+    }
 }
@@ -0,0 +1,221 @@
+//! Items for converting audio files between the supported formats.
+
+use crate::read::{self, ReadError, Reader};
+#[cfg(feature = "flac")]
+use crate::write::FlacOptions;
+use crate::write::{self, Spec, WriteError, Writer};
+use crate::{Format, SampleFormat};
+
+/// A builder for converting audio files from one format to another.
+///
+/// The output `Format` is determined from the extension of the output path, while the channel
+/// count, sample rate, tags, sampler info and any Broadcast Wave Format chunks (see
+/// `Writer::set_metadata`, `Writer::set_sampler_info`, `Writer::set_broadcast_extension` and
+/// `Writer::set_ixml`) are carried over from the input.
+/// Samples are streamed from the input to the output, so the input is never read into memory in
+/// its entirety.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Converter {
+    sample_format: Option<SampleFormat>,
+    #[cfg(feature = "flac")]
+    flac_options: FlacOptions,
+}
+
+/// Errors that might be returned from the `convert` function.
+#[derive(Debug)]
+pub enum ConvertError {
+    /// An error occurred while opening or reading from the input.
+    Read(ReadError),
+    /// An error occurred while creating or writing to the output.
+    Write(WriteError),
+}
+
+/// Converts the audio file at `input_path` to a new audio file at `output_path`.
+///
+/// The format of the input is determined via its header while the format of the output is
+/// determined from the extension of `output_path`. If a file already exists at `output_path`, it
+/// will be truncated.
+///
+/// This function is a convenience wrapper around `Converter::new().convert(..)`. See `Converter`
+/// for control over the encoding of the output.
+pub fn convert<P, Q>(input_path: P, output_path: Q) -> Result<(), ConvertError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    Converter::new().convert(input_path, output_path)
+}
+
+impl Converter {
+    /// A `Converter` with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The encoding, and in turn the bit depth, in which samples are written to the output.
+    ///
+    /// By default, the encoding of the input (see `Description::sample_format`) is used when the
+    /// output format supports it, with float input falling back to `SampleFormat::F32`.
+    /// Otherwise the smallest integer encoding able to hold the input's bit depth is chosen,
+    /// falling back to the largest integer encoding the output format supports.
+    pub fn sample_format(mut self, sample_format: SampleFormat) -> Self {
+        self.sample_format = Some(sample_format);
+        self
+    }
+
+    /// The options used to encode the output when converting to FLAC.
+    #[cfg(feature = "flac")]
+    pub fn flac_options(mut self, options: FlacOptions) -> Self {
+        self.flac_options = options;
+        self
+    }
+
+    /// Converts the audio file at `input_path` to a new audio file at `output_path`.
+    ///
+    /// If a file already exists at `output_path`, it will be truncated.
+    pub fn convert<P, Q>(&self, input_path: P, output_path: Q) -> Result<(), ConvertError>
+    where
+        P: AsRef<std::path::Path>,
+        Q: AsRef<std::path::Path>,
+    {
+        let mut reader = read::open(input_path)?;
+        let output_path = output_path.as_ref();
+        let format = write::format_from_path(output_path).ok_or(WriteError::UnsupportedFormat)?;
+
+        let description = reader.description();
+        let mut spec = Spec {
+            channel_count: description.channel_count(),
+            sample_rate: description.sample_rate(),
+            sample_format: description.sample_format(),
+        };
+        spec.sample_format = match self.sample_format {
+            Some(sample_format) => sample_format,
+            None => output_sample_format(format, spec),
+        };
+
+        // The spec is checked before the output is created, leaving any existing file untouched.
+        let supported = match format {
+            #[cfg(feature = "flac")]
+            Format::Flac => write::FlacWriter::<std::fs::File>::supports(
+                &spec,
+                self.flac_options.bits_per_sample,
+            ),
+            #[allow(unreachable_patterns)]
+            _ => write::BufFileWriter::supports(format, &spec),
+        };
+        if !supported {
+            return Err(WriteError::UnsupportedSpec(spec).into());
+        }
+        let file = std::fs::File::create(output_path).map_err(WriteError::Io)?;
+        let writer = std::io::BufWriter::new(file);
+        let mut writer = match format {
+            #[cfg(feature = "flac")]
+            Format::Flac => Writer::new_flac(writer, spec, self.flac_options)?,
+            #[allow(unreachable_patterns)]
+            _ => Writer::new(writer, format, spec)?,
+        };
+        writer.set_metadata(&reader.metadata())?;
+        if let Some(sampler_info) = reader.sampler_info() {
+            writer.set_sampler_info(&sampler_info)?;
+        }
+        if let Some(extension) = reader.broadcast_extension() {
+            writer.set_broadcast_extension(&extension)?;
+        }
+        if let Some(ixml) = reader.ixml() {
+            writer.set_ixml(&ixml)?;
+        }
+
+        // Float outputs are converted via `f32` so that no precision is lost to integer rounding.
+        if spec.sample_format.is_float() {
+            copy_samples::<_, _, f32>(&mut reader, &mut writer)?;
+        } else {
+            copy_samples::<_, _, i32>(&mut reader, &mut writer)?;
+        }
+        writer.finalize()?;
+        Ok(())
+    }
+}
+
+// Streams every sample from the `reader` to the `writer`, converting via the sample type `S`.
+fn copy_samples<R, W, S>(reader: &mut Reader<R>, writer: &mut Writer<W>) -> Result<(), ConvertError>
+where
+    R: std::io::Read + std::io::Seek,
+    W: std::io::Write + std::io::Seek,
+    S: read::Sample + write::Sample,
+{
+    for sample in reader.samples::<S>() {
+        let sample = sample.map_err(ReadError::from)?;
+        writer.write_sample(sample)?;
+    }
+    Ok(())
+}
+
+// Chooses the encoding for the output when none was requested, preferring that of the input.
+pub(crate) fn output_sample_format(format: Format, spec: Spec) -> SampleFormat {
+    const INTEGER_FORMATS: [SampleFormat; 4] = [
+        SampleFormat::I8,
+        SampleFormat::I16,
+        SampleFormat::I24,
+        SampleFormat::I32,
+    ];
+    let native = spec.sample_format;
+    let supported = |sample_format: &SampleFormat| {
+        let spec = Spec {
+            sample_format: *sample_format,
+            ..spec
+        };
+        write::BufFileWriter::supports(format, &spec)
+    };
+    if supported(&native) {
+        return native;
+    }
+    // Lossy and 64-bit float audio is best represented by 32-bit floats where available.
+    if native.is_float() && supported(&SampleFormat::F32) {
+        return SampleFormat::F32;
+    }
+    let bits = native.bits_per_sample();
+    // Many FLAC decoders cannot read 32-bit streams, so these are only written for 32-bit input.
+    let supported = |sample_format: &&SampleFormat| match format {
+        #[cfg(feature = "flac")]
+        Format::Flac if **sample_format == SampleFormat::I32 => false,
+        _ => supported(sample_format),
+    };
+    INTEGER_FORMATS
+        .iter()
+        .filter(|sample_format| sample_format.bits_per_sample() >= bits)
+        .find(supported)
+        .or_else(|| INTEGER_FORMATS.iter().rev().find(supported))
+        .cloned()
+        // The writer will report the unsupported spec.
+        .unwrap_or(native)
+}
+
+impl From<ReadError> for ConvertError {
+    fn from(err: ReadError) -> Self {
+        ConvertError::Read(err)
+    }
+}
+
+impl From<WriteError> for ConvertError {
+    fn from(err: WriteError) -> Self {
+        ConvertError::Write(err)
+    }
+}
+
+impl std::error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            ConvertError::Read(ref err) => Some(err),
+            ConvertError::Write(ref err) => Some(err),
+        }
+    }
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            ConvertError::Read(ref err) => err.fmt(f),
+            ConvertError::Write(ref err) => err.fmt(f),
+        }
+    }
+}
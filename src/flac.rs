@@ -0,0 +1,1763 @@
+//! FLAC decoding along with a pure-Rust FLAC encoder.
+//!
+//! Metadata is parsed by claxon, while frames are decoded here so that every bit depth permitted
+//! by the format, 4 through 32 bits per sample, may be read.
+//!
+//! The encoder produces fixed block size streams using constant, verbatim, fixed and LPC
+//! subframes with partitioned Rice coded residuals. Stereo streams may additionally be
+//! decorrelated via left/side, right/side or mid/side channel assignments, whichever is smallest.
+
+use crate::bit_writer::BitWriter;
+use crate::layout::ChannelLayout;
+use crate::marker::{self, CueSheet, Marker};
+use crate::metadata::{self, Metadata};
+use crate::picture::{self, Picture};
+use crate::sampler::{self, SamplerInfo};
+use crate::write::{FlacOptions, Spec};
+use crate::SampleFormat;
+use claxon::metadata::StreamInfo;
+use claxon::FlacReaderOptions;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+/// The number of bytes read at a time while scanning for frame sync codes.
+const SYNC_SCAN_LEN: usize = 16 * 1024;
+
+/// Bisection over frame headers stops once the search range is narrower than this many bytes.
+const MIN_BISECT_RANGE: u64 = 64 * 1024;
+
+/// Decodes the frames of a FLAC stream, keeping the most recently decoded block so that reading
+/// may resume exactly where it left off, including after a seek.
+pub struct FlacReader<R>
+where
+    R: Read + Seek,
+{
+    input: io::BufReader<R>,
+    streaminfo: StreamInfo,
+    // The position of the first frame within the stream.
+    audio_offset: u64,
+    seek_table: Vec<SeekPoint>,
+    // The name and value of each Vorbis comment, in the order of the stream.
+    tags: Vec<(String, String)>,
+    pictures: Vec<Picture>,
+    cue_sheet: Option<CueSheet>,
+    block: Block,
+    // The index of the next interleaved sample within `block`.
+    index: u32,
+    failed: bool,
+}
+
+/// A SEEKTABLE entry, with the `offset` relative to the first frame.
+#[derive(Copy, Clone, Debug)]
+struct SeekPoint {
+    sample: u64,
+    offset: u64,
+}
+
+/// The contents of the metadata blocks retained by the reader.
+struct MetadataBlocks {
+    seek_table: Vec<SeekPoint>,
+    pictures: Vec<Picture>,
+    cue_sheet: Option<CueSheet>,
+}
+
+/// The samples of a decoded frame, stored one channel after another.
+struct Block {
+    // Samples are decoded at 64 bits, as the side channel of 32-bit audio requires 33.
+    buffer: Vec<i64>,
+    block_size: u32,
+    channels: u32,
+    // The number of the first frame of audio within the block.
+    start: u64,
+}
+
+impl<R> FlacReader<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the stream header and metadata blocks, leaving the reader at the first frame.
+    pub fn new(mut reader: R) -> Result<Self, claxon::Error> {
+        let options = FlacReaderOptions {
+            metadata_only: true,
+            read_vorbis_comment: true,
+        };
+        let (streaminfo, tags) = {
+            let metadata = claxon::FlacReader::new_ext(&mut reader, options)?;
+            let tags = metadata
+                .tags()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            (metadata.streaminfo(), tags)
+        };
+        reader.seek(SeekFrom::Start(0))?;
+        let MetadataBlocks {
+            seek_table,
+            pictures,
+            cue_sheet,
+        } = read_metadata_blocks(&mut reader)?;
+        let audio_offset = reader.stream_position()?;
+        Ok(FlacReader {
+            input: io::BufReader::new(reader),
+            streaminfo,
+            audio_offset,
+            seek_table,
+            tags,
+            pictures,
+            cue_sheet,
+            block: Block::empty(),
+            index: 0,
+            failed: false,
+        })
+    }
+
+    /// The STREAMINFO metadata of the stream.
+    pub fn streaminfo(&self) -> StreamInfo {
+        self.streaminfo
+    }
+
+    /// The name and value of each Vorbis comment within the stream, in order.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// The Vorbis comments of the stream.
+    pub fn metadata(&self) -> Metadata {
+        self.tags.iter().cloned().collect()
+    }
+
+    /// The pictures of the PICTURE metadata blocks followed by those of any
+    /// `METADATA_BLOCK_PICTURE` Vorbis comments.
+    pub fn pictures(&self) -> Vec<Picture> {
+        let mut pictures = self.pictures.clone();
+        pictures.extend(picture::read_vorbis_comment_pictures(&self.tags));
+        pictures
+    }
+
+    /// The loop given by the `LOOPSTART` and `LOOPLENGTH` Vorbis comments, if any.
+    pub fn sampler_info(&self) -> Option<SamplerInfo> {
+        sampler::read_vorbis_comments(&self.metadata())
+    }
+
+    /// The contents of the CUESHEET metadata block, if any.
+    pub fn cue_sheet(&self) -> Option<&CueSheet> {
+        self.cue_sheet.as_ref()
+    }
+
+    /// A marker at the start of each track of the CUESHEET metadata block.
+    pub fn markers(&self) -> Vec<Marker> {
+        self.cue_sheet
+            .as_ref()
+            .map(CueSheet::markers)
+            .unwrap_or_default()
+    }
+
+    /// The channel mask given by the `WAVEFORMATEXTENSIBLE_CHANNEL_MASK` Vorbis comment, if any.
+    ///
+    /// The mask is written in hexadecimal with a leading `0x`, as by the reference encoder.
+    pub fn channel_mask(&self) -> Option<u32> {
+        let value = self
+            .tags
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("WAVEFORMATEXTENSIBLE_CHANNEL_MASK"))
+            .map(|(_, value)| value.trim())?;
+        let digits = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))?;
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    /// The speaker position of each channel, as described by the channel mask if present or by
+    /// the FLAC default for the channel count otherwise.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        let channel_count = self.streaminfo.channels as usize;
+        match self.channel_mask() {
+            Some(mask) => ChannelLayout::from_channel_mask(mask, channel_count),
+            None => ChannelLayout::default_for(channel_count),
+        }
+    }
+
+    /// Reads the next interleaved sample, decoding the next frame if necessary.
+    ///
+    /// Samples are right-aligned, i.e. they lie within the range of `bits_per_sample`. Returns
+    /// `None` at the end of the stream or once decoding has failed.
+    pub fn next_sample(&mut self) -> Option<Result<i32, claxon::Error>> {
+        while self.index >= self.block.len() {
+            if self.failed {
+                return None;
+            }
+            match self.read_block() {
+                Ok(true) => (),
+                Ok(false) => return None,
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        let channels = self.block.channels;
+        let sample = self
+            .block
+            .sample(self.index % channels, self.index / channels);
+        self.index += 1;
+        Some(Ok(sample))
+    }
+
+    /// Reads interleaved samples into `buf`, converting each via `convert` and decoding blocks as
+    /// necessary.
+    ///
+    /// Returns the number of samples read, which is less than `buf.len()` only at the end of the
+    /// stream.
+    pub fn read_into<T, F>(&mut self, buf: &mut [T], mut convert: F) -> Result<usize, claxon::Error>
+    where
+        F: FnMut(i32) -> T,
+    {
+        let mut read = 0;
+        while read < buf.len() {
+            if self.index >= self.block.len() {
+                if self.failed {
+                    break;
+                }
+                match self.read_block() {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(err) => {
+                        self.failed = true;
+                        return Err(err);
+                    }
+                }
+            }
+            // Samples are stored by channel, so walk the frames rather than dividing per sample.
+            let channels = self.block.channels;
+            let (mut channel, mut frame) = (self.index % channels, self.index / channels);
+            let len = (self.block.len() - self.index).min((buf.len() - read) as u32);
+            for sample in &mut buf[read..read + len as usize] {
+                *sample = convert(self.block.sample(channel, frame));
+                channel += 1;
+                if channel == channels {
+                    channel = 0;
+                    frame += 1;
+                }
+            }
+            self.index += len;
+            read += len as usize;
+        }
+        Ok(read)
+    }
+
+    /// Positions the reader so that the next sample read is the first of the frame at `frame`.
+    ///
+    /// A frame at or before the target is located via the SEEKTABLE, or by bisection over frame
+    /// headers if the stream has none, before decoding forward to the target. Seeking beyond
+    /// the end of the stream positions the reader at the end.
+    pub fn seek(&mut self, frame: u64) -> Result<(), claxon::Error> {
+        self.failed = false;
+        self.block = Block::empty();
+        self.index = 0;
+        if frame >= self.streaminfo.samples.unwrap_or(u64::MAX) {
+            self.input.seek(SeekFrom::End(0))?;
+            return Ok(());
+        }
+
+        let offset = match self.seek_table.iter().rev().find(|p| p.sample <= frame) {
+            Some(point) => self.audio_offset + point.offset,
+            None => self.bisect(frame)?,
+        };
+        self.input.seek(SeekFrom::Start(offset))?;
+        while self.read_block()? {
+            let start = self.block.start;
+            if start + u64::from(self.block.block_size) > frame {
+                let skip = frame.saturating_sub(start) as u32;
+                self.index = skip * self.block.channels;
+                return Ok(());
+            }
+        }
+        self.block = Block::empty();
+        self.index = 0;
+        Ok(())
+    }
+
+    // Decodes the next frame into `block`, returning `false` at the end of the stream.
+    fn read_block(&mut self) -> Result<bool, claxon::Error> {
+        let buffer = std::mem::replace(&mut self.block, Block::empty()).buffer;
+        match read_frame(&mut self.input, &self.streaminfo, buffer)? {
+            Some(block) => {
+                self.block = block;
+                self.index = 0;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Finds the position of a frame starting at or before `frame` by bisecting the stream.
+    fn bisect(&mut self, frame: u64) -> Result<u64, claxon::Error> {
+        let mut lo = self.audio_offset;
+        let mut hi = self.input.seek(SeekFrom::End(0))?;
+        let min_range =
+            u64::from(self.streaminfo.max_frame_size.unwrap_or(0)).max(MIN_BISECT_RANGE);
+        while hi - lo > min_range {
+            let mid = lo + (hi - lo) / 2;
+            match self.next_frame_from(mid, hi)? {
+                Some((offset, start)) if start <= frame => lo = offset,
+                _ => hi = mid,
+            }
+        }
+        Ok(lo)
+    }
+
+    // Scans forward from `position` for the first valid frame beginning before `end`, returning
+    // its position and first sample number.
+    //
+    // Candidate sync codes are verified by decoding the whole frame, including its CRC-16.
+    fn next_frame_from(
+        &mut self,
+        mut position: u64,
+        end: u64,
+    ) -> Result<Option<(u64, u64)>, claxon::Error> {
+        let mut bytes = vec![0; SYNC_SCAN_LEN];
+        while position < end {
+            self.input.seek(SeekFrom::Start(position))?;
+            let len = (end - position).min(SYNC_SCAN_LEN as u64) as usize;
+            self.input.read_exact(&mut bytes[..len])?;
+            // The final byte may only be checked alongside the next chunk.
+            let candidates = (0..len.saturating_sub(1))
+                .filter(|&i| bytes[i] == 0xFF && bytes[i + 1] & 0xFE == 0xF8)
+                .map(|i| position + i as u64)
+                .collect::<Vec<_>>();
+            for candidate in candidates {
+                self.input.seek(SeekFrom::Start(candidate))?;
+                let buffer = std::mem::replace(&mut self.block, Block::empty()).buffer;
+                match read_frame(&mut self.input, &self.streaminfo, buffer) {
+                    Ok(Some(block)) => return Ok(Some((candidate, block.start))),
+                    Ok(None) => return Ok(None),
+                    Err(claxon::Error::IoError(err))
+                        if err.kind() != io::ErrorKind::UnexpectedEof =>
+                    {
+                        return Err(claxon::Error::IoError(err));
+                    }
+                    // A false sync code.
+                    Err(_) => (),
+                }
+            }
+            position += len.saturating_sub(1).max(1) as u64;
+        }
+        Ok(None)
+    }
+}
+
+impl Block {
+    fn empty() -> Self {
+        Block {
+            buffer: Vec::new(),
+            block_size: 0,
+            channels: 0,
+            start: 0,
+        }
+    }
+
+    // The number of samples within the block across every channel.
+    fn len(&self) -> u32 {
+        self.block_size * self.channels
+    }
+
+    fn sample(&self, channel: u32, frame: u32) -> i32 {
+        self.buffer[(channel * self.block_size + frame) as usize] as i32
+    }
+}
+
+// Reads the metadata blocks following the stream marker, returning the points of any SEEKTABLE
+// along with the contents of any PICTURE and CUESHEET blocks.
+//
+// The reader is left positioned at the first frame.
+fn read_metadata_blocks<R>(reader: &mut R) -> io::Result<MetadataBlocks>
+where
+    R: Read + Seek,
+{
+    // Placeholder points use the largest sample number.
+    const PLACEHOLDER: u64 = u64::MAX;
+    const SEEKTABLE: u8 = 3;
+    const CUESHEET: u8 = 5;
+    const PICTURE: u8 = 6;
+
+    let mut seek_table = Vec::new();
+    let mut pictures = Vec::new();
+    let mut cue_sheet = None;
+    reader.seek(SeekFrom::Current(4))?;
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+        if header[0] & 0x7F == SEEKTABLE {
+            let mut point = [0; 18];
+            for _ in 0..len / 18 {
+                reader.read_exact(&mut point)?;
+                let mut sample = [0; 8];
+                let mut offset = [0; 8];
+                sample.copy_from_slice(&point[0..8]);
+                offset.copy_from_slice(&point[8..16]);
+                let sample = u64::from_be_bytes(sample);
+                if sample != PLACEHOLDER {
+                    let offset = u64::from_be_bytes(offset);
+                    seek_table.push(SeekPoint { sample, offset });
+                }
+            }
+            reader.seek(SeekFrom::Current(i64::from(len % 18)))?;
+        } else if header[0] & 0x7F == PICTURE || header[0] & 0x7F == CUESHEET {
+            let mut block = vec![0; len as usize];
+            reader.read_exact(&mut block)?;
+            if header[0] & 0x7F == PICTURE {
+                pictures.extend(picture::read_flac_picture(&block));
+            } else {
+                cue_sheet = marker::read_flac_cue_sheet(&block);
+            }
+        } else {
+            reader.seek(SeekFrom::Current(i64::from(len)))?;
+        }
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    Ok(MetadataBlocks {
+        seek_table,
+        pictures,
+        cue_sheet,
+    })
+}
+
+/// A big-endian bit source over buffered input.
+///
+/// The contents of the input's buffer are taken a whole buffer at a time and kept so that the
+/// checksums of the frame may be verified. Any bytes taken beyond those read are returned to the
+/// input via `finish`.
+struct BitReader<'a, R> {
+    input: &'a mut io::BufReader<R>,
+    // The bytes taken from the input, of which the first `position` have been read.
+    bytes: Vec<u8>,
+    position: usize,
+    // The bits yet to be read are the `bits` least significant bits of `acc`.
+    acc: u64,
+    bits: u32,
+}
+
+impl<'a, R> BitReader<'a, R>
+where
+    R: Read,
+{
+    fn new(input: &'a mut io::BufReader<R>) -> Self {
+        BitReader {
+            input,
+            bytes: Vec::new(),
+            position: 0,
+            acc: 0,
+            bits: 0,
+        }
+    }
+
+    // The bytes read so far, excluding whole bytes still held within `acc`.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.position - (self.bits / 8) as usize]
+    }
+
+    // Loads at least one more byte into `acc`, taking the contents of the input's buffer if all
+    // bytes taken so far have been loaded.
+    fn refill(&mut self) -> io::Result<()> {
+        if self.position == self.bytes.len() {
+            let available = self.input.fill_buf()?;
+            if available.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let len = available.len();
+            self.bytes.extend_from_slice(available);
+            self.input.consume(len);
+        }
+        // Load four bytes at a time where possible, as long as `acc` has room for them.
+        if self.bits <= 24 && self.position + 4 <= self.bytes.len() {
+            let mut word = [0; 4];
+            word.copy_from_slice(&self.bytes[self.position..self.position + 4]);
+            self.acc = (self.acc << 32) | u64::from(u32::from_be_bytes(word));
+            self.bits += 32;
+            self.position += 4;
+        } else {
+            self.acc = (self.acc << 8) | u64::from(self.bytes[self.position]);
+            self.bits += 8;
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    // Reads an unsigned value of `bits` bits, where `bits` is at most 56.
+    fn read(&mut self, bits: u32) -> io::Result<u64> {
+        while self.bits < bits {
+            self.refill()?;
+        }
+        self.bits -= bits;
+        Ok((self.acc >> self.bits) & ((1 << bits) - 1))
+    }
+
+    // Reads a two's complement value of `bits` bits.
+    fn read_signed(&mut self, bits: u32) -> io::Result<i64> {
+        if bits == 0 {
+            return Ok(0);
+        }
+        let value = self.read(bits)?;
+        Ok(((value << (64 - bits)) as i64) >> (64 - bits))
+    }
+
+    // Counts the zero bits preceding the next one bit, consuming both.
+    fn read_unary(&mut self) -> io::Result<u64> {
+        let mut zeros = 0;
+        loop {
+            let remaining = self.acc & ((1 << self.bits) - 1);
+            if remaining != 0 {
+                let one = 63 - remaining.leading_zeros();
+                zeros += u64::from(self.bits - 1 - one);
+                self.bits = one;
+                return Ok(zeros);
+            }
+            zeros += u64::from(self.bits);
+            self.bits = 0;
+            self.refill()?;
+        }
+    }
+
+    // Skips the remaining bits of the current byte.
+    fn align(&mut self) {
+        self.bits -= self.bits % 8;
+    }
+
+    // Returns the bytes taken beyond those read to the input, leaving it positioned after the
+    // last byte read.
+    fn finish(self) -> io::Result<()>
+    where
+        R: Seek,
+    {
+        let unread = self.bytes.len() - self.position + (self.bits / 8) as usize;
+        self.input.seek_relative(-(unread as i64))
+    }
+}
+
+// Decodes the frame at the current position of `input`, reusing `buffer` for its samples.
+// Returns `None` at the end of the stream.
+//
+// Both the CRC-8 of the frame header and the CRC-16 of the whole frame are verified.
+fn read_frame<R>(
+    input: &mut io::BufReader<R>,
+    streaminfo: &StreamInfo,
+    mut buffer: Vec<i64>,
+) -> Result<Option<Block>, claxon::Error>
+where
+    R: Read + Seek,
+{
+    if input.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut bits = BitReader::new(input);
+
+    // The sync code followed by a reserved bit and the block size strategy bit.
+    let sync = bits.read(16)?;
+    if sync & 0xFFFE != 0xFFF8 {
+        return format_error("invalid frame sync code");
+    }
+    let variable_block_size = sync & 1 == 1;
+
+    let block_size_code = bits.read(4)?;
+    let sample_rate_code = bits.read(4)?;
+    let assignment = bits.read(4)?;
+    let bits_per_sample = match bits.read(3)? {
+        0b000 => streaminfo.bits_per_sample,
+        0b001 => 8,
+        0b010 => 12,
+        0b100 => 16,
+        0b101 => 20,
+        0b110 => 24,
+        0b111 => 32,
+        _ => return format_error("invalid bits per sample"),
+    };
+    if bits.read(1)? != 0 {
+        return format_error("invalid frame header");
+    }
+    let number = read_utf8(&mut bits)?;
+
+    let block_size = match block_size_code {
+        0b0001 => 192,
+        code @ 0b0010..=0b0101 => 576 << (code - 2),
+        0b0110 => bits.read(8)? + 1,
+        0b0111 => bits.read(16)? + 1,
+        code @ 0b1000..=0b1111 => 256 << (code - 8),
+        _ => return format_error("invalid block size"),
+    } as u32;
+    // The sample rate is always taken from STREAMINFO.
+    match sample_rate_code {
+        0b1100 => bits.read(8).map(drop)?,
+        0b1101 | 0b1110 => bits.read(16).map(drop)?,
+        0b1111 => return format_error("invalid sample rate"),
+        _ => (),
+    }
+    let crc = crc8(bits.bytes());
+    if bits.read(8)? != u64::from(crc) {
+        return format_error("frame header CRC mismatch");
+    }
+
+    let channels = match assignment {
+        0b0000..=0b0111 => assignment as u32 + 1,
+        0b1000..=0b1010 => 2,
+        _ => return format_error("invalid channel assignment"),
+    };
+    // The side channel of decorrelated stereo requires an extra bit.
+    let side_channel = match assignment {
+        0b1000 | 0b1010 => Some(1),
+        0b1001 => Some(0),
+        _ => None,
+    };
+    buffer.clear();
+    buffer.resize((block_size * channels) as usize, 0);
+    for (channel, samples) in buffer.chunks_mut(block_size as usize).enumerate() {
+        let bps = if side_channel == Some(channel) {
+            bits_per_sample + 1
+        } else {
+            bits_per_sample
+        };
+        read_subframe(&mut bits, bps, samples)?;
+    }
+
+    let (first, second) = buffer.split_at_mut(block_size as usize);
+    match assignment {
+        // Left and side.
+        0b1000 => {
+            for (&left, side) in first.iter().zip(second) {
+                *side = left - *side;
+            }
+        }
+        // Side and right.
+        0b1001 => {
+            for (side, &right) in first.iter_mut().zip(&*second) {
+                *side += right;
+            }
+        }
+        // Mid and side.
+        0b1010 => {
+            for (mid, side) in first.iter_mut().zip(second) {
+                let sum = (*mid << 1) | (*side & 1);
+                *mid = (sum + *side) >> 1;
+                *side = (sum - *side) >> 1;
+            }
+        }
+        _ => (),
+    }
+
+    bits.align();
+    let crc = crc16(bits.bytes());
+    if bits.read(16)? != u64::from(crc) {
+        return format_error("frame CRC mismatch");
+    }
+    bits.finish()?;
+
+    let start = if variable_block_size {
+        number
+    } else {
+        number * u64::from(streaminfo.max_block_size)
+    };
+    Ok(Some(Block {
+        buffer,
+        block_size,
+        channels,
+        start,
+    }))
+}
+
+// Reads a frame or sample number in the UTF-8-like variable length coding.
+fn read_utf8<R>(bits: &mut BitReader<'_, R>) -> Result<u64, claxon::Error>
+where
+    R: Read,
+{
+    let first = bits.read(8)?;
+    let leading_ones = (first as u8).leading_ones();
+    let continuation_bytes = match leading_ones {
+        0 => 0,
+        2..=7 => leading_ones - 1,
+        _ => return format_error("invalid frame number"),
+    };
+    let mut value = first & (0xFF >> (leading_ones + 1));
+    for _ in 0..continuation_bytes {
+        let byte = bits.read(8)?;
+        if byte & 0xC0 != 0x80 {
+            return format_error("invalid frame number");
+        }
+        value = (value << 6) | (byte & 0x3F);
+    }
+    Ok(value)
+}
+
+// Decodes a subframe of `bps` bits per sample into `samples`.
+fn read_subframe<R>(
+    bits: &mut BitReader<'_, R>,
+    bps: u32,
+    samples: &mut [i64],
+) -> Result<(), claxon::Error>
+where
+    R: Read,
+{
+    if bits.read(1)? != 0 {
+        return format_error("invalid subframe header");
+    }
+    let subframe_type = bits.read(6)?;
+    let wasted = if bits.read(1)? == 1 {
+        bits.read_unary()? + 1
+    } else {
+        0
+    };
+    if wasted >= u64::from(bps) {
+        return format_error("invalid number of wasted bits");
+    }
+    let wasted = wasted as u32;
+    let bps = bps - wasted;
+
+    match subframe_type {
+        0b000000 => {
+            let value = bits.read_signed(bps)?;
+            samples.iter_mut().for_each(|sample| *sample = value);
+        }
+        0b000001 => {
+            for sample in samples.iter_mut() {
+                *sample = bits.read_signed(bps)?;
+            }
+        }
+        0b001000..=0b001100 => {
+            let order = (subframe_type & 0b111) as usize;
+            read_warm_up(bits, bps, order, samples)?;
+            read_residual(bits, order, samples)?;
+            restore_fixed(order, samples);
+        }
+        0b100000..=0b111111 => {
+            let order = (subframe_type & 0b11111) as usize + 1;
+            read_warm_up(bits, bps, order, samples)?;
+            let precision = match bits.read(4)? {
+                0b1111 => return format_error("invalid LPC coefficient precision"),
+                precision => precision as u32 + 1,
+            };
+            let shift = bits.read_signed(5)?;
+            if shift < 0 {
+                return format_error("negative LPC shift");
+            }
+            let mut coefficients = Vec::with_capacity(order);
+            for _ in 0..order {
+                coefficients.push(bits.read_signed(precision)?);
+            }
+            read_residual(bits, order, samples)?;
+            restore_lpc(&coefficients, shift as u32, samples);
+        }
+        _ => return format_error("reserved subframe type"),
+    }
+
+    if wasted > 0 {
+        samples.iter_mut().for_each(|sample| *sample <<= wasted);
+    }
+    Ok(())
+}
+
+// Reads the `order` unencoded samples preceding the residual of a predicted subframe.
+fn read_warm_up<R>(
+    bits: &mut BitReader<'_, R>,
+    bps: u32,
+    order: usize,
+    samples: &mut [i64],
+) -> Result<(), claxon::Error>
+where
+    R: Read,
+{
+    if order > samples.len() {
+        return format_error("predictor order exceeds block size");
+    }
+    for sample in &mut samples[..order] {
+        *sample = bits.read_signed(bps)?;
+    }
+    Ok(())
+}
+
+// Decodes the partitioned rice coded residual following `order` warm-up samples into the
+// remainder of `samples`.
+fn read_residual<R>(
+    bits: &mut BitReader<'_, R>,
+    order: usize,
+    samples: &mut [i64],
+) -> Result<(), claxon::Error>
+where
+    R: Read,
+{
+    let (param_bits, escape) = match bits.read(2)? {
+        0b00 => (4, 0b1111),
+        0b01 => (5, 0b11111),
+        _ => return format_error("reserved residual coding method"),
+    };
+    let partition_order = bits.read(4)? as u32;
+    let partition_len = samples.len() >> partition_order;
+    if partition_len << partition_order != samples.len() || partition_len < order {
+        return format_error("invalid rice partition order");
+    }
+
+    let mut start = order;
+    for partition in 0..1 << partition_order {
+        let end = (partition + 1) * partition_len;
+        let param = bits.read(param_bits)?;
+        if param == escape {
+            let raw_bits = bits.read(5)? as u32;
+            for sample in &mut samples[start..end] {
+                *sample = bits.read_signed(raw_bits)?;
+            }
+        } else {
+            let param = param as u32;
+            for sample in &mut samples[start..end] {
+                let folded = (bits.read_unary()? << param) | bits.read(param)?;
+                *sample = (folded >> 1) as i64 ^ -((folded & 1) as i64);
+            }
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+// Adds the prediction of the fixed predictor of the given order to each residual.
+fn restore_fixed(order: usize, samples: &mut [i64]) {
+    const COEFFICIENTS: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+    restore_lpc(COEFFICIENTS[order], 0, samples);
+}
+
+// Adds the prediction of the given quantized LPC coefficients to each residual.
+//
+// Wrapping arithmetic is used so that corrupt streams cannot cause a panic.
+fn restore_lpc(coefficients: &[i64], shift: u32, samples: &mut [i64]) {
+    let order = coefficients.len();
+    // Reversed so that each coefficient lines up with the preceding sample to which it applies.
+    let coefficients: Vec<i64> = coefficients.iter().rev().cloned().collect();
+    for i in order..samples.len() {
+        let prediction = coefficients
+            .iter()
+            .zip(&samples[i - order..i])
+            .fold(0i64, |sum, (&c, &s)| sum.wrapping_add(c.wrapping_mul(s)));
+        samples[i] = samples[i].wrapping_add(prediction >> shift);
+    }
+}
+
+fn format_error<T>(message: &'static str) -> Result<T, claxon::Error> {
+    Err(claxon::Error::FormatError(message))
+}
+
+/// The smallest block size permitted by STREAMINFO.
+const MIN_BLOCK_SIZE: u16 = 16;
+
+/// The maximum number of rice partitions is `2^MAX_PARTITION_ORDER`.
+const MAX_PARTITION_ORDER: u32 = 15;
+
+/// The largest LPC coefficient shift that may be encoded. Negative shifts are never produced.
+const MAX_QLP_SHIFT: i32 = 15;
+
+/// The largest rice parameters of the 4-bit and 5-bit partitioned rice coding methods. The
+/// all-ones escape codes are never produced.
+const MAX_RICE_PARAM: u32 = 14;
+const MAX_RICE2_PARAM: u32 = 30;
+
+/// Encoder parameters associated with each compression level.
+#[derive(Copy, Clone, Debug)]
+struct Level {
+    block_size: u16,
+    // Whether to try left/side, right/side and mid/side channel assignments for stereo.
+    stereo_decorrelation: bool,
+    // LPC subframes are only tried when this is greater than zero.
+    max_lpc_order: usize,
+    max_partition_order: u32,
+    // Whether to try quantizing LPC coefficients with a range of precisions.
+    qlp_precision_search: bool,
+}
+
+/// Compression levels `0` through `8`, modelled after those of the reference encoder.
+const LEVELS: [Level; 9] = [
+    Level::new(1152, false, 0, 3, false),
+    Level::new(1152, true, 0, 3, false),
+    Level::new(1152, true, 0, 4, false),
+    Level::new(4096, false, 6, 4, false),
+    Level::new(4096, true, 8, 4, false),
+    Level::new(4096, true, 8, 5, false),
+    Level::new(4096, true, 8, 6, false),
+    Level::new(4096, true, 12, 6, true),
+    Level::new(4096, true, 12, 8, true),
+];
+
+impl Level {
+    const fn new(
+        block_size: u16,
+        stereo_decorrelation: bool,
+        max_lpc_order: usize,
+        max_partition_order: u32,
+        qlp_precision_search: bool,
+    ) -> Self {
+        Level {
+            block_size,
+            stereo_decorrelation,
+            max_lpc_order,
+            max_partition_order,
+            qlp_precision_search,
+        }
+    }
+}
+
+/// Encodes interleaved integer samples to a FLAC stream.
+pub struct FlacWriter<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    spec: Spec,
+    level: Level,
+    block_size: usize,
+    bits_per_sample: u32,
+    // The number of least significant bits discarded from each sample to reach `bits_per_sample`.
+    shift: u32,
+    // Position of the STREAMINFO block within the writer, rewritten on `flush` and `finalize`.
+    streaminfo_position: u64,
+    metadata: Metadata,
+    sampler_info: Option<SamplerInfo>,
+    // Whether or not the metadata blocks following STREAMINFO have been written.
+    header_complete: bool,
+    // Interleaved samples of the block that is currently being filled.
+    buffer: Vec<i32>,
+    frame_number: u64,
+    frame_count: u64,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    md5: md5::Context,
+    window: Vec<f64>,
+    finalized: bool,
+}
+
+impl<W> FlacWriter<W>
+where
+    W: Write + Seek,
+{
+    /// Writes the stream marker and a provisional STREAMINFO block.
+    ///
+    /// The `spec` and the `bits_per_sample` of the `options` are expected to have been validated
+    /// via `FlacWriter::supports`.
+    pub(crate) fn new(mut writer: W, spec: Spec, options: FlacOptions) -> io::Result<Self> {
+        let level = LEVELS[usize::from(options.compression_level).min(LEVELS.len() - 1)];
+        let block_size = options
+            .block_size
+            .unwrap_or(level.block_size)
+            .max(MIN_BLOCK_SIZE);
+        let container_bits = spec.sample_format.bits_per_sample();
+        let bits_per_sample = options.bits_per_sample.unwrap_or(container_bits);
+
+        writer.write_all(b"fLaC")?;
+        // The STREAMINFO block header: the "last block" flag, type 0 and a length of 34 bytes.
+        writer.write_all(&[0x80, 0x00, 0x00, 34])?;
+        let streaminfo_position = writer.stream_position()?;
+
+        let mut flac = FlacWriter {
+            writer,
+            spec,
+            level,
+            block_size: usize::from(block_size),
+            bits_per_sample,
+            shift: container_bits - bits_per_sample,
+            streaminfo_position,
+            metadata: Metadata::new(),
+            sampler_info: None,
+            header_complete: false,
+            buffer: Vec::with_capacity(usize::from(block_size) * spec.channel_count as usize),
+            frame_number: 0,
+            frame_count: 0,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            md5: md5::Context::new(),
+            window: Vec::new(),
+            finalized: false,
+        };
+        let streaminfo = flac.streaminfo();
+        flac.writer.write_all(&streaminfo)?;
+        Ok(flac)
+    }
+
+    /// Whether or not the encoder can represent audio of the given `spec` at the given
+    /// `bits_per_sample`, which may be no less than 4 and no more than the bits of the spec's
+    /// sample format.
+    pub fn supports(spec: &Spec, bits_per_sample: Option<u32>) -> bool {
+        let sample_format = match spec.sample_format {
+            SampleFormat::I8 | SampleFormat::I16 | SampleFormat::I24 | SampleFormat::I32 => true,
+            SampleFormat::F32 | SampleFormat::F64 | SampleFormat::LossyFloat => false,
+        };
+        let container_bits = spec.sample_format.bits_per_sample();
+        let bits_per_sample = match bits_per_sample {
+            Some(bits) => (4..=container_bits).contains(&bits),
+            None => true,
+        };
+        sample_format
+            && bits_per_sample
+            && (1..=8).contains(&spec.channel_count)
+            && (1..=655_350).contains(&spec.sample_rate)
+    }
+
+    /// A description of the audio being written.
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// Sets the tags that are written in a VORBIS_COMMENT block following STREAMINFO.
+    ///
+    /// This is expected to be called before any samples are written, see
+    /// `FlacWriter::has_started`.
+    pub fn set_metadata(&mut self, metadata: &Metadata) {
+        self.metadata = metadata.clone();
+    }
+
+    /// Sets the loop that is written as `LOOPSTART` and `LOOPLENGTH` Vorbis comments, replacing
+    /// any such comments of the tags.
+    ///
+    /// Only the first loop is written, while the pitch cannot be represented. This is expected to
+    /// be called before any samples are written, see `FlacWriter::has_started`.
+    pub fn set_sampler_info(&mut self, sampler_info: &SamplerInfo) {
+        self.sampler_info = Some(sampler_info.clone());
+    }
+
+    /// Whether or not any samples have been written or the writer has been flushed, after which
+    /// the metadata blocks can no longer be changed.
+    pub fn has_started(&self) -> bool {
+        self.header_complete || !self.buffer.is_empty()
+    }
+
+    /// Buffers the given sample, encoding a frame each time a block has been filled.
+    ///
+    /// The sample is expected to be within the range of the spec's sample format, and is reduced
+    /// to the encoded `bits_per_sample` by discarding its least significant bits.
+    pub fn write_sample(&mut self, sample: i32) -> io::Result<()> {
+        self.buffer.push(sample >> self.shift);
+        if self.buffer.len() == self.block_size * self.spec.channel_count as usize {
+            self.write_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Updates STREAMINFO to reflect the frames written so far and flushes the inner writer.
+    ///
+    /// Samples of an incomplete block remain buffered, as only the final frame of the stream may
+    /// be shorter than the block size.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.complete_header()?;
+        self.rewrite_streaminfo()?;
+        self.writer.flush()
+    }
+
+    /// Encodes any remaining samples as the final frame and completes STREAMINFO.
+    pub fn finalize(&mut self) -> io::Result<()> {
+        if self.finalized {
+            return Ok(());
+        }
+        self.finalized = true;
+        // Only whole frames can be encoded, so discard any trailing partial frame.
+        let channels = self.spec.channel_count as usize;
+        let len = self.buffer.len() / channels * channels;
+        self.buffer.truncate(len);
+        if !self.buffer.is_empty() {
+            self.write_frame()?;
+        }
+        self.flush()
+    }
+
+    // Writes the metadata blocks following STREAMINFO if they have not yet been written.
+    fn complete_header(&mut self) -> io::Result<()> {
+        if self.header_complete {
+            return Ok(());
+        }
+        self.header_complete = true;
+        let mut metadata = self.metadata.clone();
+        if let Some(ref sampler_info) = self.sampler_info {
+            sampler::write_vorbis_comments(sampler_info, &mut metadata);
+        }
+        if metadata.is_empty() {
+            return Ok(());
+        }
+        // The VORBIS_COMMENT block becomes the last block in place of STREAMINFO.
+        let block = metadata::vorbis_comment(&metadata);
+        self.writer.write_all(&[0x84])?;
+        self.writer
+            .write_all(&(block.len() as u32).to_be_bytes()[1..])?;
+        self.writer.write_all(&block)?;
+        let end = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.streaminfo_position - 4))?;
+        self.writer.write_all(&[0x00])?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    // Writes STREAMINFO at its original position before returning to the end of the stream.
+    fn rewrite_streaminfo(&mut self) -> io::Result<()> {
+        let end = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.streaminfo_position))?;
+        let streaminfo = self.streaminfo();
+        self.writer.write_all(&streaminfo)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    // The 34 bytes of the STREAMINFO metadata block.
+    fn streaminfo(&self) -> Vec<u8> {
+        let mut out = BitWriter::new();
+        let block_size = self.block_size as u64;
+        out.write(block_size, 16);
+        out.write(block_size, 16);
+        out.write(u64::from(self.min_frame_size), 24);
+        out.write(u64::from(self.max_frame_size), 24);
+        out.write(u64::from(self.spec.sample_rate), 20);
+        out.write(u64::from(self.spec.channel_count - 1), 3);
+        out.write(u64::from(self.bits_per_sample - 1), 5);
+        // A total of 0 marks the number of samples as unknown.
+        let total = if self.frame_count < 1 << 36 {
+            self.frame_count
+        } else {
+            0
+        };
+        out.write(total >> 32, 4);
+        out.write(total & 0xFFFF_FFFF, 32);
+        let mut bytes = out.into_bytes();
+        bytes.extend_from_slice(&self.md5.clone().compute().0);
+        bytes
+    }
+
+    // Encodes the buffered samples as a single frame.
+    fn write_frame(&mut self) -> io::Result<()> {
+        self.complete_header()?;
+        let channels = self.spec.channel_count as usize;
+        let block_size = self.buffer.len() / channels;
+        let bps = self.bits_per_sample;
+
+        // The MD5 signature covers the interleaved samples in little-endian byte order.
+        let bytes_per_sample = (bps as usize + 7) >> 3;
+        let mut bytes = Vec::with_capacity(self.buffer.len() * bytes_per_sample);
+        for &sample in &self.buffer {
+            bytes.extend_from_slice(&sample.to_le_bytes()[..bytes_per_sample]);
+        }
+        self.md5.consume(&bytes);
+
+        let mut channel_samples = vec![Vec::with_capacity(block_size); channels];
+        for frame in self.buffer.chunks(channels) {
+            for (samples, &sample) in channel_samples.iter_mut().zip(frame) {
+                samples.push(i64::from(sample));
+            }
+        }
+        self.buffer.clear();
+
+        if self.level.max_lpc_order > 0 && self.window.len() != block_size {
+            self.window = tukey_window(block_size, 0.5);
+        }
+
+        let (assignment, subframes) = if channels == 2 && self.level.stereo_decorrelation {
+            let (left, right) = (&channel_samples[0], &channel_samples[1]);
+            let mid: Vec<i64> = left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+            let side: Vec<i64> = left.iter().zip(right).map(|(l, r)| l - r).collect();
+            let left = self.encode_subframe(left, bps);
+            let right = self.encode_subframe(right, bps);
+            let mid = self.encode_subframe(&mid, bps);
+            let side = self.encode_subframe(&side, bps + 1);
+            let candidates = vec![
+                (0b0001, left.len() + right.len()),
+                (0b1000, left.len() + side.len()),
+                (0b1001, side.len() + right.len()),
+                (0b1010, mid.len() + side.len()),
+            ];
+            let (assignment, _) = candidates
+                .into_iter()
+                .min_by_key(|&(_, len)| len)
+                .expect("no channel assignment");
+            let subframes = match assignment {
+                0b0001 => vec![left, right],
+                0b1000 => vec![left, side],
+                0b1001 => vec![side, right],
+                _ => vec![mid, side],
+            };
+            (assignment, subframes)
+        } else {
+            let subframes = channel_samples
+                .iter()
+                .map(|samples| self.encode_subframe(samples, bps))
+                .collect();
+            (channels as u64 - 1, subframes)
+        };
+
+        let mut frame = self.frame_header(block_size, assignment);
+        for subframe in &subframes {
+            frame.append(subframe);
+        }
+        frame.pad_to_byte();
+        let mut bytes = frame.into_bytes();
+        let crc = crc16(&bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        self.writer.write_all(&bytes)?;
+
+        let frame_size = bytes.len() as u32;
+        if self.frame_number == 0 || frame_size < self.min_frame_size {
+            self.min_frame_size = frame_size;
+        }
+        self.max_frame_size = self.max_frame_size.max(frame_size);
+        self.frame_number += 1;
+        self.frame_count += block_size as u64;
+        Ok(())
+    }
+
+    // The frame header, including its trailing CRC-8.
+    fn frame_header(&self, block_size: usize, assignment: u64) -> BitWriter {
+        let mut out = BitWriter::new();
+        // The sync code followed by a reserved bit and the fixed block size strategy bit.
+        out.write(0b1111_1111_1111_1000, 16);
+
+        let block_size_code = match block_size {
+            192 => 0b0001,
+            576 => 0b0010,
+            1152 => 0b0011,
+            2304 => 0b0100,
+            4608 => 0b0101,
+            256 => 0b1000,
+            512 => 0b1001,
+            1024 => 0b1010,
+            2048 => 0b1011,
+            4096 => 0b1100,
+            8192 => 0b1101,
+            16384 => 0b1110,
+            32768 => 0b1111,
+            n if n <= 256 => 0b0110,
+            _ => 0b0111,
+        };
+        out.write(block_size_code, 4);
+
+        let sample_rate = self.spec.sample_rate;
+        let sample_rate_code = match sample_rate {
+            88_200 => 0b0001,
+            176_400 => 0b0010,
+            192_000 => 0b0011,
+            8_000 => 0b0100,
+            16_000 => 0b0101,
+            22_050 => 0b0110,
+            24_000 => 0b0111,
+            32_000 => 0b1000,
+            44_100 => 0b1001,
+            48_000 => 0b1010,
+            96_000 => 0b1011,
+            rate if rate <= 0xFFFF => 0b1101,
+            rate if rate % 10 == 0 && rate / 10 <= 0xFFFF => 0b1110,
+            // Otherwise the sample rate is read from STREAMINFO.
+            _ => 0b0000,
+        };
+        out.write(sample_rate_code, 4);
+
+        out.write(assignment, 4);
+        let bits_per_sample_code = match self.bits_per_sample {
+            8 => 0b001,
+            12 => 0b010,
+            16 => 0b100,
+            20 => 0b101,
+            24 => 0b110,
+            32 => 0b111,
+            // Otherwise the bits per sample are read from STREAMINFO.
+            _ => 0b000,
+        };
+        out.write(bits_per_sample_code, 3);
+        out.write(0, 1);
+        write_utf8(&mut out, self.frame_number);
+
+        match block_size_code {
+            0b0110 => out.write(block_size as u64 - 1, 8),
+            0b0111 => out.write(block_size as u64 - 1, 16),
+            _ => (),
+        }
+        match sample_rate_code {
+            0b1101 => out.write(u64::from(sample_rate), 16),
+            0b1110 => out.write(u64::from(sample_rate / 10), 16),
+            _ => (),
+        }
+
+        let crc = crc8(out.bytes());
+        out.write(u64::from(crc), 8);
+        out
+    }
+
+    // Encodes the given samples of a single channel using the smallest of the available subframe
+    // types.
+    fn encode_subframe(&self, samples: &[i64], bps: u32) -> BitWriter {
+        let mut out = BitWriter::new();
+        let first = samples[0];
+        if samples.iter().all(|&s| s == first) {
+            write_subframe_header(&mut out, 0b000000, 0);
+            out.write_signed(first, bps);
+            return out;
+        }
+
+        // Samples that share trailing zero bits may be stored with a reduced bit depth.
+        let wasted = samples.iter().fold(0, |acc, &s| acc | s).trailing_zeros();
+        let shifted;
+        let samples = if wasted > 0 {
+            shifted = samples.iter().map(|&s| s >> wasted).collect::<Vec<_>>();
+            &shifted[..]
+        } else {
+            samples
+        };
+        let bps = bps - wasted;
+        let n = samples.len();
+
+        let mut best = Model::Verbatim;
+        let mut best_bits = n as u64 * u64::from(bps);
+
+        for order in 0..=4.min(n - 1) {
+            let residual = fixed_residual(samples, order);
+            if let Some(rice) = self.rice_plan(&residual, n, order) {
+                let bits = order as u64 * u64::from(bps) + rice.bits;
+                if bits < best_bits {
+                    best = Model::Fixed { order, rice };
+                    best_bits = bits;
+                }
+            }
+        }
+
+        let max_lpc_order = self.level.max_lpc_order.min(n - 1);
+        for (coefficients, precision, shift) in self.lpc_models(samples, bps, max_lpc_order) {
+            let order = coefficients.len();
+            let residual = lpc_residual(samples, &coefficients, shift);
+            if let Some(rice) = self.rice_plan(&residual, n, order) {
+                let bits = order as u64 * u64::from(bps + precision) + 4 + 5 + rice.bits;
+                if bits < best_bits {
+                    best = Model::Lpc {
+                        coefficients,
+                        precision,
+                        shift,
+                        rice,
+                    };
+                    best_bits = bits;
+                }
+            }
+        }
+
+        match best {
+            Model::Verbatim => {
+                write_subframe_header(&mut out, 0b000001, wasted);
+                for &sample in samples {
+                    out.write_signed(sample, bps);
+                }
+            }
+            Model::Fixed { order, rice } => {
+                write_subframe_header(&mut out, 0b001000 | order as u64, wasted);
+                for &sample in &samples[..order] {
+                    out.write_signed(sample, bps);
+                }
+                write_residual(&mut out, &fixed_residual(samples, order), order, &rice);
+            }
+            Model::Lpc {
+                coefficients,
+                precision,
+                shift,
+                rice,
+            } => {
+                let order = coefficients.len();
+                write_subframe_header(&mut out, 0b100000 | (order as u64 - 1), wasted);
+                for &sample in &samples[..order] {
+                    out.write_signed(sample, bps);
+                }
+                out.write(u64::from(precision - 1), 4);
+                out.write(shift as u64, 5);
+                for &coefficient in &coefficients {
+                    out.write_signed(coefficient, precision);
+                }
+                let residual = lpc_residual(samples, &coefficients, shift);
+                write_residual(&mut out, &residual, order, &rice);
+            }
+        }
+        out
+    }
+
+    // Produces the quantized coefficients, precision and shift of each LPC model worth trying.
+    fn lpc_models(&self, samples: &[i64], bps: u32, max_order: usize) -> Vec<(Vec<i64>, u32, i32)> {
+        if max_order == 0 {
+            return Vec::new();
+        }
+        let windowed: Vec<f64> = samples
+            .iter()
+            .zip(&self.window)
+            .map(|(&s, &w)| s as f64 * w)
+            .collect();
+        let autocorrelation: Vec<f64> = (0..=max_order)
+            .map(|lag| {
+                windowed[lag..]
+                    .iter()
+                    .zip(&windowed)
+                    .map(|(a, b)| a * b)
+                    .sum()
+            })
+            .collect();
+        if autocorrelation[0] == 0.0 {
+            return Vec::new();
+        }
+
+        let precision = qlp_precision(bps, samples.len());
+        let precisions = if self.level.qlp_precision_search {
+            (precision.saturating_sub(2).max(5)..=(precision + 2).min(15)).collect()
+        } else {
+            vec![precision]
+        };
+        let mut models = Vec::new();
+        for coefficients in levinson_durbin(&autocorrelation) {
+            for &precision in &precisions {
+                if let Some((quantized, shift)) = quantize_coefficients(&coefficients, precision) {
+                    models.push((quantized, precision, shift));
+                }
+            }
+        }
+        models
+    }
+
+    // Chooses the partition order and rice parameters that minimise the encoded size of the
+    // residual of a block of `n` samples, returning `None` if the residual cannot be represented.
+    fn rice_plan(&self, residual: &[i64], n: usize, order: usize) -> Option<RicePlan> {
+        let mut folded = Vec::with_capacity(residual.len());
+        for &r in residual {
+            if r < i64::from(i32::MIN) || r > i64::from(i32::MAX) {
+                return None;
+            }
+            folded.push(((r << 1) ^ (r >> 63)) as u64);
+        }
+
+        // The largest partition order for which every partition is at least as long as the
+        // warm-up and the block size divides evenly.
+        let mut max_order = self.level.max_partition_order.min(MAX_PARTITION_ORDER);
+        while max_order > 0 && (n & ((1 << max_order) - 1) != 0 || (n >> max_order) < order) {
+            max_order -= 1;
+        }
+
+        // Sum the folded residuals of the finest partitions, merging pairs for coarser orders.
+        let partition_len = n >> max_order;
+        let mut sums = Vec::with_capacity(1 << max_order);
+        let mut start = 0;
+        for p in 0..1 << max_order {
+            let len = if p == 0 {
+                partition_len - order
+            } else {
+                partition_len
+            };
+            sums.push(folded[start..start + len].iter().sum::<u64>());
+            start += len;
+        }
+
+        let mut best: Option<RicePlan> = None;
+        let mut partition_order = max_order;
+        loop {
+            let partition_len = n >> partition_order;
+            let mut params = Vec::with_capacity(sums.len());
+            let mut bits = 0;
+            for (p, &sum) in sums.iter().enumerate() {
+                let len = if p == 0 {
+                    partition_len - order
+                } else {
+                    partition_len
+                } as u64;
+                let (param, param_bits) = rice_parameter(sum, len);
+                params.push(param);
+                bits += param_bits;
+            }
+            let method = if params.iter().any(|&k| k > MAX_RICE_PARAM) {
+                1
+            } else {
+                0
+            };
+            bits += 2 + 4 + sums.len() as u64 * if method == 0 { 4 } else { 5 };
+            let smaller = match best {
+                Some(ref best) => bits < best.bits,
+                None => true,
+            };
+            if smaller {
+                best = Some(RicePlan {
+                    method,
+                    partition_order,
+                    params,
+                    bits,
+                });
+            }
+            if partition_order == 0 {
+                break;
+            }
+            partition_order -= 1;
+            sums = sums.chunks(2).map(|pair| pair[0] + pair[1]).collect();
+        }
+        best
+    }
+}
+
+impl<W> Drop for FlacWriter<W>
+where
+    W: Write + Seek,
+{
+    fn drop(&mut self) {
+        // Errors cannot be reported from `drop`, so they are ignored.
+        let _ = self.finalize();
+    }
+}
+
+/// The prediction model chosen for a subframe.
+enum Model {
+    Verbatim,
+    Fixed {
+        order: usize,
+        rice: RicePlan,
+    },
+    Lpc {
+        coefficients: Vec<i64>,
+        precision: u32,
+        shift: i32,
+        rice: RicePlan,
+    },
+}
+
+/// The partitioning and parameters with which a residual is rice coded.
+struct RicePlan {
+    method: u32,
+    partition_order: u32,
+    params: Vec<u32>,
+    // The estimated size of the coded residual in bits.
+    bits: u64,
+}
+
+// Writes `zeros` zero bits followed by a single one bit.
+fn write_unary(out: &mut BitWriter, mut zeros: u64) {
+    while zeros >= 32 {
+        out.write(0, 32);
+        zeros -= 32;
+    }
+    out.write(1, zeros as u32 + 1);
+}
+
+// Writes `value` using the UTF-8-like variable length coding of frame numbers.
+fn write_utf8(out: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        out.write(value, 8);
+        return;
+    }
+    let continuation_bytes = match value {
+        v if v < 0x800 => 1,
+        v if v < 0x1_0000 => 2,
+        v if v < 0x20_0000 => 3,
+        v if v < 0x400_0000 => 4,
+        _ => 5,
+    };
+    let prefix = !(0xFFu64 >> (continuation_bytes + 1)) & 0xFF;
+    out.write(prefix | (value >> (6 * continuation_bytes)), 8);
+    for i in (0..continuation_bytes).rev() {
+        out.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+    }
+}
+
+fn write_subframe_header(out: &mut BitWriter, subframe_type: u64, wasted: u32) {
+    out.write(0, 1);
+    out.write(subframe_type, 6);
+    if wasted > 0 {
+        out.write(1, 1);
+        write_unary(out, u64::from(wasted) - 1);
+    } else {
+        out.write(0, 1);
+    }
+}
+
+// Writes the residual following `order` warm-up samples.
+fn write_residual(out: &mut BitWriter, residual: &[i64], order: usize, rice: &RicePlan) {
+    out.write(u64::from(rice.method), 2);
+    out.write(u64::from(rice.partition_order), 4);
+    let param_bits = if rice.method == 0 { 4 } else { 5 };
+    let partition_len = (residual.len() + order) >> rice.partition_order;
+    let mut start = 0;
+    for (p, &param) in rice.params.iter().enumerate() {
+        let len = if p == 0 {
+            partition_len - order
+        } else {
+            partition_len
+        };
+        out.write(u64::from(param), param_bits);
+        for &r in &residual[start..start + len] {
+            let folded = ((r << 1) ^ (r >> 63)) as u64;
+            write_unary(out, folded >> param);
+            out.write(folded, param);
+        }
+        start += len;
+    }
+}
+
+// The rice parameter that minimises the estimated size of `len` folded residuals summing to
+// `sum`, along with that estimated size in bits.
+fn rice_parameter(sum: u64, len: u64) -> (u32, u64) {
+    if len == 0 {
+        return (0, 0);
+    }
+    let mean = sum / len;
+    let estimate = 64 - mean.leading_zeros();
+    let bits = |k: u32| len * u64::from(k + 1) + (sum >> k);
+    (estimate.saturating_sub(1)..=estimate + 1)
+        .map(|k| k.min(MAX_RICE2_PARAM))
+        .map(|k| (k, bits(k)))
+        .min_by_key(|&(_, bits)| bits)
+        .expect("no rice parameters")
+}
+
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    let predict: fn(&[i64], usize) -> i64 = match order {
+        0 => |_, _| 0,
+        1 => |s, i| s[i - 1],
+        2 => |s, i| 2 * s[i - 1] - s[i - 2],
+        3 => |s, i| 3 * s[i - 1] - 3 * s[i - 2] + s[i - 3],
+        _ => |s, i| 4 * s[i - 1] - 6 * s[i - 2] + 4 * s[i - 3] - s[i - 4],
+    };
+    (order..samples.len())
+        .map(|i| samples[i] - predict(samples, i))
+        .collect()
+}
+
+// Coefficients are ordered from the most recent sample to the least recent.
+fn lpc_residual(samples: &[i64], coefficients: &[i64], shift: i32) -> Vec<i64> {
+    let order = coefficients.len();
+    (order..samples.len())
+        .map(|i| {
+            let prediction: i64 = coefficients
+                .iter()
+                .enumerate()
+                .map(|(j, &c)| c * samples[i - 1 - j])
+                .sum();
+            samples[i] - (prediction >> shift)
+        })
+        .collect()
+}
+
+// The precision of quantized LPC coefficients, following the reference encoder.
+fn qlp_precision(bps: u32, block_size: usize) -> u32 {
+    let precision = if bps < 16 {
+        (2 + bps / 2).max(5)
+    } else {
+        match block_size {
+            n if n <= 192 => 7,
+            n if n <= 384 => 8,
+            n if n <= 576 => 9,
+            n if n <= 1152 => 10,
+            n if n <= 2304 => 11,
+            n if n <= 4608 => 12,
+            _ => 13,
+        }
+    };
+    precision.min(15)
+}
+
+// A tapered cosine window, flat across all but the outer `p / 2` of each side.
+fn tukey_window(len: usize, p: f64) -> Vec<f64> {
+    let mut window = vec![1.0; len];
+    let np = (p / 2.0 * len as f64) as isize - 1;
+    if np > 0 {
+        let np = np as usize;
+        for n in 0..=np {
+            let w = |x: usize| 0.5 - 0.5 * (std::f64::consts::PI * x as f64 / np as f64).cos();
+            window[n] = w(n);
+            window[len - np - 1 + n] = w(n + np);
+        }
+    }
+    window
+}
+
+// Computes the predictor coefficients of every order up to the number of lags in the given
+// autocorrelation.
+fn levinson_durbin(autocorrelation: &[f64]) -> Vec<Vec<f64>> {
+    let max_order = autocorrelation.len() - 1;
+    let mut lpc = vec![0.0; max_order];
+    let mut error = autocorrelation[0];
+    let mut coefficients = Vec::with_capacity(max_order);
+    for i in 0..max_order {
+        let mut r = -autocorrelation[i + 1];
+        for j in 0..i {
+            r -= lpc[j] * autocorrelation[i - j];
+        }
+        r /= error;
+
+        lpc[i] = r;
+        let mut j = 0;
+        while j < i >> 1 {
+            let tmp = lpc[j];
+            lpc[j] += r * lpc[i - 1 - j];
+            lpc[i - 1 - j] += r * tmp;
+            j += 1;
+        }
+        if i & 1 == 1 {
+            lpc[j] += lpc[j] * r;
+        }
+        error *= 1.0 - r * r;
+
+        coefficients.push(lpc[..=i].iter().map(|c| -c).collect());
+        if error == 0.0 {
+            break;
+        }
+    }
+    coefficients
+}
+
+// Quantizes the given coefficients to `precision` bits, returning them along with their shift.
+fn quantize_coefficients(coefficients: &[f64], precision: u32) -> Option<(Vec<i64>, i32)> {
+    let max = coefficients.iter().fold(0.0f64, |max, c| max.max(c.abs()));
+    if max == 0.0 || !max.is_finite() {
+        return None;
+    }
+    let q_max = (1i64 << (precision - 1)) - 1;
+    let q_min = -(1i64 << (precision - 1));
+    // Leave room for the sign bit and the integer part of the largest coefficient.
+    let log2_max = max.log2().floor() as i32;
+    let shift = (precision as i32 - log2_max - 2).min(MAX_QLP_SHIFT);
+    if shift < 0 {
+        return None;
+    }
+    // Carry the rounding error of each coefficient over to the next.
+    let mut error = 0.0;
+    let quantized = coefficients
+        .iter()
+        .map(|&c| {
+            error += c * f64::from(1 << shift);
+            let q = (error.round() as i64).max(q_min).min(q_max);
+            error -= q as f64;
+            q
+        })
+        .collect();
+    Some((quantized, shift))
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (crc << 8) ^ CRC16_TABLE[usize::from((crc >> 8) as u8 ^ byte)]
+    })
+}
+
+/// The CRC-16 of each byte value, as frames are too large to checksum a bit at a time.
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
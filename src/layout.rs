@@ -0,0 +1,388 @@
+//! Items describing the speaker position for which each channel of audio is intended.
+
+/// The position of the speaker for which a channel is intended.
+///
+/// Positions are listed in the order of the bits of a WAV channel mask.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    FrontLeftOfCenter,
+    FrontRightOfCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+    /// A channel without a designated speaker position.
+    Unknown,
+}
+
+/// The speaker position of each channel of audio, in channel order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChannelLayout {
+    speakers: Vec<Speaker>,
+}
+
+/// The speakers of each bit of a WAV channel mask, from the least significant bit.
+const MASK_SPEAKERS: [Speaker; 18] = [
+    Speaker::FrontLeft,
+    Speaker::FrontRight,
+    Speaker::FrontCenter,
+    Speaker::LowFrequency,
+    Speaker::BackLeft,
+    Speaker::BackRight,
+    Speaker::FrontLeftOfCenter,
+    Speaker::FrontRightOfCenter,
+    Speaker::BackCenter,
+    Speaker::SideLeft,
+    Speaker::SideRight,
+    Speaker::TopCenter,
+    Speaker::TopFrontLeft,
+    Speaker::TopFrontCenter,
+    Speaker::TopFrontRight,
+    Speaker::TopBackLeft,
+    Speaker::TopBackCenter,
+    Speaker::TopBackRight,
+];
+
+impl Speaker {
+    /// The bit representing the speaker within a WAV channel mask, or `None` for `Unknown`.
+    pub fn channel_mask_bit(self) -> Option<u32> {
+        MASK_SPEAKERS
+            .iter()
+            .position(|&speaker| speaker == self)
+            .map(|bit| 1 << bit)
+    }
+}
+
+impl ChannelLayout {
+    /// A layout of the given speakers, one per channel.
+    pub fn new(speakers: Vec<Speaker>) -> Self {
+        ChannelLayout { speakers }
+    }
+
+    /// A layout of `channel_count` channels without designated speaker positions.
+    pub fn unknown(channel_count: usize) -> Self {
+        Self::new(vec![Speaker::Unknown; channel_count])
+    }
+
+    /// The default layout of WAV and FLAC audio with the given number of channels.
+    ///
+    /// These are the channel assignments defined by FLAC for up to 8 channels, which follow the
+    /// order of the WAV channel mask. Channels beyond the eighth have no designated position.
+    pub fn default_for(channel_count: usize) -> Self {
+        use self::Speaker::*;
+        let speakers: &[Speaker] = match channel_count {
+            1 => &[FrontCenter],
+            2 => &[FrontLeft, FrontRight],
+            3 => &[FrontLeft, FrontRight, FrontCenter],
+            4 => &[FrontLeft, FrontRight, BackLeft, BackRight],
+            5 => &[FrontLeft, FrontRight, FrontCenter, BackLeft, BackRight],
+            6 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+            ],
+            7 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackCenter,
+                SideLeft,
+                SideRight,
+            ],
+            8 => &[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+            ],
+            n => return Self::unknown(n),
+        };
+        Self::new(speakers.to_vec())
+    }
+
+    /// The layout of Ogg Vorbis audio with the given number of channels, as defined by the Vorbis
+    /// specification.
+    ///
+    /// Vorbis orders channels differently to WAV and FLAC, e.g. 5.1 is ordered front left, front
+    /// centre, front right, back left, back right, LFE. Channels of streams with more than 8
+    /// channels have no designated position.
+    pub fn vorbis(channel_count: usize) -> Self {
+        use self::Speaker::*;
+        let speakers: &[Speaker] = match channel_count {
+            1 => &[FrontCenter],
+            2 => &[FrontLeft, FrontRight],
+            3 => &[FrontLeft, FrontCenter, FrontRight],
+            4 => &[FrontLeft, FrontRight, BackLeft, BackRight],
+            5 => &[FrontLeft, FrontCenter, FrontRight, BackLeft, BackRight],
+            6 => &[
+                FrontLeft,
+                FrontCenter,
+                FrontRight,
+                BackLeft,
+                BackRight,
+                LowFrequency,
+            ],
+            7 => &[
+                FrontLeft,
+                FrontCenter,
+                FrontRight,
+                SideLeft,
+                SideRight,
+                BackCenter,
+                LowFrequency,
+            ],
+            8 => &[
+                FrontLeft,
+                FrontCenter,
+                FrontRight,
+                SideLeft,
+                SideRight,
+                BackLeft,
+                BackRight,
+                LowFrequency,
+            ],
+            n => return Self::unknown(n),
+        };
+        Self::new(speakers.to_vec())
+    }
+
+    /// The layout described by a WAV channel mask, as found within `WAVE_FORMAT_EXTENSIBLE`
+    /// headers.
+    ///
+    /// Channels are assigned the speakers of the set bits in order from the least significant
+    /// bit. Channels beyond the number of set bits have no designated position, while surplus
+    /// bits are ignored.
+    pub fn from_channel_mask(channel_mask: u32, channel_count: usize) -> Self {
+        let mut speakers: Vec<Speaker> = MASK_SPEAKERS
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| channel_mask & (1 << bit) != 0)
+            .map(|(_, &speaker)| speaker)
+            .take(channel_count)
+            .collect();
+        speakers.resize(channel_count, Speaker::Unknown);
+        Self::new(speakers)
+    }
+
+    /// The WAV channel mask describing the layout, if every channel has a distinct known speaker
+    /// position and the channels are in the order of the mask's bits.
+    pub fn channel_mask(&self) -> Option<u32> {
+        let mut mask = 0;
+        for speaker in &self.speakers {
+            let bit = speaker.channel_mask_bit()?;
+            // Each bit must be more significant than those of the preceding channels.
+            if bit <= mask {
+                return None;
+            }
+            mask |= bit;
+        }
+        Some(mask)
+    }
+
+    /// The speaker position of each channel, in channel order.
+    pub fn speakers(&self) -> &[Speaker] {
+        &self.speakers
+    }
+
+    /// The number of channels within the layout.
+    pub fn channel_count(&self) -> usize {
+        self.speakers.len()
+    }
+
+    /// The index of the first channel intended for the given `speaker`, e.g. the LFE channel.
+    pub fn channel_of(&self, speaker: Speaker) -> Option<usize> {
+        self.speakers.iter().position(|&s| s == speaker)
+    }
+
+    /// The index of the channel placed at each position of the canonical order, i.e. the order of
+    /// the WAV channel mask used by WAV and FLAC audio.
+    ///
+    /// Channels are sorted by speaker position, followed by any channels without a designated
+    /// position in their original order.
+    pub fn canonical_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.speakers.len()).collect();
+        order.sort_by_key(|&channel| {
+            self.speakers[channel]
+                .channel_mask_bit()
+                .unwrap_or(u32::MAX)
+        });
+        order
+    }
+
+    /// The layout with its channels in the canonical order given by `canonical_order`.
+    pub fn to_canonical(&self) -> Self {
+        Self::new(reorder_interleaved(&self.speakers, &self.canonical_order()))
+    }
+}
+
+// Reorders the channels of the given interleaved samples, placing the channel at `order[i]` at
+// index `i` of each frame.
+pub(crate) fn reorder_interleaved<T>(samples: &[T], order: &[usize]) -> Vec<T>
+where
+    T: Copy,
+{
+    samples
+        .chunks(order.len().max(1))
+        .flat_map(|frame| order.iter().map(move |&channel| frame[channel]))
+        .collect()
+}
+
+// The layout described by the contents of a CAF `chan` chunk, or `None` if the layout tag is
+// unrecognised.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn from_caf_channel_layout(
+    layout: &caf::chunks::ChannelLayout,
+    channel_count: usize,
+) -> Option<ChannelLayout> {
+    const USE_CHANNEL_DESCRIPTIONS: u32 = 0;
+    const USE_CHANNEL_BITMAP: u32 = 1 << 16;
+    match layout.channel_layout_tag {
+        USE_CHANNEL_DESCRIPTIONS => {
+            let labels: Vec<u32> = layout
+                .channel_descriptions
+                .iter()
+                .map(|description| description.channel_label)
+                .collect();
+            let mut speakers: Vec<Speaker> = labels
+                .iter()
+                .map(|&label| caf_label_speaker(label, &labels))
+                .take(channel_count)
+                .collect();
+            speakers.resize(channel_count, Speaker::Unknown);
+            Some(ChannelLayout::new(speakers))
+        }
+        USE_CHANNEL_BITMAP => Some(ChannelLayout::from_channel_mask(
+            layout.channel_bitmap,
+            channel_count,
+        )),
+        tag => caf_layout_tag_labels(tag).and_then(|labels| {
+            // Each tag describes a fixed number of channels.
+            if labels.len() != channel_count {
+                return None;
+            }
+            let speakers = labels
+                .iter()
+                .map(|&label| caf_label_speaker(label, labels))
+                .collect();
+            Some(ChannelLayout::new(speakers))
+        }),
+    }
+}
+
+// The default layout of ALAC audio without a `chan` chunk, as defined by the ALAC specification.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn alac_default(channel_count: usize) -> ChannelLayout {
+    const MONO: u32 = 100 << 16 | 1;
+    const STEREO: u32 = 101 << 16 | 2;
+    const MPEG_3_0_B: u32 = 114 << 16 | 3;
+    const MPEG_4_0_B: u32 = 116 << 16 | 4;
+    const MPEG_5_0_D: u32 = 120 << 16 | 5;
+    const MPEG_5_1_D: u32 = 124 << 16 | 6;
+    const AAC_6_1: u32 = 142 << 16 | 7;
+    const MPEG_7_1_B: u32 = 127 << 16 | 8;
+    let tag = match channel_count {
+        1 => MONO,
+        2 => STEREO,
+        3 => MPEG_3_0_B,
+        4 => MPEG_4_0_B,
+        5 => MPEG_5_0_D,
+        6 => MPEG_5_1_D,
+        7 => AAC_6_1,
+        8 => MPEG_7_1_B,
+        n => return ChannelLayout::unknown(n),
+    };
+    let labels = caf_layout_tag_labels(tag).expect("missing ALAC channel layout");
+    let speakers = labels
+        .iter()
+        .map(|&label| caf_label_speaker(label, labels))
+        .collect();
+    ChannelLayout::new(speakers)
+}
+
+// The channel labels of the common CAF layout tags.
+#[cfg(feature = "caf_alac")]
+fn caf_layout_tag_labels(tag: u32) -> Option<&'static [u32]> {
+    const L: u32 = 1;
+    const R: u32 = 2;
+    const C: u32 = 3;
+    const LFE: u32 = 4;
+    const LS: u32 = 5;
+    const RS: u32 = 6;
+    const LC: u32 = 7;
+    const RC: u32 = 8;
+    const CS: u32 = 9;
+    const RLS: u32 = 33;
+    const RRS: u32 = 34;
+    const MONO: u32 = 42;
+    let labels: &[u32] = match tag >> 16 {
+        100 => &[MONO],
+        101 | 102 => &[L, R],
+        108 => &[L, R, LS, RS],
+        113 => &[L, R, C],
+        114 => &[C, L, R],
+        115 => &[L, R, C, CS],
+        116 => &[C, L, R, CS],
+        117 => &[L, R, C, LS, RS],
+        118 => &[L, R, LS, RS, C],
+        119 => &[L, C, R, LS, RS],
+        120 => &[C, L, R, LS, RS],
+        121 => &[L, R, C, LFE, LS, RS],
+        122 => &[L, R, LS, RS, C, LFE],
+        123 => &[L, C, R, LS, RS, LFE],
+        124 => &[C, L, R, LS, RS, LFE],
+        125 => &[L, R, C, LFE, LS, RS, CS],
+        126 => &[L, R, C, LFE, LS, RS, LC, RC],
+        127 => &[C, LC, RC, L, R, LS, RS, LFE],
+        128 => &[L, R, C, LFE, LS, RS, RLS, RRS],
+        131 => &[L, R, CS],
+        132 => &[L, R, LS, RS],
+        142 => &[C, L, R, LS, RS, CS, LFE],
+        _ => return None,
+    };
+    Some(labels)
+}
+
+// The speaker of a CAF channel label within a layout of the given labels.
+//
+// Labels 1 through 18 correspond to the bits of the WAV channel mask. The surround labels refer
+// to the side speakers where rear surround or centre surround labels are also present, as in 7.1
+// and 6.1.
+#[cfg(feature = "caf_alac")]
+fn caf_label_speaker(label: u32, labels: &[u32]) -> Speaker {
+    const LEFT_SURROUND: u32 = 5;
+    const RIGHT_SURROUND: u32 = 6;
+    const CENTER_SURROUND: u32 = 9;
+    const REAR_SURROUND_LEFT: u32 = 33;
+    const REAR_SURROUND_RIGHT: u32 = 34;
+    const MONO: u32 = 42;
+    let side_surround = labels.contains(&REAR_SURROUND_LEFT) || labels.contains(&CENTER_SURROUND);
+    match label {
+        LEFT_SURROUND if side_surround => Speaker::SideLeft,
+        RIGHT_SURROUND if side_surround => Speaker::SideRight,
+        1..=18 => MASK_SPEAKERS[label as usize - 1],
+        REAR_SURROUND_LEFT => Speaker::BackLeft,
+        REAR_SURROUND_RIGHT => Speaker::BackRight,
+        MONO => Speaker::FrontCenter,
+        _ => Speaker::Unknown,
+    }
+}
@@ -7,18 +7,48 @@ pub extern crate alac;
 pub extern crate caf;
 #[cfg(feature = "flac")]
 pub extern crate claxon; // flac
-#[cfg(feature = "wav")]
-pub extern crate hound; // wav
 #[cfg(feature = "ogg_vorbis")]
 pub extern crate lewton; // ogg vorbis
 
+#[cfg(any(feature = "flac", feature = "caf_alac"))]
+mod bit_writer;
 #[cfg(feature = "caf_alac")]
 mod caf_alac;
+#[cfg(feature = "flac")]
+mod flac;
+#[cfg(feature = "ogg_vorbis")]
+mod ogg_vorbis;
+#[cfg(feature = "wav")]
+mod wav;
 
+pub mod broadcast;
+pub mod buffer;
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub mod convert;
+pub mod layout;
+pub mod marker;
+pub mod metadata;
+pub mod mix;
+pub mod picture;
 pub mod read;
+pub mod sampler;
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
 pub mod write;
 
+pub use crate::broadcast::BroadcastExtension;
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub use crate::buffer::write_buffer;
+pub use crate::buffer::{read_to_buffer, AudioBuffer};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub use crate::convert::{convert, Converter};
+pub use crate::layout::{ChannelLayout, Speaker};
+pub use crate::marker::{CueSheet, Marker};
+pub use crate::metadata::Metadata;
+pub use crate::picture::{Picture, PictureType};
 pub use crate::read::{open, Reader};
+pub use crate::sampler::SamplerInfo;
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub use crate::write::{create, Writer};
 
 /// Enumerates the various formats supported by the crate.
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
@@ -65,3 +95,64 @@ impl Format {
         }
     }
 }
+
+/// Enumerates the encodings in which individual samples may be stored within an audio format.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Signed 8-bit integer samples.
+    I8,
+    /// Signed 16-bit integer samples.
+    I16,
+    /// Signed 24-bit integer samples.
+    I24,
+    /// Signed 32-bit integer samples.
+    I32,
+    /// 32-bit floating point samples.
+    F32,
+    /// 64-bit floating point samples.
+    F64,
+    /// Floating point samples decoded from a lossy encoding such as Vorbis.
+    ///
+    /// Lossy formats do not store samples at any particular bit depth, so converting to any
+    /// other sample format may be considered lossless with respect to the stored audio.
+    LossyFloat,
+}
+
+impl SampleFormat {
+    /// The number of bits used to store a single sample.
+    ///
+    /// `LossyFloat` samples are decoded as 32-bit floating point.
+    pub fn bits_per_sample(self) -> u32 {
+        match self {
+            SampleFormat::I8 => 8,
+            SampleFormat::I16 => 16,
+            SampleFormat::I24 => 24,
+            SampleFormat::I32 | SampleFormat::F32 | SampleFormat::LossyFloat => 32,
+            SampleFormat::F64 => 64,
+        }
+    }
+
+    /// Whether or not samples are stored in a floating point representation.
+    pub fn is_float(self) -> bool {
+        match self {
+            SampleFormat::F32 | SampleFormat::F64 | SampleFormat::LossyFloat => true,
+            SampleFormat::I8 | SampleFormat::I16 | SampleFormat::I24 | SampleFormat::I32 => false,
+        }
+    }
+
+    /// Whether or not samples were decoded from a lossy encoding.
+    pub fn is_lossy(self) -> bool {
+        self == SampleFormat::LossyFloat
+    }
+
+    // The smallest integer sample format able to store samples of the given bit depth.
+    #[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+    pub(crate) fn from_int_bits(bits_per_sample: u32) -> Self {
+        match bits_per_sample {
+            0..=8 => SampleFormat::I8,
+            9..=16 => SampleFormat::I16,
+            17..=24 => SampleFormat::I24,
+            _ => SampleFormat::I32,
+        }
+    }
+}
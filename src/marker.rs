@@ -0,0 +1,251 @@
+//! Items for the markers and cue sheets that locate positions within audio.
+
+/// A named position within audio, e.g. a cue point of a WAV file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Marker {
+    /// The position of the marker as a number of frames from the start of the audio, at the
+    /// sample rate given by `Description::sample_rate`.
+    pub frame: u64,
+    /// The name of the marker, which is empty where the file gives none.
+    pub label: String,
+}
+
+/// The table of contents of a CD, as stored within a FLAC CUESHEET metadata block.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CueSheet {
+    /// The Media Catalog Number of the CD, which is empty where the file gives none.
+    pub media_catalog_number: String,
+    /// The number of frames preceding the first track of a CD, `0` for other sources.
+    pub lead_in: u64,
+    /// Whether or not the cue sheet describes a CD.
+    pub is_cd: bool,
+    /// The tracks in the order of the file, ending with the lead-out track.
+    pub tracks: Vec<CueTrack>,
+}
+
+/// A track within a `CueSheet`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CueTrack {
+    /// The position of the start of the track, as a number of frames from the start of the audio.
+    pub frame: u64,
+    /// The number of the track, where `170` for CDs and `255` otherwise marks the lead-out track.
+    pub number: u8,
+    /// The International Standard Recording Code of the track, which is empty where the file
+    /// gives none.
+    pub isrc: String,
+    /// Whether the track holds audio rather than data.
+    pub is_audio: bool,
+    /// Whether or not the track was recorded with pre-emphasis.
+    pub pre_emphasis: bool,
+    /// The index points of the track, of which index `1` marks the start of the track proper and
+    /// index `0` any preceding pregap.
+    pub indices: Vec<CueIndex>,
+}
+
+/// An index point within a `CueTrack`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CueIndex {
+    /// The position of the index point as a number of frames from the start of the audio, rather
+    /// than from the start of the track as stored within the file.
+    pub frame: u64,
+    /// The number of the index point.
+    pub number: u8,
+}
+
+impl CueTrack {
+    /// Whether or not this is the lead-out track, which marks the end of the audio.
+    pub fn is_lead_out(&self) -> bool {
+        self.number == 170 || self.number == 255
+    }
+
+    /// The position of index `1`, or of the start of the track if it has no such index point.
+    pub fn start_frame(&self) -> u64 {
+        self.indices
+            .iter()
+            .find(|index| index.number == 1)
+            .map(|index| index.frame)
+            .unwrap_or(self.frame)
+    }
+}
+
+impl CueSheet {
+    /// A marker at the start of each track besides the lead-out, labelled by its number, e.g.
+    /// `Track 01`.
+    pub fn markers(&self) -> Vec<Marker> {
+        self.tracks
+            .iter()
+            .filter(|track| !track.is_lead_out())
+            .map(|track| Marker {
+                frame: track.start_frame(),
+                label: format!("Track {:02}", track.number),
+            })
+            .collect()
+    }
+}
+
+// Reads the contents of a FLAC CUESHEET metadata block.
+#[cfg(feature = "flac")]
+pub(crate) fn read_flac_cue_sheet(block: &[u8]) -> Option<CueSheet> {
+    let text = |bytes: &[u8]| {
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    let u64_be = |bytes: &[u8]| {
+        let mut value = [0; 8];
+        value.copy_from_slice(&bytes[..8]);
+        u64::from_be_bytes(value)
+    };
+
+    // The catalog number, lead-in, flags, reserved bytes and number of tracks.
+    let header = block.get(..396)?;
+    let mut cue_sheet = CueSheet {
+        media_catalog_number: text(&header[..128]),
+        lead_in: u64_be(&header[128..136]),
+        is_cd: header[136] & 0x80 != 0,
+        tracks: Vec::with_capacity(usize::from(header[395])),
+    };
+    let mut position = header.len();
+    for _ in 0..header[395] {
+        // The offset, number, ISRC, flags, reserved bytes and number of index points.
+        let track = block.get(position..position + 36)?;
+        position += track.len();
+        let frame = u64_be(&track[..8]);
+        let mut indices = Vec::with_capacity(usize::from(track[35]));
+        for _ in 0..track[35] {
+            let index = block.get(position..position + 12)?;
+            position += index.len();
+            indices.push(CueIndex {
+                frame: frame.saturating_add(u64_be(&index[..8])),
+                number: index[8],
+            });
+        }
+        cue_sheet.tracks.push(CueTrack {
+            frame,
+            number: track[8],
+            isrc: text(&track[9..21]),
+            is_audio: track[21] & 0x80 == 0,
+            pre_emphasis: track[21] & 0x40 != 0,
+            indices,
+        });
+    }
+    Some(cue_sheet)
+}
+
+// Reads the points of a RIFF `cue ` chunk, labelled by the `labl` sub-chunks of the given `LIST`
+// chunks of the `adtl` type.
+#[cfg(feature = "wav")]
+pub(crate) fn read_riff_cue_points<'a, I>(cue: &[u8], lists: I) -> Vec<Marker>
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let u32_le = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let mut labels = Vec::new();
+    for list in lists {
+        if list.len() < 4 || &list[0..4] != b"adtl" {
+            continue;
+        }
+        let mut position = 4;
+        while position + 8 <= list.len() {
+            let id = &list[position..position + 4];
+            let len = u32_le(&list[position + 4..position + 8]) as usize;
+            let start = position + 8;
+            let contents = &list[start..start.saturating_add(len).min(list.len())];
+            if id == b"labl" && contents.len() >= 4 {
+                let text = &contents[4..];
+                let end = text
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .unwrap_or(text.len());
+                let label = String::from_utf8_lossy(&text[..end]).into_owned();
+                labels.push((u32_le(contents), label));
+            }
+            position = start.saturating_add(len).saturating_add(len % 2);
+        }
+    }
+
+    // The identifier, play order position, chunk identifier, chunk start, block start and sample
+    // offset of each point.
+    let count = cue.get(..4).map(u32_le).unwrap_or(0) as usize;
+    let mut markers = cue
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(24)
+        .take(count)
+        .map(|point| {
+            let id = u32_le(&point[..4]);
+            let label = labels
+                .iter()
+                .find(|&&(label_id, _)| label_id == id)
+                .map(|(_, label)| label.clone())
+                .unwrap_or_default();
+            Marker {
+                frame: u64::from(u32_le(&point[20..24])),
+                label,
+            }
+        })
+        .collect::<Vec<_>>();
+    markers.sort_by_key(|marker| marker.frame);
+    markers
+}
+
+// Reads the markers of a CAF `mark` chunk, labelled by the given `strg` chunk.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn read_caf_markers(mark: &[u8], strings: Option<&[u8]>) -> Vec<Marker> {
+    let u32_be = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    // The identifier and text of each string, which are nul-terminated and located by their
+    // offset from the end of the table of identifiers.
+    let mut labels = Vec::new();
+    if let Some(strings) = strings.filter(|strings| strings.len() >= 4) {
+        let count = u32_be(strings) as usize;
+        let table = &strings[4..];
+        let text = table.get(count.saturating_mul(12)..).unwrap_or_default();
+        for entry in table.chunks_exact(12).take(count) {
+            let mut offset = [0; 8];
+            offset.copy_from_slice(&entry[4..12]);
+            let offset = i64::from_be_bytes(offset);
+            let label = match text.get(offset.max(0) as usize..) {
+                Some(label) if offset >= 0 => label,
+                _ => continue,
+            };
+            let end = label
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(label.len());
+            labels.push((
+                u32_be(entry),
+                String::from_utf8_lossy(&label[..end]).into_owned(),
+            ));
+        }
+    }
+
+    // The SMPTE time type and number of markers, followed by the type, frame position, string
+    // identifier, SMPTE time and channel of each marker.
+    let count = mark.get(4..8).map(u32_be).unwrap_or(0) as usize;
+    let mut markers = mark
+        .get(8..)
+        .unwrap_or_default()
+        .chunks_exact(28)
+        .take(count)
+        .map(|marker| {
+            let mut frame = [0; 8];
+            frame.copy_from_slice(&marker[4..12]);
+            let id = u32_be(&marker[12..16]);
+            let label = labels
+                .iter()
+                .find(|&&(label_id, _)| label_id == id)
+                .map(|(_, label)| label.clone())
+                .unwrap_or_default();
+            Marker {
+                frame: f64::from_be_bytes(frame).max(0.0).round() as u64,
+                label,
+            }
+        })
+        .collect::<Vec<_>>();
+    markers.sort_by_key(|marker| marker.frame);
+    markers
+}
@@ -0,0 +1,301 @@
+//! Items for the tags describing audio, such as its title and artist.
+
+/// The well-known tags, named as Vorbis comments.
+const TITLE: &str = "TITLE";
+const ARTIST: &str = "ARTIST";
+const ALBUM: &str = "ALBUM";
+const TRACK_NUMBER: &str = "TRACKNUMBER";
+const DATE: &str = "DATE";
+const GENRE: &str = "GENRE";
+const COMMENT: &str = "COMMENT";
+#[cfg(any(feature = "caf_alac", feature = "wav"))]
+const COPYRIGHT: &str = "COPYRIGHT";
+#[cfg(any(feature = "caf_alac", feature = "wav"))]
+const COMPOSER: &str = "COMPOSER";
+#[cfg(any(feature = "caf_alac", feature = "wav"))]
+const ENCODER: &str = "ENCODER";
+
+/// The Vorbis comment name of each RIFF `INFO` chunk identifier.
+///
+/// Tags are written with the first identifier of their name.
+#[cfg(feature = "wav")]
+const RIFF_INFO_KEYS: [(&[u8; 4], &str); 11] = [
+    (b"INAM", TITLE),
+    (b"IART", ARTIST),
+    (b"IPRD", ALBUM),
+    (b"ITRK", TRACK_NUMBER),
+    (b"IPRT", TRACK_NUMBER),
+    (b"ICRD", DATE),
+    (b"IGNR", GENRE),
+    (b"ICMT", COMMENT),
+    (b"ICOP", COPYRIGHT),
+    (b"IMUS", COMPOSER),
+    (b"ISFT", ENCODER),
+];
+
+/// The Vorbis comment name of each key of a CAF `info` chunk.
+#[cfg(feature = "caf_alac")]
+const CAF_INFO_KEYS: [(&str, &str); 10] = [
+    ("title", TITLE),
+    ("artist", ARTIST),
+    ("album", ALBUM),
+    ("track number", TRACK_NUMBER),
+    ("year", DATE),
+    ("genre", GENRE),
+    ("comments", COMMENT),
+    ("copyright", COPYRIGHT),
+    ("composer", COMPOSER),
+    ("encoding application", ENCODER),
+];
+
+/// Tags describing audio, stored as key and value pairs in the manner of Vorbis comments.
+///
+/// Keys are compared case-insensitively and may occur more than once, e.g. for several artists.
+/// The tags of WAV and CAF files are given the equivalent Vorbis comment names where one exists,
+/// e.g. the `INAM` tag of a WAV file is read as `TITLE`, while other tags retain their own keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    tags: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Metadata without any tags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the first tag with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| &value[..])
+    }
+
+    /// The values of every tag with the given key, in order.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| &value[..])
+            .collect()
+    }
+
+    /// Adds a tag, retaining any existing tags with the same key.
+    pub fn push<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.tags.push((key.into(), value.into()));
+    }
+
+    /// Replaces every tag with the given key by a single tag of the given value.
+    pub fn set<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        self.remove(&key);
+        self.tags.push((key, value.into()));
+    }
+
+    /// Removes every tag with the given key.
+    pub fn remove(&mut self, key: &str) {
+        self.tags.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+    }
+
+    /// An iterator yielding the key and value of every tag, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tags.iter().map(|(key, value)| (&key[..], &value[..]))
+    }
+
+    /// The number of tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Whether or not there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// The `TITLE` tag.
+    pub fn title(&self) -> Option<&str> {
+        self.get(TITLE)
+    }
+
+    /// The `ARTIST` tag.
+    pub fn artist(&self) -> Option<&str> {
+        self.get(ARTIST)
+    }
+
+    /// The `ALBUM` tag.
+    pub fn album(&self) -> Option<&str> {
+        self.get(ALBUM)
+    }
+
+    /// The `TRACKNUMBER` tag, read as a number.
+    ///
+    /// Tags of the form `3/12`, giving the number of tracks too, are read as the track number
+    /// alone.
+    pub fn track_number(&self) -> Option<u32> {
+        let value = self.get(TRACK_NUMBER)?;
+        let number = value.split('/').next().unwrap_or(value);
+        number.trim().parse().ok()
+    }
+
+    /// The `DATE` tag.
+    pub fn date(&self) -> Option<&str> {
+        self.get(DATE)
+    }
+
+    /// The `GENRE` tag.
+    pub fn genre(&self) -> Option<&str> {
+        self.get(GENRE)
+    }
+
+    /// The `COMMENT` tag.
+    pub fn comment(&self) -> Option<&str> {
+        self.get(COMMENT)
+    }
+}
+
+impl<K, V> std::iter::FromIterator<(K, V)> for Metadata
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<I>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Metadata {
+            tags: tags
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+// Adds the tags of the sub-chunks of a RIFF `LIST` chunk of the `INFO` type to `metadata`.
+//
+// Values are terminated by a nul byte and padded to an even length. Other `LIST` chunks hold no
+// tags.
+#[cfg(feature = "wav")]
+pub(crate) fn read_riff_list(list: &[u8], metadata: &mut Metadata) {
+    if list.len() < 4 || &list[0..4] != b"INFO" {
+        return;
+    }
+    let mut position = 4;
+    while position + 8 <= list.len() {
+        let id = &list[position..position + 4];
+        let mut len = [0; 4];
+        len.copy_from_slice(&list[position + 4..position + 8]);
+        let len = u32::from_le_bytes(len) as usize;
+        let start = position + 8;
+        let value = &list[start..start.saturating_add(len).min(list.len())];
+        let value = match value.iter().position(|&byte| byte == 0) {
+            Some(end) => &value[..end],
+            None => value,
+        };
+        let key = match RIFF_INFO_KEYS.iter().find(|&&(riff_id, _)| riff_id == id) {
+            Some(&(_, key)) => key.to_string(),
+            None => String::from_utf8_lossy(id).into_owned(),
+        };
+        metadata.push(key, String::from_utf8_lossy(value));
+        position = start.saturating_add(len).saturating_add(len % 2);
+    }
+}
+
+// Adds the tags of a CAF `info` chunk to `metadata`.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn read_caf_info(info: &[(String, String)], metadata: &mut Metadata) {
+    for (key, value) in info {
+        let key = match CAF_INFO_KEYS.iter().find(|&&(caf_key, _)| caf_key == key) {
+            Some(&(_, key)) => key,
+            None => key,
+        };
+        metadata.push(key, &value[..]);
+    }
+}
+
+// The contents of a FLAC VORBIS_COMMENT block holding the tags of `metadata`.
+#[cfg(feature = "flac")]
+pub(crate) fn vorbis_comment(metadata: &Metadata) -> Vec<u8> {
+    const VENDOR: &str = concat!("audrey ", env!("CARGO_PKG_VERSION"));
+    let mut block = Vec::new();
+    block.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+    block.extend_from_slice(VENDOR.as_bytes());
+    block.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    for (key, value) in metadata.iter() {
+        let len = key.len() + 1 + value.len();
+        block.extend_from_slice(&(len as u32).to_le_bytes());
+        block.extend_from_slice(key.as_bytes());
+        block.push(b'=');
+        block.extend_from_slice(value.as_bytes());
+    }
+    block
+}
+
+// The contents of a RIFF `LIST` chunk of the `INFO` type holding the tags of `metadata`.
+//
+// Tags without a Vorbis comment name are written under their own key where it has the form of an
+// `INFO` identifier, e.g. `ISRC`, and are otherwise omitted.
+#[cfg(feature = "wav")]
+pub(crate) fn riff_info_list(metadata: &Metadata) -> Vec<u8> {
+    let mut list = b"INFO".to_vec();
+    for (key, value) in metadata.iter() {
+        let id = match RIFF_INFO_KEYS
+            .iter()
+            .find(|&&(_, name)| name.eq_ignore_ascii_case(key))
+        {
+            Some(&(id, _)) => *id,
+            None if key.len() == 4
+                && key.starts_with('I')
+                && key
+                    .bytes()
+                    .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit()) =>
+            {
+                let mut id = [0; 4];
+                id.copy_from_slice(key.as_bytes());
+                id
+            }
+            None => continue,
+        };
+        // Values are nul-terminated and padded to an even length.
+        let len = value.len() + 1;
+        list.extend_from_slice(&id);
+        list.extend_from_slice(&(len as u32).to_le_bytes());
+        list.extend_from_slice(value.as_bytes());
+        list.push(0);
+        if len % 2 == 1 {
+            list.push(0);
+        }
+    }
+    list
+}
+
+// The contents of a CAF `info` chunk holding the tags of `metadata`.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn caf_info(metadata: &Metadata) -> Vec<u8> {
+    let mut info = Vec::new();
+    info.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+    for (key, value) in metadata.iter() {
+        let key = match CAF_INFO_KEYS
+            .iter()
+            .find(|&&(_, name)| name.eq_ignore_ascii_case(key))
+        {
+            Some(&(caf_key, _)) => caf_key,
+            None => key,
+        };
+        // Keys and values are nul-terminated strings.
+        info.extend_from_slice(key.as_bytes());
+        info.push(0);
+        info.extend_from_slice(value.as_bytes());
+        info.push(0);
+    }
+    info
+}
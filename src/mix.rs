@@ -0,0 +1,157 @@
+//! Items for mixing audio between differing numbers of channels.
+
+/// The diagonal coefficient of the ITU-R BS.775 downmix, i.e. -3dB.
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// A matrix of gains describing how each output channel is mixed from the input channels.
+///
+/// The sample of each output channel is the sum of every input channel's sample multiplied by the
+/// coefficient at that output channel and input channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MixMatrix {
+    input_channels: usize,
+    output_channels: usize,
+    // Stored by output channel, i.e. each row holds the coefficients of one output channel.
+    coefficients: Vec<f32>,
+}
+
+impl MixMatrix {
+    /// A matrix from the given `coefficients`, stored one row of `input_channels` coefficients per
+    /// output channel.
+    ///
+    /// **Panics** if the number of `coefficients` is not `input_channels * output_channels`.
+    pub fn new(input_channels: usize, output_channels: usize, coefficients: Vec<f32>) -> Self {
+        assert_eq!(
+            coefficients.len(),
+            input_channels * output_channels,
+            "expected one coefficient per input channel for each output channel"
+        );
+        MixMatrix {
+            input_channels,
+            output_channels,
+            coefficients,
+        }
+    }
+
+    /// A matrix that passes each channel through unchanged.
+    pub fn identity(channels: usize) -> Self {
+        let mut matrix = Self::silent(channels, channels);
+        for channel in 0..channels {
+            matrix.set(channel, channel, 1.0);
+        }
+        matrix
+    }
+
+    /// The matrix used by `Reader::frames` to adapt audio with `input_channels` channels to frames
+    /// with `output_channels` channels.
+    ///
+    /// Channels are assumed to follow the WAV and SMPTE ordering, i.e. front left, front right,
+    /// front centre, LFE, back left, back right, side left, side right.
+    ///
+    /// - Mono is duplicated to both channels of stereo, or to the front centre of more channels.
+    /// - 5.1 and 7.1 are mixed to stereo using the ITU-R BS.775 coefficients, dropping the LFE and
+    ///   normalised so that a full scale signal in every channel does not clip.
+    /// - Mixing to mono averages the stereo mix of 5.1 and 7.1, or every channel otherwise.
+    /// - In all other cases channels are passed through by index, dropping surplus input
+    ///   channels and leaving surplus output channels silent.
+    pub fn default_for(input_channels: usize, output_channels: usize) -> Self {
+        match (input_channels, output_channels) {
+            (a, b) if a == b => Self::identity(a),
+            (1, 2) => Self::new(1, 2, vec![1.0, 1.0]),
+            (1, n) if n > 2 => {
+                let mut matrix = Self::silent(1, n);
+                matrix.set(2, 0, 1.0);
+                matrix
+            }
+            (6, 2) => Self::normalized(
+                6,
+                2,
+                vec![
+                    1.0, 0.0, MINUS_3DB, 0.0, MINUS_3DB, 0.0, //
+                    0.0, 1.0, MINUS_3DB, 0.0, 0.0, MINUS_3DB,
+                ],
+            ),
+            (8, 2) => Self::normalized(
+                8,
+                2,
+                vec![
+                    1.0, 0.0, MINUS_3DB, 0.0, MINUS_3DB, 0.0, MINUS_3DB, 0.0, //
+                    0.0, 1.0, MINUS_3DB, 0.0, 0.0, MINUS_3DB, 0.0, MINUS_3DB,
+                ],
+            ),
+            (n @ 6, 1) | (n @ 8, 1) => {
+                let stereo = Self::default_for(n, 2);
+                let coefficients = (0..n)
+                    .map(|input| (stereo.get(0, input) + stereo.get(1, input)) / 2.0)
+                    .collect();
+                Self::new(n, 1, coefficients)
+            }
+            (n, 1) => Self::new(n, 1, vec![1.0 / n as f32; n]),
+            (a, b) => {
+                let mut matrix = Self::silent(a, b);
+                for channel in 0..a.min(b) {
+                    matrix.set(channel, channel, 1.0);
+                }
+                matrix
+            }
+        }
+    }
+
+    /// The number of channels of the audio being mixed.
+    pub fn input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    /// The number of channels produced by the mix.
+    pub fn output_channels(&self) -> usize {
+        self.output_channels
+    }
+
+    /// The gain applied to the `input` channel when mixing the `output` channel.
+    ///
+    /// **Panics** if either channel is out of range.
+    pub fn get(&self, output: usize, input: usize) -> f32 {
+        assert!(output < self.output_channels && input < self.input_channels);
+        self.coefficients[output * self.input_channels + input]
+    }
+
+    /// Sets the gain applied to the `input` channel when mixing the `output` channel.
+    ///
+    /// **Panics** if either channel is out of range.
+    pub fn set(&mut self, output: usize, input: usize, gain: f32) {
+        assert!(output < self.output_channels && input < self.input_channels);
+        self.coefficients[output * self.input_channels + input] = gain;
+    }
+
+    /// Mixes the `output` channel from the samples of a single frame of `input`.
+    pub(crate) fn mix(&self, output: usize, input: &[f32]) -> f32 {
+        let start = output * self.input_channels;
+        self.coefficients[start..start + self.input_channels]
+            .iter()
+            .zip(input)
+            .map(|(gain, sample)| gain * sample)
+            .sum()
+    }
+
+    // A matrix of the given dimensions with every coefficient set to zero.
+    fn silent(input_channels: usize, output_channels: usize) -> Self {
+        Self::new(
+            input_channels,
+            output_channels,
+            vec![0.0; input_channels * output_channels],
+        )
+    }
+
+    // Scales the coefficients of each row so that they sum to one.
+    fn normalized(
+        input_channels: usize,
+        output_channels: usize,
+        mut coefficients: Vec<f32>,
+    ) -> Self {
+        for row in coefficients.chunks_mut(input_channels) {
+            let sum: f32 = row.iter().sum();
+            row.iter_mut().for_each(|gain| *gain /= sum);
+        }
+        Self::new(input_channels, output_channels, coefficients)
+    }
+}
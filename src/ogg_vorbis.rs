@@ -0,0 +1,306 @@
+//! Ogg Vorbis decoding on top of lewton.
+
+use crate::layout::{self, ChannelLayout};
+use crate::metadata::Metadata;
+use crate::picture::{self, Picture};
+use crate::sampler::{self, SamplerInfo};
+use lewton::audio::AudioReadError;
+use lewton::inside_ogg::OggStreamReader;
+use lewton::samples::InterleavedSamples;
+use lewton::{OggReadError, VorbisError};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The largest possible size of an Ogg page, i.e. the header with 255 segments of 255 bytes.
+const MAX_PAGE_LEN: u64 = 27 + 255 + 255 * 255;
+
+/// Decodes the packets of an Ogg Vorbis stream, keeping the most recently decoded packet so that
+/// reading may resume exactly where it left off, including after a seek.
+pub struct OggVorbisReader<R>
+where
+    R: Read + Seek,
+{
+    /// The underlying lewton reader, from which the headers of the stream may be inspected.
+    pub stream_reader: OggStreamReader<R>,
+    // The granule position of the first frame of the stream.
+    start: u64,
+    frame_count: Option<u64>,
+    // The index of the decoded channel placed at each index of a frame, if reordering.
+    channel_order: Option<Vec<usize>>,
+    // The interleaved samples of the most recently decoded packet and the index of the next.
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl<R> OggVorbisReader<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the identification, comment and setup headers of the stream.
+    ///
+    /// The granule position of the final page is also read in order to determine the length of
+    /// the stream, along with that of the first audio page in order to account for streams that
+    /// begin at a nonzero granule position.
+    pub fn new(mut reader: R) -> Result<Self, VorbisError> {
+        let final_granule = final_granule_position(&mut reader).map_err(OggReadError::ReadError)?;
+        reader
+            .seek(SeekFrom::Start(0))
+            .map_err(OggReadError::ReadError)?;
+        let mut stream_reader = OggStreamReader::new(reader)?;
+        let start = first_granule_position(&mut stream_reader)?;
+        // Rewind to the first audio packet by reading the headers anew.
+        let mut reader = stream_reader.into_inner().into_inner();
+        reader
+            .seek(SeekFrom::Start(0))
+            .map_err(OggReadError::ReadError)?;
+        Ok(OggVorbisReader {
+            stream_reader: OggStreamReader::new(reader)?,
+            start,
+            frame_count: final_granule.map(|granule| granule.saturating_sub(start)),
+            channel_order: None,
+            buffer: Vec::new(),
+            index: 0,
+        })
+    }
+
+    /// The number of frames within the stream, as given by the granule position of its final
+    /// page less that of its first frame.
+    pub fn frame_count(&self) -> Option<u64> {
+        self.frame_count
+    }
+
+    /// The comments of the stream's comment header.
+    pub fn metadata(&self) -> Metadata {
+        self.stream_reader
+            .comment_hdr
+            .comment_list
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// The loop given by the `LOOPSTART` and `LOOPLENGTH` comments, if any.
+    pub fn sampler_info(&self) -> Option<SamplerInfo> {
+        sampler::read_vorbis_comments(&self.metadata())
+    }
+
+    /// The pictures of the `METADATA_BLOCK_PICTURE` comments of the stream's comment header.
+    pub fn pictures(&self) -> Vec<Picture> {
+        picture::read_vorbis_comment_pictures(&self.stream_reader.comment_hdr.comment_list)
+    }
+
+    /// The speaker position of each channel, as defined by the Vorbis channel order.
+    ///
+    /// Channels reordered via `set_channel_order` are described in their new order.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        let layout =
+            ChannelLayout::vorbis(usize::from(self.stream_reader.ident_hdr.audio_channels));
+        match self.channel_order {
+            Some(ref order) => {
+                ChannelLayout::new(layout::reorder_interleaved(layout.speakers(), order))
+            }
+            None => layout,
+        }
+    }
+
+    /// Reorders the channels of each frame read from here on, placing the decoded channel at
+    /// `order[i]` at index `i`.
+    ///
+    /// Any remaining samples of the current frame are reordered too, with reading resuming from
+    /// the same index within the frame.
+    pub fn set_channel_order(&mut self, order: Vec<usize>) {
+        // The samples of the current packet are already in the order currently in use.
+        let relative: Vec<usize> = match self.channel_order {
+            Some(ref current) => order
+                .iter()
+                .map(|&channel| {
+                    current
+                        .iter()
+                        .position(|&c| c == channel)
+                        .unwrap_or(channel)
+                })
+                .collect(),
+            None => order.clone(),
+        };
+        let frame_start = self.index - self.index % order.len().max(1);
+        self.buffer = layout::reorder_interleaved(&self.buffer[frame_start..], &relative);
+        self.index -= frame_start;
+        self.channel_order = Some(order);
+    }
+
+    /// Reads the next interleaved sample, decoding the next packet if necessary.
+    ///
+    /// Samples are decoded as floating point, leaving any conversion to the caller.
+    pub fn next_sample(&mut self) -> Option<Result<f32, VorbisError>> {
+        while self.index >= self.buffer.len() {
+            match self.read_packet() {
+                Ok(Some(packet)) => {
+                    self.buffer = packet;
+                    self.index = 0;
+                }
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        let sample = self.buffer[self.index];
+        self.index += 1;
+        Some(Ok(sample))
+    }
+
+    /// Reads interleaved samples into `buf`, converting each via `convert` and decoding packets
+    /// as necessary.
+    ///
+    /// Returns the number of samples read, which is less than `buf.len()` only at the end of the
+    /// stream.
+    pub fn read_into<T, F>(&mut self, buf: &mut [T], mut convert: F) -> Result<usize, VorbisError>
+    where
+        F: FnMut(f32) -> T,
+    {
+        let mut read = 0;
+        while read < buf.len() {
+            if self.index >= self.buffer.len() {
+                match self.read_packet()? {
+                    Some(packet) => {
+                        self.buffer = packet;
+                        self.index = 0;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            let samples = &self.buffer[self.index..];
+            let len = samples.len().min(buf.len() - read);
+            for (sample, &decoded) in buf[read..read + len].iter_mut().zip(samples) {
+                *sample = convert(decoded);
+            }
+            self.index += len;
+            read += len;
+        }
+        Ok(read)
+    }
+
+    // Decodes the next audio packet to interleaved floating point samples.
+    fn read_packet(&mut self) -> Result<Option<Vec<f32>>, VorbisError> {
+        let packet = self
+            .stream_reader
+            .read_dec_packet_generic::<InterleavedSamples<f32>>()?;
+        Ok(packet.map(|packet| match self.channel_order {
+            Some(ref order) => layout::reorder_interleaved(&packet.samples, order),
+            None => packet.samples,
+        }))
+    }
+
+    /// Positions the reader so that the next sample read is the first of the frame at `frame`.
+    ///
+    /// lewton seeks with page granularity and only learns the position of the decoded audio once
+    /// the end of a page is reached, so packets are decoded and discarded from there until the
+    /// packet containing the frame is found. If the page reached lies beyond the frame, the seek
+    /// is retried from increasingly earlier granule positions. Seeking beyond the end of the
+    /// stream positions the reader at the end.
+    pub fn seek(&mut self, frame: u64) -> Result<(), VorbisError> {
+        let channels = usize::from(self.stream_reader.ident_hdr.audio_channels);
+        // Granule positions count from the first frame of the stream.
+        let frame = frame.saturating_add(self.start);
+        let mut goal = frame;
+        // The distance before `frame` to seek to on the next attempt, doubled on each retry.
+        let mut step = u64::from(self.stream_reader.ident_hdr.blocksize_1);
+        loop {
+            self.stream_reader.seek_absgp_pg(goal)?;
+            self.buffer.clear();
+            self.index = 0;
+
+            // The position following the most recently decoded packet, once known.
+            let mut position = None;
+            let overshoot = loop {
+                let packet = match self.read_packet() {
+                    Ok(Some(packet)) => packet,
+                    Ok(None) => return Ok(()),
+                    // Seeking to the start of the stream lands on the header packets.
+                    Err(VorbisError::BadAudio(AudioReadError::AudioIsHeader)) => {
+                        position = Some(self.start);
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
+                let len = (packet.len() / channels) as u64;
+                let start = position;
+                // The first known position is that at the end of a page. The length of the
+                // packet ending it is unreliable, as lewton only truncates the final packet of
+                // the stream once a position is known.
+                let end = match start {
+                    Some(start) => start + len,
+                    None => match self.stream_reader.get_last_absgp() {
+                        Some(end) => end,
+                        None => continue,
+                    },
+                };
+                position = Some(end);
+                if end > frame {
+                    match start {
+                        Some(start) => self.index = (frame - start) as usize * channels,
+                        None if goal > self.start => break end - frame,
+                        // Without an earlier page to seek to, settle for the nearest packet.
+                        None => (),
+                    }
+                    self.buffer = packet;
+                    return Ok(());
+                }
+            };
+            step = step.max(overshoot);
+            goal = frame.saturating_sub(step);
+            step *= 2;
+        }
+    }
+}
+
+// Finds the granule position of the first frame of the stream by decoding the packets of the first
+// audio page, whose granule position is that following its final packet.
+fn first_granule_position<R>(stream_reader: &mut OggStreamReader<R>) -> Result<u64, VorbisError>
+where
+    R: Read + Seek,
+{
+    let channels = usize::from(stream_reader.ident_hdr.audio_channels).max(1);
+    let mut frames = 0;
+    loop {
+        let packet = match stream_reader.read_dec_packet_generic::<InterleavedSamples<f32>>()? {
+            Some(packet) => packet,
+            None => return Ok(0),
+        };
+        frames += (packet.samples.len() / channels) as u64;
+        if let Some(granule_position) = stream_reader.get_last_absgp() {
+            return Ok(granule_position.saturating_sub(frames));
+        }
+    }
+}
+
+// Finds the granule position of the final page belonging to the stream of the first page.
+//
+// Returns `None` if no such page lies within reach of the end of the stream.
+fn final_granule_position<R>(reader: &mut R) -> io::Result<Option<u64>>
+where
+    R: Read + Seek,
+{
+    // Pages without any packet ending within them have a granule position of -1.
+    const NO_GRANULE_POSITION: u64 = u64::MAX;
+
+    let mut header = [0; 27];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    let serial = &header[14..18];
+
+    let len = reader.seek(SeekFrom::End(0))?;
+    let start = len.saturating_sub(MAX_PAGE_LEN);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    reader.take(len - start).read_to_end(&mut bytes)?;
+    let granule_position = (0..bytes.len().saturating_sub(header.len() - 1))
+        .rev()
+        .map(|i| &bytes[i..i + header.len()])
+        .filter(|page| &page[0..4] == b"OggS" && page[4] == 0 && &page[14..18] == serial)
+        .map(|page| {
+            let mut granule_position = [0; 8];
+            granule_position.copy_from_slice(&page[6..14]);
+            u64::from_le_bytes(granule_position)
+        })
+        .find(|&granule_position| granule_position != NO_GRANULE_POSITION);
+    Ok(granule_position)
+}
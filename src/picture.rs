@@ -0,0 +1,453 @@
+//! Items for the pictures embedded within audio files, such as cover art.
+
+/// The role of an embedded picture, as defined by the ID3v2 `APIC` frame and the FLAC PICTURE
+/// block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PictureType {
+    Other,
+    /// A 32x32 pixel PNG file icon.
+    FileIcon,
+    OtherFileIcon,
+    FrontCover,
+    BackCover,
+    Leaflet,
+    /// The media itself, e.g. the label side of a CD.
+    Media,
+    LeadArtist,
+    Artist,
+    Conductor,
+    Band,
+    Composer,
+    Lyricist,
+    RecordingLocation,
+    DuringRecording,
+    DuringPerformance,
+    ScreenCapture,
+    BrightColoredFish,
+    Illustration,
+    BandLogo,
+    PublisherLogo,
+}
+
+/// A picture embedded within an audio file, e.g. the cover of the album.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Picture {
+    picture_type: PictureType,
+    mime_type: String,
+    description: String,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl PictureType {
+    /// The picture type of the given ID3v2 or FLAC code, with unknown codes read as `Other`.
+    pub fn from_code(code: u32) -> Self {
+        use self::PictureType::*;
+        const TYPES: [PictureType; 21] = [
+            Other,
+            FileIcon,
+            OtherFileIcon,
+            FrontCover,
+            BackCover,
+            Leaflet,
+            Media,
+            LeadArtist,
+            Artist,
+            Conductor,
+            Band,
+            Composer,
+            Lyricist,
+            RecordingLocation,
+            DuringRecording,
+            DuringPerformance,
+            ScreenCapture,
+            BrightColoredFish,
+            Illustration,
+            BandLogo,
+            PublisherLogo,
+        ];
+        TYPES.get(code as usize).cloned().unwrap_or(Other)
+    }
+
+    /// The ID3v2 and FLAC code of the picture type.
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Picture {
+    /// The role of the picture, e.g. `PictureType::FrontCover`.
+    pub fn picture_type(&self) -> PictureType {
+        self.picture_type
+    }
+
+    /// The MIME type of the picture's data, e.g. `image/jpeg`.
+    ///
+    /// The MIME type `-->` signifies that the data is a URL linking to the picture.
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// A description of the picture, which is often empty.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The width of the picture in pixels, or `0` if unknown.
+    ///
+    /// Where the file does not give the dimensions, they are read from the header of PNG, JPEG
+    /// and GIF data.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the picture in pixels, or `0` if unknown.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The encoded picture, e.g. the contents of a JPEG file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // A picture of the given dimensions, which are read from the data where not given.
+    #[cfg(any(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+    fn new(
+        picture_type: PictureType,
+        mime_type: String,
+        description: String,
+        (width, height): (u32, u32),
+        data: Vec<u8>,
+    ) -> Self {
+        let (width, height) = match (width, height) {
+            (0, 0) => image_dimensions(&data).unwrap_or((0, 0)),
+            dimensions => dimensions,
+        };
+        Picture {
+            picture_type,
+            mime_type,
+            description,
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+// Reads the contents of a FLAC PICTURE metadata block.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+pub(crate) fn read_flac_picture(block: &[u8]) -> Option<Picture> {
+    let mut bytes = Bytes(block);
+    let picture_type = PictureType::from_code(bytes.u32_be()?);
+    let len = bytes.u32_be()? as usize;
+    let mime_type = String::from_utf8_lossy(bytes.take(len)?).into_owned();
+    let len = bytes.u32_be()? as usize;
+    let description = String::from_utf8_lossy(bytes.take(len)?).into_owned();
+    let width = bytes.u32_be()?;
+    let height = bytes.u32_be()?;
+    // The colour depth and the number of colours of indexed pictures.
+    bytes.take(8)?;
+    let len = bytes.u32_be()? as usize;
+    let data = bytes.take(len)?.to_vec();
+    Some(Picture::new(
+        picture_type,
+        mime_type,
+        description,
+        (width, height),
+        data,
+    ))
+}
+
+// Reads the pictures of the `METADATA_BLOCK_PICTURE` Vorbis comments, each holding a FLAC PICTURE
+// block encoded as base64.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+pub(crate) fn read_vorbis_comment_pictures(tags: &[(String, String)]) -> Vec<Picture> {
+    tags.iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("METADATA_BLOCK_PICTURE"))
+        .filter_map(|(_, value)| read_flac_picture(&decode_base64(value)?))
+        .collect()
+}
+
+// Adds the pictures of the `APIC` frames of an ID3v2.3 or ID3v2.4 tag, or the `PIC` frames of an
+// ID3v2.2 tag, to `pictures`.
+//
+// Compressed and encrypted frames are skipped.
+#[cfg(feature = "wav")]
+pub(crate) fn read_id3_pictures(tag: &[u8], pictures: &mut Vec<Picture>) {
+    const UNSYNCHRONISATION: u8 = 0x80;
+    const EXTENDED_HEADER: u8 = 0x40;
+
+    if tag.len() < 10 || &tag[0..3] != b"ID3" {
+        return;
+    }
+    let version = tag[3];
+    let flags = tag[5];
+    let len = syncsafe(&tag[6..10]) as usize;
+    let body = &tag[10..(10 + len).min(tag.len())];
+    // Tags prior to ID3v2.4 are unsynchronised as a whole, rather than frame by frame.
+    let body = match version {
+        // The flag of ID3v2.2 in place of the extended header marks the tag as compressed.
+        2 if flags & EXTENDED_HEADER != 0 => return,
+        2 | 3 if flags & UNSYNCHRONISATION != 0 => resynchronise(body),
+        2..=4 => body.to_vec(),
+        _ => return,
+    };
+    let mut bytes = Bytes(&body);
+    if version > 2 && flags & EXTENDED_HEADER != 0 {
+        // The size of the extended header excludes itself prior to ID3v2.4.
+        let extended_len = match version {
+            3 => bytes.u32_be().map(|len| len as usize),
+            _ => bytes
+                .take(4)
+                .map(|len| (syncsafe(len) as usize).saturating_sub(4)),
+        };
+        if extended_len.and_then(|len| bytes.take(len)).is_none() {
+            return;
+        }
+    }
+
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    while bytes.0.len() >= header_len && bytes.0[0] != 0 {
+        let header = bytes.take(header_len).unwrap_or_default();
+        let id = &header[..id_len];
+        let len = match version {
+            2 => u32::from_be_bytes([0, header[3], header[4], header[5]]),
+            3 => u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+            _ => syncsafe(&header[4..8]),
+        } as usize;
+        let frame = match bytes.take(len) {
+            Some(frame) => frame,
+            None => break,
+        };
+        let picture = match (version, id) {
+            (2, b"PIC") => read_id3_picture(frame, true),
+            (3, b"APIC") => {
+                const COMPRESSION: u8 = 0x80;
+                const ENCRYPTION: u8 = 0x40;
+                const GROUPING: u8 = 0x20;
+                match header[9] {
+                    flags if flags & (COMPRESSION | ENCRYPTION) != 0 => None,
+                    flags if flags & GROUPING != 0 => {
+                        read_id3_picture(frame.get(1..).unwrap_or_default(), false)
+                    }
+                    _ => read_id3_picture(frame, false),
+                }
+            }
+            (4, b"APIC") => {
+                const GROUPING: u8 = 0x40;
+                const COMPRESSION: u8 = 0x08;
+                const ENCRYPTION: u8 = 0x04;
+                const UNSYNCHRONISATION: u8 = 0x02;
+                const DATA_LENGTH: u8 = 0x01;
+                let flags = header[9];
+                if flags & (COMPRESSION | ENCRYPTION) != 0 {
+                    continue;
+                }
+                let skip =
+                    usize::from(flags & GROUPING != 0) + 4 * usize::from(flags & DATA_LENGTH);
+                let frame = frame.get(skip..).unwrap_or_default();
+                if flags & UNSYNCHRONISATION != 0 {
+                    read_id3_picture(&resynchronise(frame), false)
+                } else {
+                    read_id3_picture(frame, false)
+                }
+            }
+            _ => None,
+        };
+        pictures.extend(picture);
+    }
+}
+
+// Reads the contents of an ID3v2 `APIC` frame, or of an ID3v2.2 `PIC` frame whose image format is
+// given by 3 characters rather than a MIME type.
+#[cfg(feature = "wav")]
+fn read_id3_picture(frame: &[u8], image_format: bool) -> Option<Picture> {
+    let mut bytes = Bytes(frame);
+    let encoding = bytes.take(1)?[0];
+    let mime_type = if image_format {
+        match &bytes.take(3)?.to_ascii_uppercase()[..] {
+            b"JPG" => "image/jpeg".to_string(),
+            b"PNG" => "image/png".to_string(),
+            b"GIF" => "image/gif".to_string(),
+            other => format!("image/{}", String::from_utf8_lossy(other).to_lowercase()),
+        }
+    } else {
+        latin1(bytes.take_terminated(false)?)
+    };
+    let picture_type = PictureType::from_code(u32::from(bytes.take(1)?[0]));
+    // Text encoded as UTF-16 is terminated by two nul bytes.
+    let wide = encoding == 1 || encoding == 2;
+    let description = bytes.take_terminated(wide)?;
+    let description = match encoding {
+        0 => latin1(description),
+        1 => utf16(description, None),
+        2 => utf16(description, Some(false)),
+        _ => String::from_utf8_lossy(description).into_owned(),
+    };
+    Some(Picture::new(
+        picture_type,
+        mime_type,
+        description,
+        (0, 0),
+        bytes.0.to_vec(),
+    ))
+}
+
+// The value of a 28-bit "syncsafe" integer, stored 7 bits per byte.
+#[cfg(feature = "wav")]
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 7) | u32::from(byte & 0x7F))
+}
+
+// Reverses unsynchronisation, which inserts a zero byte after every `0xFF` byte.
+#[cfg(feature = "wav")]
+fn resynchronise(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut previous = 0;
+    for &byte in bytes {
+        if !(previous == 0xFF && byte == 0) {
+            out.push(byte);
+        }
+        previous = byte;
+    }
+    out
+}
+
+// Decodes ISO-8859-1 text.
+#[cfg(feature = "wav")]
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+// Decodes UTF-16 text of the given endianness, or of the endianness given by its byte order mark
+// if `None`, which defaults to little-endian.
+#[cfg(feature = "wav")]
+fn utf16(bytes: &[u8], little_endian: Option<bool>) -> String {
+    let (bytes, little_endian) = match (little_endian, bytes) {
+        (Some(little_endian), _) => (bytes, little_endian),
+        (None, [0xFE, 0xFF, rest @ ..]) => (rest, false),
+        (None, [0xFF, 0xFE, rest @ ..]) => (rest, true),
+        (None, _) => (bytes, true),
+    };
+    let units = bytes.chunks_exact(2).map(|unit| match little_endian {
+        true => u16::from_le_bytes([unit[0], unit[1]]),
+        false => u16::from_be_bytes([unit[0], unit[1]]),
+    });
+    std::char::decode_utf16(units)
+        .map(|c| c.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+// Decodes standard base64, ignoring whitespace and padding.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        acc = (acc << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+// The width and height of PNG, JPEG or GIF data, read from its header.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    let be = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | u32::from(byte))
+    };
+
+    if data.starts_with(PNG_SIGNATURE) {
+        // The IHDR chunk comes first.
+        let ihdr = data.get(12..24)?;
+        if &ihdr[0..4] != b"IHDR" {
+            return None;
+        }
+        return Some((be(&ihdr[4..8]), be(&ihdr[8..12])));
+    }
+    if data.starts_with(b"GIF8") {
+        let screen = data.get(6..10)?;
+        let width = u16::from_le_bytes([screen[0], screen[1]]);
+        let height = u16::from_le_bytes([screen[2], screen[3]]);
+        return Some((u32::from(width), u32::from(height)));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // Walk the segments following the start of image marker until a start of frame.
+        let mut position = 2;
+        loop {
+            let segment = data.get(position..position + 4)?;
+            if segment[0] != 0xFF {
+                return None;
+            }
+            let marker = segment[1];
+            let len = be(&segment[2..4]) as usize;
+            // Start of frame markers, excluding those defining tables and arithmetic coding.
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                let frame = data.get(position + 5..position + 9)?;
+                return Some((be(&frame[2..4]), be(&frame[0..2])));
+            }
+            position += 2 + len;
+        }
+    }
+    None
+}
+
+// Reads fields from the front of a byte slice.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+struct Bytes<'a>(&'a [u8]);
+
+#[cfg(any(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+impl<'a> Bytes<'a> {
+    // Takes the next `len` bytes, or `None` if fewer remain.
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u32_be(&mut self) -> Option<u32> {
+        let bytes = self.take(4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Takes the bytes preceding the next nul terminator, skipping the terminator, which is two
+    // bytes aligned to a two byte boundary if `wide`.
+    #[cfg(feature = "wav")]
+    fn take_terminated(&mut self, wide: bool) -> Option<&'a [u8]> {
+        let len = if wide {
+            self.0
+                .chunks_exact(2)
+                .position(|unit| unit == [0, 0])
+                .map(|units| units * 2)
+        } else {
+            self.0.iter().position(|&byte| byte == 0)
+        }?;
+        let taken = self.take(len)?;
+        self.take(if wide { 2 } else { 1 })?;
+        Some(taken)
+    }
+}
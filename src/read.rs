@@ -1,16 +1,30 @@
 //! Items for reading and opening file formats from file.
 
-use crate::Format;
+use crate::broadcast::BroadcastExtension;
+use crate::layout::ChannelLayout;
+use crate::marker::{CueSheet, Marker};
+use crate::metadata::Metadata;
+use crate::mix::MixMatrix;
+use crate::picture::Picture;
+use crate::sampler::SamplerInfo;
+use crate::{Format, SampleFormat};
 
 #[cfg(feature = "caf")]
 use caf::{self, CafError};
 #[cfg(feature = "flac")]
 use claxon;
-#[cfg(feature = "wav")]
-use hound;
 #[cfg(feature = "ogg_vorbis")]
 use lewton;
 
+#[cfg(feature = "caf_alac")]
+pub use crate::caf_alac::AlacReader;
+#[cfg(feature = "flac")]
+pub use crate::flac::FlacReader;
+#[cfg(feature = "ogg_vorbis")]
+pub use crate::ogg_vorbis::OggVorbisReader;
+#[cfg(feature = "wav")]
+pub use crate::wav::{WavError, WavReader, WavSampleFormat, WavSpec};
+
 /// Types to which read samples may be converted via the `Reader::samples` method.
 pub trait Sample:
     dasp_sample::Sample
@@ -33,18 +47,19 @@ impl<T> Sample for T where
 }
 
 /// Returned by the `read` function, enumerates the various supported readers.
+#[allow(clippy::large_enum_variant)]
 pub enum Reader<R>
 where
     R: std::io::Read + std::io::Seek,
 {
     #[cfg(feature = "flac")]
-    Flac(claxon::FlacReader<R>),
+    Flac(FlacReader<R>),
     #[cfg(feature = "ogg_vorbis")]
-    OggVorbis(lewton::inside_ogg::OggStreamReader<R>),
+    OggVorbis(OggVorbisReader<R>),
     #[cfg(feature = "wav")]
-    Wav(hound::WavReader<R>),
+    Wav(WavReader<R>),
     #[cfg(feature = "caf_alac")]
-    CafAlac(super::caf_alac::AlacReader<R>),
+    CafAlac(AlacReader<R>),
 }
 
 /// An iterator that reads samples from the underlying reader, converts them to the sample type `S`
@@ -66,41 +81,23 @@ where
     #[cfg(feature = "flac")]
     Flac {
         sample_bits: u32,
-        flac_samples: claxon::FlacSamples<&'a mut claxon::input::BufferedReader<R>>,
+        reader: &'a mut FlacReader<R>,
     },
 
     #[cfg(feature = "flac")]
     FlacUnsupportedSampleBits(u32),
 
     #[cfg(feature = "ogg_vorbis")]
-    OggVorbis {
-        reader: &'a mut lewton::inside_ogg::OggStreamReader<R>,
-        index: usize,
-        buffer: Vec<i16>,
-    },
+    OggVorbis(&'a mut OggVorbisReader<R>),
 
     #[cfg(feature = "wav")]
-    Wav(WavSamples<'a, R>),
+    Wav(&'a mut WavReader<R>),
 
     #[cfg(feature = "wav")]
     WavUnsupportedSampleBits(u16),
 
     #[cfg(feature = "caf_alac")]
-    CafAlac {
-        reader: &'a mut super::caf_alac::AlacReader<R>,
-        index: usize,
-        buffer: Vec<i32>,
-    },
-}
-
-// The variants of hound's supported sample bit depths.
-#[cfg(feature = "wav")]
-enum WavSamples<'a, R: 'a> {
-    I8(hound::WavSamples<'a, R, i8>),
-    I16(hound::WavSamples<'a, R, i16>),
-    I24(hound::WavSamples<'a, R, i32>),
-    I32(hound::WavSamples<'a, R, i32>),
-    F32(hound::WavSamples<'a, R, f32>),
+    CafAlac(&'a mut AlacReader<R>),
 }
 
 /// An iterator that reads samples from the underlying reader, converts them to frames of type `F`
@@ -111,6 +108,10 @@ where
     F: dasp_frame::Frame,
 {
     samples: Samples<'a, R, F::Sample>,
+    // `None` if the audio has as many channels as `F`, in which case samples pass through as read.
+    mix: Option<MixMatrix>,
+    // The samples of the frame being mixed.
+    input: Vec<f32>,
     frame: std::marker::PhantomData<F>,
 }
 
@@ -123,6 +124,9 @@ pub struct Description {
     format: Format,
     channel_count: u32,
     sample_rate: u32,
+    frame_count: Option<u64>,
+    sample_format: SampleFormat,
+    bits_per_sample: Option<u32>,
 }
 
 /// Errors that might be returned from the `Reader::new` function.
@@ -143,13 +147,24 @@ pub enum FormatError {
     #[cfg(feature = "ogg_vorbis")]
     OggVorbis(lewton::VorbisError),
     #[cfg(feature = "wav")]
-    Wav(hound::Error),
+    Wav(WavError),
     #[cfg(feature = "wav")]
     WavUnsupportedSampleBits(u16),
     #[cfg(feature = "caf")]
     Caf(caf::CafError),
     #[cfg(feature = "alac")]
     Alac(()),
+    /// The frame type has a different number of channels to the audio being read.
+    ChannelMismatch { expected: u32, found: u32 },
+    /// The audio ended part way through a frame, leaving `found` of the `expected` samples.
+    ///
+    /// `frames_read` is the number of whole frames read by the failing call before the partial
+    /// frame was reached, such as those written to the buffer given to `read_frames_into`.
+    PartialFrame {
+        expected: u32,
+        found: u32,
+        frames_read: usize,
+    },
 }
 
 /// Attempts to open an audio `Reader` from the file at the specified `Path`.
@@ -182,6 +197,47 @@ impl Description {
     pub fn sample_rate(&self) -> u32 {
         self.sample_rate
     }
+
+    /// The total number of frames of audio, i.e. the number of samples per channel.
+    ///
+    /// This is read from the file's header (or, for Ogg Vorbis, the final page) without decoding
+    /// any audio. Returns `None` if the file does not specify its length.
+    pub fn frame_count(&self) -> Option<u64> {
+        self.frame_count
+    }
+
+    /// The total duration of the audio, derived from the `frame_count` and `sample_rate`.
+    ///
+    /// Returns `None` if the frame count is unknown.
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        let frame_count = self.frame_count?;
+        let sample_rate = u64::from(self.sample_rate);
+        if sample_rate == 0 {
+            return None;
+        }
+        let secs = frame_count / sample_rate;
+        let nanos = (frame_count % sample_rate) * 1_000_000_000 / sample_rate;
+        Some(std::time::Duration::new(secs, nanos as u32))
+    }
+
+    /// The encoding in which samples are natively stored by the format.
+    ///
+    /// Integer samples are reported using the smallest `SampleFormat` able to hold the
+    /// `bits_per_sample`, e.g. 20-bit ALAC audio is reported as `SampleFormat::I24`. Ogg Vorbis is
+    /// reported as `SampleFormat::LossyFloat`.
+    ///
+    /// Reading samples of a type with fewer bits than the native format, e.g. reading `i16`
+    /// samples from 24-bit audio, discards precision.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of significant bits stored for each sample, e.g. `20` for 20-bit audio.
+    ///
+    /// Returns `None` for lossy formats, which do not store samples at any particular bit depth.
+    pub fn bits_per_sample(&self) -> Option<u32> {
+        self.bits_per_sample
+    }
 }
 
 impl BufFileReader {
@@ -210,18 +266,17 @@ where
     /// `Reader` variant.
     ///
     /// The format is determined by attempting to construct each specific format reader until one
-    /// is successful.
+    /// is successful, with WAV files recognised by their RIFF, RF64 or BW64 header.
     pub fn new(mut reader: R) -> Result<Self, ReadError> {
         #[cfg(feature = "wav")]
         {
-            let is_wav = match hound::WavReader::new(&mut reader) {
-                Err(hound::Error::FormatError(_)) => false,
-                Err(err) => return Err(err.into()),
-                Ok(_) => true,
-            };
+            use std::io::Read;
+            let mut header = Vec::with_capacity(12);
+            reader.by_ref().take(12).read_to_end(&mut header)?;
             reader.seek(std::io::SeekFrom::Start(0))?;
+            let is_wav = super::wav::is_wav_header(&header);
             if is_wav {
-                return Ok(Reader::Wav(hound::WavReader::new(reader)?));
+                return Ok(Reader::Wav(WavReader::new(reader)?));
             }
         }
 
@@ -234,7 +289,7 @@ where
             };
             reader.seek(std::io::SeekFrom::Start(0))?;
             if is_flac {
-                return Ok(Reader::Flac(claxon::FlacReader::new(reader)?));
+                return Ok(Reader::Flac(FlacReader::new(reader)?));
             }
         }
 
@@ -250,15 +305,13 @@ where
             };
             reader.seek(std::io::SeekFrom::Start(0))?;
             if is_ogg_vorbis {
-                return Ok(Reader::OggVorbis(lewton::inside_ogg::OggStreamReader::new(
-                    reader,
-                )?));
+                return Ok(Reader::OggVorbis(OggVorbisReader::new(reader)?));
             }
         }
 
         #[cfg(feature = "caf_alac")]
         {
-            let is_caf_alac = match super::caf_alac::AlacReader::new(&mut reader) {
+            let is_caf_alac = match AlacReader::new(&mut reader) {
                 Err(FormatError::Caf(CafError::NotCaf)) => false,
                 Err(err) => return Err(err.into()),
                 // There is a CAF container, but no ALAC inside
@@ -268,9 +321,7 @@ where
             };
             reader.seek(std::io::SeekFrom::Start(0))?;
             if is_caf_alac {
-                return Ok(Reader::CafAlac(
-                    super::caf_alac::AlacReader::new(reader)?.unwrap(),
-                ));
+                return Ok(Reader::CafAlac(AlacReader::new(reader)?.unwrap()));
             }
         }
 
@@ -299,45 +350,231 @@ where
                 let info = reader.streaminfo();
                 Description {
                     format: Format::Flac,
-                    channel_count: info.channels as u32,
+                    channel_count: info.channels,
                     sample_rate: info.sample_rate,
+                    frame_count: info.samples,
+                    sample_format: SampleFormat::from_int_bits(info.bits_per_sample),
+                    bits_per_sample: Some(info.bits_per_sample),
                 }
             }
 
             #[cfg(feature = "ogg_vorbis")]
-            Reader::OggVorbis(ref reader) => Description {
-                format: Format::OggVorbis,
-                channel_count: u32::from(reader.ident_hdr.audio_channels),
-                sample_rate: reader.ident_hdr.audio_sample_rate as u32,
-            },
+            Reader::OggVorbis(ref reader) => {
+                let ident_hdr = &reader.stream_reader.ident_hdr;
+                Description {
+                    format: Format::OggVorbis,
+                    channel_count: u32::from(ident_hdr.audio_channels),
+                    sample_rate: ident_hdr.audio_sample_rate,
+                    frame_count: reader.frame_count(),
+                    sample_format: SampleFormat::LossyFloat,
+                    bits_per_sample: None,
+                }
+            }
 
             #[cfg(feature = "wav")]
             Reader::Wav(ref reader) => {
                 let spec = reader.spec();
+                let bits_per_sample = u32::from(spec.bits_per_sample);
+                let sample_format = match spec.sample_format {
+                    WavSampleFormat::Float if bits_per_sample > 32 => SampleFormat::F64,
+                    WavSampleFormat::Float => SampleFormat::F32,
+                    WavSampleFormat::Int => SampleFormat::from_int_bits(bits_per_sample),
+                };
                 Description {
                     format: Format::Wav,
                     channel_count: u32::from(spec.channels),
                     sample_rate: spec.sample_rate,
+                    frame_count: Some(reader.frame_count()),
+                    sample_format,
+                    bits_per_sample: Some(bits_per_sample),
                 }
             }
 
             #[cfg(feature = "caf_alac")]
             Reader::CafAlac(ref reader) => {
-                let desc = &reader.caf_reader.audio_desc;
+                let desc = &reader.audio_desc;
+                let bits_per_sample = u32::from(reader.alac_decoder.stream_info().bit_depth());
                 Description {
                     format: Format::CafAlac,
-                    channel_count: desc.channels_per_frame as u32,
-                    sample_rate: (1.0 / desc.sample_rate) as u32,
+                    channel_count: desc.channels_per_frame,
+                    sample_rate: desc.sample_rate as u32,
+                    frame_count: reader.frame_count(),
+                    sample_format: SampleFormat::from_int_bits(bits_per_sample),
+                    bits_per_sample: Some(bits_per_sample),
                 }
             }
         }
     }
 
+    /// The speaker position for which each channel of the audio is intended.
+    ///
+    /// The layout is taken from the WAV channel mask, the FLAC `WAVEFORMATEXTENSIBLE_CHANNEL_MASK`
+    /// comment or the CAF `chan` chunk where present. Otherwise the format's default channel
+    /// order for the channel count is assumed, e.g. the LFE channel of 5.1 audio is the fourth
+    /// channel of WAV and FLAC audio, but the sixth of Ogg Vorbis audio.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref reader) => reader.channel_layout(),
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(ref reader) => reader.channel_layout(),
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.channel_layout(),
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(ref reader) => reader.channel_layout(),
+        }
+    }
+
+    /// The tags describing the audio, such as its title and artist.
+    ///
+    /// Tags are read from the Vorbis comments of FLAC and Ogg Vorbis audio, the `LIST` chunks of
+    /// the `INFO` type within WAV files and the `info` chunks of CAF files.
+    pub fn metadata(&self) -> Metadata {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref reader) => reader.metadata(),
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(ref reader) => reader.metadata(),
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.metadata(),
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(ref reader) => reader.metadata(),
+        }
+    }
+
+    /// The named positions within the audio, in order of their position.
+    ///
+    /// Markers are read from the `cue ` chunk of WAV files, labelled by their `labl` chunks, the
+    /// `mark` chunk of CAF files, labelled by their `strg` chunk, and the start of each track of
+    /// the CUESHEET metadata block of FLAC audio (see `cue_sheet`). Ogg Vorbis audio holds no
+    /// markers.
+    pub fn markers(&self) -> Vec<Marker> {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref reader) => reader.markers(),
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(_) => Vec::new(),
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.markers(),
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(ref reader) => reader.markers(),
+        }
+    }
+
+    /// The cue sheet of FLAC audio, describing the tracks and index points of a CD.
+    ///
+    /// Returns `None` for audio without a CUESHEET metadata block and for other formats.
+    pub fn cue_sheet(&self) -> Option<CueSheet> {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref reader) => reader.cue_sheet().cloned(),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// The pitch and loop points of the audio for playback via a sampler, if any.
+    ///
+    /// These are read from the `smpl` chunk of WAV files, the `inst` chunk and looping regions of
+    /// the `regn` chunk of CAF files, and the `LOOPSTART` and `LOOPLENGTH` Vorbis comments of
+    /// FLAC and Ogg Vorbis audio. The latter give a single forward loop at the default pitch.
+    pub fn sampler_info(&self) -> Option<SamplerInfo> {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref reader) => reader.sampler_info(),
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(ref reader) => reader.sampler_info(),
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.sampler_info(),
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(ref reader) => reader.sampler_info(),
+        }
+    }
+
+    /// The contents of the `bext` chunk of a Broadcast Wave Format file, including the time
+    /// reference used to place the audio on a timeline.
+    ///
+    /// Returns `None` for WAV files without a `bext` chunk and for other formats.
+    pub fn broadcast_extension(&self) -> Option<BroadcastExtension> {
+        match *self {
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.broadcast_extension(),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// The text of the `iXML` chunk of a WAV file, as used by production sound recorders.
+    ///
+    /// The XML is returned as written rather than parsed. Returns `None` for WAV files without an
+    /// `iXML` chunk and for other formats.
+    pub fn ixml(&self) -> Option<String> {
+        match *self {
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.ixml(),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// The pictures embedded within the file, such as cover art.
+    ///
+    /// Pictures are read from the PICTURE metadata blocks of FLAC audio, the
+    /// `METADATA_BLOCK_PICTURE` comments of FLAC and Ogg Vorbis audio and the ID3v2 `APIC` frames
+    /// of `id3 ` chunks within WAV files. CAF files hold no pictures.
+    pub fn pictures(&self) -> Vec<Picture> {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref reader) => reader.pictures(),
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(ref reader) => reader.pictures(),
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref reader) => reader.pictures(),
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(_) => Vec::new(),
+        }
+    }
+
+    /// Reorders the channels of Ogg Vorbis and CAF audio to the canonical order of the WAV channel
+    /// mask, as used by WAV and FLAC.
+    ///
+    /// For example, the channels of 5.1 Ogg Vorbis audio are read as front left, front right,
+    /// centre, LFE, back left and back right rather than in the Vorbis order of front left,
+    /// centre, front right, back left, back right and LFE. Samples are reordered as they are
+    /// decoded, and `channel_layout` describes the new order. WAV and FLAC audio are always read
+    /// in canonical order.
+    pub fn with_canonical_channel_order(self) -> Self {
+        let order = self.channel_layout().canonical_order();
+        if order
+            .iter()
+            .enumerate()
+            .all(|(index, &channel)| index == channel)
+        {
+            return self;
+        }
+        match self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(reader) => Reader::Flac(reader),
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(mut reader) => {
+                reader.set_channel_order(order);
+                Reader::OggVorbis(reader)
+            }
+            #[cfg(feature = "wav")]
+            Reader::Wav(reader) => Reader::Wav(reader),
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(mut reader) => {
+                reader.set_channel_order(order);
+                Reader::CafAlac(reader)
+            }
+        }
+    }
+
     /// Produce an iterator that reads samples from the underlying reader, converts them to the
     /// sample type `S` if not already in that format and yields them.
     ///
     /// When reading from multiple channels, samples are **interleaved**.
-    pub fn samples<S>(&mut self) -> Samples<R, S>
+    pub fn samples<S>(&mut self) -> Samples<'_, R, S>
     where
         S: Sample,
     {
@@ -350,41 +587,25 @@ where
                 } else {
                     FormatSamples::Flac {
                         sample_bits,
-                        flac_samples: reader.samples(),
+                        reader,
                     }
                 }
             }
 
             #[cfg(feature = "ogg_vorbis")]
-            Reader::OggVorbis(ref mut reader) => FormatSamples::OggVorbis {
-                reader,
-                index: 0,
-                buffer: Vec::new(),
-            },
+            Reader::OggVorbis(ref mut reader) => FormatSamples::OggVorbis(reader),
 
             #[cfg(feature = "wav")]
             Reader::Wav(ref mut reader) => {
-                let spec = reader.spec();
-                match spec.sample_format {
-                    hound::SampleFormat::Int => match spec.bits_per_sample {
-                        8 => FormatSamples::Wav(WavSamples::I8(reader.samples())),
-                        16 => FormatSamples::Wav(WavSamples::I16(reader.samples())),
-                        24 => FormatSamples::Wav(WavSamples::I24(reader.samples())),
-                        32 => FormatSamples::Wav(WavSamples::I32(reader.samples())),
-                        _ => FormatSamples::WavUnsupportedSampleBits(spec.bits_per_sample),
-                    },
-                    hound::SampleFormat::Float => {
-                        FormatSamples::Wav(WavSamples::F32(reader.samples()))
-                    }
+                if reader.has_supported_encoding() {
+                    FormatSamples::Wav(reader)
+                } else {
+                    FormatSamples::WavUnsupportedSampleBits(reader.spec().bits_per_sample)
                 }
             }
 
             #[cfg(feature = "caf_alac")]
-            Reader::CafAlac(ref mut reader) => FormatSamples::CafAlac {
-                reader,
-                index: 0,
-                buffer: Vec::new(),
-            },
+            Reader::CafAlac(ref mut reader) => FormatSamples::CafAlac(reader),
         };
 
         Samples {
@@ -393,20 +614,300 @@ where
         }
     }
 
+    /// Reads interleaved samples into `buf`, converting them to the sample type `S`.
+    ///
+    /// Whole blocks or packets are decoded and converted at a time, making this considerably
+    /// faster than `samples` when reading large amounts of audio. Returns the number of samples
+    /// read, which is less than `buf.len()` only once the end of the audio is reached. If an error
+    /// occurs, `buf` may have been partially written.
+    pub fn read_into<S>(&mut self, buf: &mut [S]) -> Result<usize, FormatError>
+    where
+        S: Sample,
+    {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref mut reader) => {
+                let sample_bits = reader.streaminfo().bits_per_sample;
+                if sample_bits > 32 {
+                    return Err(FormatError::FlacUnsupportedSampleBits(sample_bits));
+                }
+                let read =
+                    reader.read_into(buf, |sample| flac_sample_to_sample(sample, sample_bits))?;
+                Ok(read)
+            }
+
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(ref mut reader) => {
+                Ok(reader.read_into(buf, dasp_sample::Sample::to_sample)?)
+            }
+
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref mut reader) => {
+                if !reader.has_supported_encoding() {
+                    let sample_bits = reader.spec().bits_per_sample;
+                    return Err(FormatError::WavUnsupportedSampleBits(sample_bits));
+                }
+                Ok(reader.read_into(buf)?)
+            }
+
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(ref mut reader) => {
+                reader.read_into(buf, dasp_sample::Sample::to_sample)
+            }
+        }
+    }
+
+    /// Reads frames into `buf`, converting their samples to the sample type of `F`.
+    ///
+    /// This is the frame equivalent of `read_into`, returning the number of frames read. The
+    /// frame type `F` must have the same number of channels as the underlying audio, otherwise
+    /// `FormatError::ChannelMismatch` is returned. If the audio ends part way through a frame,
+    /// `FormatError::PartialFrame` is returned.
+    pub fn read_frames_into<F>(&mut self, buf: &mut [F]) -> Result<usize, FormatError>
+    where
+        F: dasp_frame::Frame,
+        F::Sample: Sample,
+    {
+        // The number of samples decoded at a time before being gathered into frames.
+        const CHUNK_LEN: usize = 1024;
+
+        let channels = self.description().channel_count();
+        if channels as usize != F::CHANNELS {
+            return Err(FormatError::ChannelMismatch {
+                expected: F::CHANNELS as u32,
+                found: channels,
+            });
+        }
+        if F::CHANNELS == 0 {
+            return Ok(0);
+        }
+
+        // Frames wider than a chunk are read one at a time.
+        let frames_per_chunk = (CHUNK_LEN / F::CHANNELS).max(1);
+        let mut samples =
+            vec![<F::Sample as dasp_sample::Sample>::EQUILIBRIUM; frames_per_chunk * F::CHANNELS];
+        let mut read = 0;
+        for frames in buf.chunks_mut(frames_per_chunk) {
+            let len = frames.len() * F::CHANNELS;
+            let samples_read = self.read_into(&mut samples[..len])?;
+            let whole_frames = samples_read / F::CHANNELS;
+            let samples_read_whole = &samples[..whole_frames * F::CHANNELS];
+            for (frame, samples) in frames
+                .iter_mut()
+                .zip(samples_read_whole.chunks(F::CHANNELS))
+            {
+                *frame = F::from_fn(|channel| samples[channel]);
+            }
+            read += whole_frames;
+            if samples_read < len {
+                if samples_read % F::CHANNELS != 0 {
+                    return Err(FormatError::PartialFrame {
+                        expected: F::CHANNELS as u32,
+                        found: (samples_read % F::CHANNELS) as u32,
+                        frames_read: read,
+                    });
+                }
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Reads the remainder of the audio into a buffer of samples per channel, converting them to
+    /// the sample type `S`.
+    ///
+    /// The returned `Vec` holds one `Vec` of samples for each channel, in channel order. If the
+    /// audio ends part way through a frame, `FormatError::PartialFrame` is returned.
+    pub fn read_planar<S>(&mut self) -> Result<Vec<Vec<S>>, FormatError>
+    where
+        S: Sample,
+    {
+        // The number of frames by which the buffers grow before each read.
+        const CHUNK_FRAMES: usize = 4096;
+
+        let channels = self.description().channel_count() as usize;
+        let mut planar = vec![Vec::new(); channels];
+        let mut len = 0;
+        loop {
+            for channel in &mut planar {
+                channel.resize(len + CHUNK_FRAMES, <S as dasp_sample::Sample>::EQUILIBRIUM);
+            }
+            let mut buf = planar
+                .iter_mut()
+                .map(|channel| &mut channel[len..])
+                .collect::<Vec<_>>();
+            let read = self.read_planar_into(&mut buf).map_err(|err| match err {
+                FormatError::PartialFrame {
+                    expected,
+                    found,
+                    frames_read,
+                } => FormatError::PartialFrame {
+                    expected,
+                    found,
+                    frames_read: len + frames_read,
+                },
+                err => err,
+            })?;
+            len += read;
+            if read < CHUNK_FRAMES {
+                break;
+            }
+        }
+        for channel in &mut planar {
+            channel.truncate(len);
+        }
+        Ok(planar)
+    }
+
+    /// Reads samples into one slice per channel, converting them to the sample type `S`.
+    ///
+    /// `buf` must hold a slice for each channel of the underlying audio, otherwise
+    /// `FormatError::ChannelMismatch` is returned. Frames are read until the shortest slice is
+    /// full or the end of the audio is reached, returning the number of frames read. If the audio
+    /// ends part way through a frame, `FormatError::PartialFrame` is returned.
+    pub fn read_planar_into<S>(&mut self, buf: &mut [&mut [S]]) -> Result<usize, FormatError>
+    where
+        S: Sample,
+    {
+        // The number of interleaved samples decoded at a time before being distributed.
+        const CHUNK_LEN: usize = 4096;
+
+        let channels = self.description().channel_count() as usize;
+        if buf.len() != channels {
+            return Err(FormatError::ChannelMismatch {
+                expected: buf.len() as u32,
+                found: channels as u32,
+            });
+        }
+        let len = buf.iter().map(|channel| channel.len()).min().unwrap_or(0);
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let frames_per_chunk = (CHUNK_LEN / channels).max(1).min(len);
+        let mut samples =
+            vec![<S as dasp_sample::Sample>::EQUILIBRIUM; frames_per_chunk * channels];
+        let mut read = 0;
+        while read < len {
+            let frames = frames_per_chunk.min(len - read);
+            let samples_read = self.read_into(&mut samples[..frames * channels])?;
+            let whole_frames = samples_read / channels;
+            for (i, channel) in buf.iter_mut().enumerate() {
+                let interleaved = samples[i..].iter().step_by(channels);
+                for (sample, &interleaved) in channel[read..read + whole_frames]
+                    .iter_mut()
+                    .zip(interleaved)
+                {
+                    *sample = interleaved;
+                }
+            }
+            read += whole_frames;
+            if samples_read < frames * channels {
+                if samples_read % channels != 0 {
+                    return Err(FormatError::PartialFrame {
+                        expected: channels as u32,
+                        found: (samples_read % channels) as u32,
+                        frames_read: read,
+                    });
+                }
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Seeks to the frame at the given index, such that the next sample read belongs to it.
+    ///
+    /// The index is measured in frames, i.e. one sample per channel, from the beginning of the
+    /// audio. Seeking beyond the end of the audio positions the reader at the end.
+    ///
+    /// - WAV seeks directly to the frame's offset within the data chunk.
+    /// - FLAC seeks via the stream's SEEKTABLE, or by bisection over the frame headers if there
+    ///   is none, before decoding forward to the frame.
+    /// - Ogg Vorbis bisects granule positions to the page preceding the frame before decoding
+    ///   forward to the frame.
+    /// - ALAC within CAF seeks to the packet containing the frame via the packet table.
+    pub fn seek(&mut self, frame_index: u64) -> Result<(), FormatError> {
+        match *self {
+            #[cfg(feature = "flac")]
+            Reader::Flac(ref mut reader) => reader.seek(frame_index)?,
+            #[cfg(feature = "ogg_vorbis")]
+            Reader::OggVorbis(ref mut reader) => reader.seek(frame_index)?,
+            #[cfg(feature = "wav")]
+            Reader::Wav(ref mut reader) => reader.seek(frame_index).map_err(WavError::Io)?,
+            #[cfg(feature = "caf_alac")]
+            Reader::CafAlac(ref mut reader) => reader.seek(frame_index)?,
+        }
+        Ok(())
+    }
+
     /// Produce an iterator that yields read frames from the underlying `Reader`.
     ///
-    /// This method currently expects that the frame type `F` has the same number of channels as
-    /// stored in the underlying audio format.
+    /// If the frame type `F` has a different number of channels to the underlying audio, each
+    /// frame is mixed to `F`'s channel count using `MixMatrix::default_for`, e.g. mono audio read
+    /// as stereo frames is duplicated to both channels while stereo audio read as mono frames is
+    /// averaged. Otherwise samples are yielded exactly as read. See `frames_strict` for refusing
+    /// to read audio with a differing channel count.
+    ///
+    /// If the audio ends part way through a frame, `FormatError::PartialFrame` is yielded.
+    pub fn frames<F>(&mut self) -> Frames<'_, R, F>
+    where
+        F: dasp_frame::Frame,
+        F::Sample: Sample,
+    {
+        let channels = self.description().channel_count() as usize;
+        let mix = if channels == F::CHANNELS {
+            None
+        } else {
+            Some(MixMatrix::default_for(channels, F::CHANNELS))
+        };
+        self.frames_with_optional_mix(mix)
+    }
+
+    /// Produce an iterator that yields read frames from the underlying `Reader`, requiring that
+    /// the frame type `F` has the same number of channels as the underlying audio.
     ///
-    /// TODO: Should consider changing this behaviour to check the audio file's actual number of
-    /// channels and automatically convert to `F`'s number of channels while reading.
-    pub fn frames<F>(&mut self) -> Frames<R, F>
+    /// Returns `FormatError::ChannelMismatch` rather than mixing the audio if the channel counts
+    /// differ.
+    pub fn frames_strict<F>(&mut self) -> Result<Frames<'_, R, F>, FormatError>
     where
         F: dasp_frame::Frame,
         F::Sample: Sample,
     {
+        let channels = self.description().channel_count();
+        if channels as usize != F::CHANNELS {
+            return Err(FormatError::ChannelMismatch {
+                expected: F::CHANNELS as u32,
+                found: channels,
+            });
+        }
+        Ok(self.frames_with_optional_mix(None))
+    }
+
+    /// Produce an iterator that yields read frames from the underlying `Reader`, mixed from the
+    /// channels of the underlying audio using the given `matrix`.
+    ///
+    /// Each frame is formed from `matrix.input_channels()` samples, which should match the
+    /// channel count of the underlying audio. Channels of `F` beyond `matrix.output_channels()`
+    /// are left at equilibrium.
+    pub fn frames_with_mix<F>(&mut self, matrix: MixMatrix) -> Frames<'_, R, F>
+    where
+        F: dasp_frame::Frame,
+        F::Sample: Sample,
+    {
+        self.frames_with_optional_mix(Some(matrix))
+    }
+
+    fn frames_with_optional_mix<F>(&mut self, mix: Option<MixMatrix>) -> Frames<'_, R, F>
+    where
+        F: dasp_frame::Frame,
+        F::Sample: Sample,
+    {
+        let input = Vec::with_capacity(mix.as_ref().map_or(0, MixMatrix::input_channels));
         Frames {
             samples: self.samples(),
+            mix,
+            input,
             frame: std::marker::PhantomData,
         }
     }
@@ -423,12 +924,11 @@ where
             #[cfg(feature = "flac")]
             FormatSamples::Flac {
                 sample_bits,
-                ref mut flac_samples,
-            } => flac_samples.next().map(|sample| {
+                ref mut reader,
+            } => reader.next_sample().map(|sample| {
                 sample
                     .map_err(FormatError::Flac)
-                    .map(|sample| sample << (32 - sample_bits))
-                    .map(dasp_sample::Sample::to_sample)
+                    .map(|sample| flac_sample_to_sample(sample, sample_bits))
             }),
 
             #[cfg(feature = "flac")]
@@ -437,54 +937,16 @@ where
             }
 
             #[cfg(feature = "ogg_vorbis")]
-            FormatSamples::OggVorbis {
-                ref mut reader,
-                ref mut index,
-                ref mut buffer,
-            } => loop {
-                // Convert and return any pending samples.
-                if *index < buffer.len() {
-                    let sample = dasp_sample::Sample::to_sample(buffer[*index]);
-                    *index += 1;
-                    return Some(Ok(sample));
-                }
-
-                // If there are no samples left in the buffer, refill the buffer.
-                match reader.read_dec_packet_itl() {
-                    Ok(Some(packet)) => {
-                        std::mem::replace(buffer, packet);
-                        *index = 0;
-                    }
-                    Ok(None) => return None,
-                    Err(err) => return Some(Err(err.into())),
-                }
-            },
+            FormatSamples::OggVorbis(ref mut reader) => reader.next_sample().map(|sample| {
+                sample
+                    .map_err(FormatError::OggVorbis)
+                    .map(dasp_sample::Sample::to_sample)
+            }),
 
             #[cfg(feature = "wav")]
-            FormatSamples::Wav(ref mut wav_samples) => {
-                macro_rules! next_sample {
-                    ($samples:expr) => {{
-                        $samples.next().map(|sample| {
-                            sample
-                                .map_err(FormatError::Wav)
-                                .map(dasp_sample::Sample::to_sample)
-                        })
-                    }};
-                }
-
-                match *wav_samples {
-                    WavSamples::I8(ref mut samples) => next_sample!(samples),
-                    WavSamples::I16(ref mut samples) => next_sample!(samples),
-                    WavSamples::I24(ref mut samples) => samples.next().map(|sample| {
-                        sample
-                            .map_err(FormatError::Wav)
-                            .map(dasp_sample::I24::new_unchecked)
-                            .map(dasp_sample::Sample::to_sample)
-                    }),
-                    WavSamples::I32(ref mut samples) => next_sample!(samples),
-                    WavSamples::F32(ref mut samples) => next_sample!(samples),
-                }
-            }
+            FormatSamples::Wav(ref mut reader) => reader
+                .next_sample()
+                .map(|sample| sample.map_err(FormatError::Wav)),
 
             #[cfg(feature = "wav")]
             FormatSamples::WavUnsupportedSampleBits(sample_bits) => {
@@ -492,28 +954,9 @@ where
             }
 
             #[cfg(feature = "caf_alac")]
-            FormatSamples::CafAlac {
-                ref mut reader,
-                ref mut index,
-                ref mut buffer,
-            } => loop {
-                // Convert and return any pending samples.
-                if *index < buffer.len() {
-                    let sample = dasp_sample::Sample::to_sample(buffer[*index]);
-                    *index += 1;
-                    return Some(Ok(sample));
-                }
-
-                // If there are no samples left in the buffer, refill the buffer.
-                match reader.read_packet() {
-                    Ok(Some(packet)) => {
-                        std::mem::replace(buffer, packet);
-                        *index = 0;
-                    }
-                    Ok(None) => return None,
-                    Err(err) => return Some(Err(err)),
-                }
-            },
+            FormatSamples::CafAlac(ref mut reader) => reader
+                .next_sample()
+                .map(|sample| sample.map(dasp_sample::Sample::to_sample)),
         }
     }
 }
@@ -526,6 +969,39 @@ where
 {
     type Item = Result<F, FormatError>;
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ref mix) = self.mix {
+            if mix.input_channels() == 0 {
+                return None;
+            }
+            self.input.clear();
+            for read in 0..mix.input_channels() {
+                match self.samples.next() {
+                    Some(Ok(sample)) => {
+                        let sample = dasp_sample::Sample::to_float_sample(sample);
+                        self.input.push(dasp_sample::Sample::to_sample(sample));
+                    }
+                    Some(Err(error)) => return Some(Err(error)),
+                    None if read == 0 => return None,
+                    None => {
+                        return Some(Err(FormatError::PartialFrame {
+                            expected: mix.input_channels() as u32,
+                            found: read as u32,
+                            frames_read: 0,
+                        }))
+                    }
+                }
+            }
+            let input = &self.input;
+            let frame = F::from_fn(|channel| {
+                if channel < mix.output_channels() {
+                    dasp_sample::Sample::to_sample(mix.mix(channel, input))
+                } else {
+                    <F::Sample as dasp_sample::Sample>::EQUILIBRIUM
+                }
+            });
+            return Some(Ok(frame));
+        }
+
         enum FrameConstruction {
             NotEnoughSamples,
             Ok,
@@ -533,8 +1009,12 @@ where
         }
 
         let mut result = FrameConstruction::Ok;
+        let mut read = 0;
         let frame = F::from_fn(|_| match self.samples.next() {
-            Some(Ok(sample)) => sample,
+            Some(Ok(sample)) => {
+                read += 1;
+                sample
+            }
             Some(Err(error)) => {
                 result = FrameConstruction::Err(error);
                 <F::Sample as dasp_sample::Sample>::EQUILIBRIUM
@@ -548,7 +1028,12 @@ where
         match result {
             FrameConstruction::Ok => Some(Ok(frame)),
             FrameConstruction::Err(error) => Some(Err(error)),
-            FrameConstruction::NotEnoughSamples => None,
+            FrameConstruction::NotEnoughSamples if read == 0 => None,
+            FrameConstruction::NotEnoughSamples => Some(Err(FormatError::PartialFrame {
+                expected: F::CHANNELS as u32,
+                found: read,
+                frames_read: 0,
+            })),
         }
     }
 }
@@ -568,8 +1053,8 @@ impl From<lewton::VorbisError> for FormatError {
 }
 
 #[cfg(feature = "wav")]
-impl From<hound::Error> for FormatError {
-    fn from(err: hound::Error) -> Self {
+impl From<WavError> for FormatError {
+    fn from(err: WavError) -> Self {
         FormatError::Wav(err)
     }
 }
@@ -597,6 +1082,7 @@ impl From<std::io::Error> for ReadError {
 }
 
 impl std::error::Error for FormatError {
+    #[allow(deprecated)]
     fn description(&self) -> &str {
         match *self {
             #[cfg(feature = "flac")]
@@ -617,9 +1103,13 @@ impl std::error::Error for FormatError {
             FormatError::Caf(ref err) => std::error::Error::description(err),
             #[cfg(feature = "alac")]
             FormatError::Alac(_) => "Alac decode error",
+            FormatError::ChannelMismatch { .. } => {
+                "The frame type has a different number of channels to the audio"
+            }
+            FormatError::PartialFrame { .. } => "The audio ended part way through a frame",
         }
     }
-    fn cause(&self) -> Option<&dyn std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             #[cfg(feature = "flac")]
             FormatError::Flac(ref err) => Some(err),
@@ -635,11 +1125,13 @@ impl std::error::Error for FormatError {
             FormatError::Caf(ref err) => Some(err),
             #[cfg(feature = "alac")]
             FormatError::Alac(_) => None,
+            FormatError::ChannelMismatch { .. } | FormatError::PartialFrame { .. } => None,
         }
     }
 }
 
 impl std::error::Error for ReadError {
+    #[allow(deprecated)]
     fn description(&self) -> &str {
         match *self {
             ReadError::Io(ref err) => std::error::Error::description(err),
@@ -647,7 +1139,7 @@ impl std::error::Error for ReadError {
             ReadError::UnsupportedFormat => "no supported format was detected",
         }
     }
-    fn cause(&self) -> Option<&dyn std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             ReadError::Io(ref err) => Some(err),
             ReadError::Reader(ref err) => Some(err),
@@ -676,7 +1168,22 @@ impl std::fmt::Display for FormatError {
             #[cfg(feature = "caf")]
             FormatError::Caf(ref err) => err.fmt(f),
             #[cfg(feature = "alac")]
-            FormatError::Alac(_) => write!(f, "{}", std::error::Error::description(self)),
+            FormatError::Alac(_) => write!(f, "Alac decode error"),
+            FormatError::ChannelMismatch { expected, found } => write!(
+                f,
+                "expected audio with {} channels but found {}",
+                expected, found
+            ),
+            FormatError::PartialFrame {
+                expected,
+                found,
+                frames_read,
+            } => write!(
+                f,
+                "audio ended with a partial frame of {} samples, expected {}, after reading {} \
+                 frames",
+                found, expected, frames_read
+            ),
         }
     }
 }
@@ -686,7 +1193,26 @@ impl std::fmt::Display for ReadError {
         match *self {
             ReadError::Io(ref err) => err.fmt(f),
             ReadError::Reader(ref err) => err.fmt(f),
-            ReadError::UnsupportedFormat => write!(f, "{}", std::error::Error::description(self)),
+            ReadError::UnsupportedFormat => write!(f, "no supported format was detected"),
         }
     }
 }
+
+// Converts a right-aligned FLAC sample of the given bit depth to the sample type `S`.
+//
+// The sample is first left-aligned within the smallest integer type of at least `sample_bits`
+// bits, matching the containers in which WAV stores samples of the same bit depth, so that full
+// scale maps to the same normalised range as the WAV reader.
+#[cfg(feature = "flac")]
+fn flac_sample_to_sample<S>(sample: i32, sample_bits: u32) -> S
+where
+    S: Sample,
+{
+    use dasp_sample::{Sample as _, I24};
+    match sample_bits {
+        0..=8 => ((sample << (8 - sample_bits)) as i8).to_sample(),
+        9..=16 => ((sample << (16 - sample_bits)) as i16).to_sample(),
+        17..=24 => I24::new_unchecked(sample << (24 - sample_bits)).to_sample(),
+        _ => (sample << (32 - sample_bits)).to_sample(),
+    }
+}
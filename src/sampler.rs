@@ -0,0 +1,316 @@
+//! Items for the pitch and loop points used when playing audio back via a sampler.
+
+/// The Vorbis comments giving the start and length of a loop as a number of frames.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+const LOOP_START: &str = "LOOPSTART";
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+const LOOP_LENGTH: &str = "LOOPLENGTH";
+
+/// The flags and marker types of the regions of a CAF `regn` chunk.
+#[cfg(feature = "caf_alac")]
+const CAF_LOOP_ENABLE: u32 = 1;
+#[cfg(feature = "caf_alac")]
+const CAF_PLAY_FORWARD: u32 = 2;
+#[cfg(feature = "caf_alac")]
+const CAF_PLAY_BACKWARD: u32 = 4;
+#[cfg(feature = "caf_alac")]
+const CAF_LOOP_STARTS: [&[u8; 4]; 3] = [b"rbeg", b"slbg", b"rlbg"];
+#[cfg(feature = "caf_alac")]
+const CAF_LOOP_ENDS: [&[u8; 4]; 3] = [b"rend", b"slen", b"rlen"];
+
+/// The pitch and loop points of audio that is to be played back via a sampler, e.g. as stored
+/// within the `smpl` chunk of a WAV file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplerInfo {
+    /// The MIDI note at which the audio plays back at its original pitch, `60` being middle C.
+    pub root_note: u8,
+    /// The number of cents by which the pitch of the audio lies above `root_note`.
+    pub fine_tune: f32,
+    /// The loops of the audio, in the order of the file.
+    pub loops: Vec<SampleLoop>,
+}
+
+/// A section of audio that is repeated during playback.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SampleLoop {
+    /// The position of the first frame of the loop.
+    pub start: u64,
+    /// The position following the last frame of the loop, such that `end - start` is the length
+    /// of the loop in frames.
+    pub end: u64,
+    /// The direction in which the loop is played.
+    pub loop_type: LoopType,
+    /// The number of times that the loop is played, where `0` repeats it indefinitely.
+    pub play_count: u32,
+}
+
+/// The direction in which a `SampleLoop` is played.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LoopType {
+    /// The loop is played from start to end.
+    Forward,
+    /// The loop is played from start to end and then from end to start, alternately.
+    Alternating,
+    /// The loop is played from end to start.
+    Backward,
+    /// A type specific to the manufacturer of a sampler, given by its code within a `smpl` chunk.
+    Other(u32),
+}
+
+impl Default for SamplerInfo {
+    /// Middle C without any fine tuning or loops.
+    fn default() -> Self {
+        SamplerInfo {
+            root_note: 60,
+            fine_tune: 0.0,
+            loops: Vec::new(),
+        }
+    }
+}
+
+impl LoopType {
+    /// The type given by the code of a loop within a `smpl` chunk.
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0 => LoopType::Forward,
+            1 => LoopType::Alternating,
+            2 => LoopType::Backward,
+            code => LoopType::Other(code),
+        }
+    }
+
+    /// The code of the type for a loop within a `smpl` chunk.
+    pub fn code(self) -> u32 {
+        match self {
+            LoopType::Forward => 0,
+            LoopType::Alternating => 1,
+            LoopType::Backward => 2,
+            LoopType::Other(code) => code,
+        }
+    }
+}
+
+// The root note and fine tuning of a pitch given as a fractional MIDI note.
+#[cfg(any(feature = "caf_alac", feature = "wav"))]
+fn from_pitch(pitch: f64) -> (u8, f32) {
+    let root_note = pitch.floor().clamp(0.0, 127.0);
+    (root_note as u8, ((pitch - root_note) * 100.0) as f32)
+}
+
+// The pitch given by a root note and fine tuning as a fractional MIDI note.
+#[cfg(any(feature = "caf_alac", feature = "wav"))]
+fn to_pitch(info: &SamplerInfo) -> f64 {
+    f64::from(info.root_note) + f64::from(info.fine_tune) / 100.0
+}
+
+// Reads a loop from the `LOOPSTART` and `LOOPLENGTH` Vorbis comments, with the pitch left at its
+// default.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+pub(crate) fn read_vorbis_comments(metadata: &crate::Metadata) -> Option<SamplerInfo> {
+    let start: u64 = metadata.get(LOOP_START)?.trim().parse().ok()?;
+    let length: u64 = metadata.get(LOOP_LENGTH)?.trim().parse().ok()?;
+    Some(SamplerInfo {
+        loops: vec![SampleLoop {
+            start,
+            end: start.saturating_add(length),
+            loop_type: LoopType::Forward,
+            play_count: 0,
+        }],
+        ..SamplerInfo::default()
+    })
+}
+
+// Replaces the `LOOPSTART` and `LOOPLENGTH` comments of `metadata` with those of the first loop.
+#[cfg(feature = "flac")]
+pub(crate) fn write_vorbis_comments(info: &SamplerInfo, metadata: &mut crate::Metadata) {
+    metadata.remove(LOOP_START);
+    metadata.remove(LOOP_LENGTH);
+    if let Some(first) = info.loops.first() {
+        metadata.push(LOOP_START, first.start.to_string());
+        let length = first.end.saturating_sub(first.start);
+        metadata.push(LOOP_LENGTH, length.to_string());
+    }
+}
+
+// Reads the contents of a RIFF `smpl` chunk.
+#[cfg(feature = "wav")]
+pub(crate) fn read_riff_smpl(smpl: &[u8]) -> Option<SamplerInfo> {
+    let u32_le = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    // The manufacturer, product, sample period, MIDI unity note, MIDI pitch fraction, SMPTE
+    // format, SMPTE offset, number of loops and length of the trailing sampler data.
+    let header = smpl.get(..36)?;
+    let fraction = f64::from(u32_le(&header[16..20])) / (f64::from(u32::MAX) + 1.0);
+    let (root_note, fine_tune) = from_pitch(f64::from(u32_le(&header[12..16])) + fraction);
+    let count = u32_le(&header[28..32]) as usize;
+    // The cue point identifier, type, start, inclusive end, fraction and play count of each loop.
+    let loops = smpl[36..]
+        .chunks_exact(24)
+        .take(count)
+        .map(|point| SampleLoop {
+            start: u64::from(u32_le(&point[8..12])),
+            end: u64::from(u32_le(&point[12..16])) + 1,
+            loop_type: LoopType::from_code(u32_le(&point[4..8])),
+            play_count: u32_le(&point[20..24]),
+        })
+        .collect();
+    Some(SamplerInfo {
+        root_note,
+        fine_tune,
+        loops,
+    })
+}
+
+// The contents of a RIFF `smpl` chunk describing `info` for audio of the given sample rate.
+#[cfg(feature = "wav")]
+pub(crate) fn riff_smpl(info: &SamplerInfo, sample_rate: u32) -> Vec<u8> {
+    let to_u32 = |frame: u64| frame.min(u64::from(u32::MAX)) as u32;
+    let pitch = to_pitch(info);
+    let root_note = pitch.floor().max(0.0);
+    let fraction = ((pitch - root_note) * (f64::from(u32::MAX) + 1.0)).min(f64::from(u32::MAX));
+    // The sample period is given in nanoseconds.
+    let sample_period = (1e9 / f64::from(sample_rate.max(1))).round() as u32;
+
+    let mut smpl = Vec::with_capacity(36 + 24 * info.loops.len());
+    let header = [
+        0,
+        0,
+        sample_period,
+        root_note as u32,
+        fraction as u32,
+        0,
+        0,
+        info.loops.len() as u32,
+        0,
+    ];
+    for field in &header {
+        smpl.extend_from_slice(&field.to_le_bytes());
+    }
+    for (id, sample_loop) in info.loops.iter().enumerate() {
+        let fields = [
+            id as u32,
+            sample_loop.loop_type.code(),
+            to_u32(sample_loop.start),
+            to_u32(sample_loop.end.saturating_sub(1).max(sample_loop.start)),
+            0,
+            sample_loop.play_count,
+        ];
+        for field in &fields {
+            smpl.extend_from_slice(&field.to_le_bytes());
+        }
+    }
+    smpl
+}
+
+// Reads the pitch from a CAF `inst` chunk and the loops from the looping regions of a `regn`
+// chunk, in the order of the file.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn read_caf_instrument(inst: Option<&[u8]>, regn: Option<&[u8]>) -> Option<SamplerInfo> {
+    let u32_be = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let mut info = SamplerInfo::default();
+    // The base note, MIDI note and velocity ranges, gain and region identifiers.
+    let inst = inst.and_then(|inst| inst.get(..28));
+    if let Some(inst) = inst {
+        let base_note = f32::from_be_bytes([inst[0], inst[1], inst[2], inst[3]]);
+        let (root_note, fine_tune) = from_pitch(f64::from(base_note));
+        info.root_note = root_note;
+        info.fine_tune = fine_tune;
+    }
+
+    // The SMPTE time type and number of regions, followed by the identifier, flags and markers of
+    // each region.
+    let regn = regn.unwrap_or_default();
+    let count = regn.get(4..8).map(u32_be).unwrap_or(0);
+    let mut position = 8;
+    for _ in 0..count {
+        let region = match regn.get(position..position + 12) {
+            Some(region) => region,
+            None => break,
+        };
+        let flags = u32_be(&region[4..8]);
+        let marker_count = u32_be(&region[8..12]) as usize;
+        let markers_len = marker_count.saturating_mul(28);
+        let markers = match regn.get(position + 12..(position + 12).saturating_add(markers_len)) {
+            Some(markers) => markers,
+            None => break,
+        };
+        position += 12 + markers_len;
+        if flags & CAF_LOOP_ENABLE == 0 {
+            continue;
+        }
+        // The type, frame position, string identifier, SMPTE time and channel of each marker.
+        let frame = |types: &[&[u8; 4]]| {
+            markers
+                .chunks_exact(28)
+                .find(|marker| types.iter().any(|&t| &marker[..4] == t))
+                .map(|marker| {
+                    let mut frame = [0; 8];
+                    frame.copy_from_slice(&marker[4..12]);
+                    f64::from_be_bytes(frame).max(0.0).round() as u64
+                })
+        };
+        let (start, end) = match (frame(&CAF_LOOP_STARTS), frame(&CAF_LOOP_ENDS)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+        let loop_type = match (
+            flags & CAF_PLAY_FORWARD != 0,
+            flags & CAF_PLAY_BACKWARD != 0,
+        ) {
+            (true, true) => LoopType::Alternating,
+            (false, true) => LoopType::Backward,
+            _ => LoopType::Forward,
+        };
+        info.loops.push(SampleLoop {
+            start,
+            end,
+            loop_type,
+            play_count: 0,
+        });
+    }
+
+    if inst.is_none() && info.loops.is_empty() {
+        return None;
+    }
+    Some(info)
+}
+
+// The contents of a CAF `inst` chunk giving the pitch of `info`, followed by those of a `regn`
+// chunk holding a looping region for each loop.
+//
+// The first loop is referenced as the sustain region of the instrument. Play counts are not
+// represented and loops of manufacturer-specific types are written as forward loops.
+#[cfg(feature = "caf_alac")]
+pub(crate) fn caf_instrument(info: &SamplerInfo) -> (Vec<u8>, Vec<u8>) {
+    let mut inst = Vec::with_capacity(28);
+    inst.extend_from_slice(&(to_pitch(info) as f32).to_be_bytes());
+    // The full range of MIDI notes and velocities, with no gain.
+    inst.extend_from_slice(&[0, 127, 1, 127]);
+    inst.extend_from_slice(&0f32.to_be_bytes());
+    let sustain_region = if info.loops.is_empty() { 0u32 } else { 1 };
+    for &field in &[0, sustain_region, 0, 0] {
+        inst.extend_from_slice(&field.to_be_bytes());
+    }
+
+    let mut regn = Vec::new();
+    regn.extend_from_slice(&0u32.to_be_bytes());
+    regn.extend_from_slice(&(info.loops.len() as u32).to_be_bytes());
+    for (index, sample_loop) in info.loops.iter().enumerate() {
+        let direction = match sample_loop.loop_type {
+            LoopType::Alternating => CAF_PLAY_FORWARD | CAF_PLAY_BACKWARD,
+            LoopType::Backward => CAF_PLAY_BACKWARD,
+            LoopType::Forward | LoopType::Other(_) => CAF_PLAY_FORWARD,
+        };
+        for &field in &[index as u32 + 1, CAF_LOOP_ENABLE | direction, 2] {
+            regn.extend_from_slice(&field.to_be_bytes());
+        }
+        for &(marker_type, frame) in &[(b"rbeg", sample_loop.start), (b"rend", sample_loop.end)] {
+            regn.extend_from_slice(marker_type);
+            regn.extend_from_slice(&(frame as f64).to_be_bytes());
+            // No string identifier, SMPTE time or channel.
+            regn.extend_from_slice(&[0; 16]);
+        }
+    }
+    (inst, regn)
+}
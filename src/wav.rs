@@ -0,0 +1,832 @@
+//! WAV decoding of RIFF, RF64 and BW64 files, retaining every chunk besides `data`, and a WAV
+//! encoder able to write those chunks.
+//!
+//! Neither is built on hound, as hound cannot read the `ds64` chunk that gives the sizes of RF64
+//! and BW64 files larger than 4 GiB, nor write chunks other than `fmt ` and `data`.
+
+use crate::broadcast::{self, BroadcastExtension};
+use crate::layout::ChannelLayout;
+use crate::marker::{self, Marker};
+use crate::metadata::{self, Metadata};
+use crate::picture::{self, Picture};
+use crate::read::Sample;
+use crate::sampler::{self, SamplerInfo};
+use crate::write::Spec;
+use crate::SampleFormat;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The `SubFormat` GUIDs of a `WAVE_FORMAT_EXTENSIBLE` header.
+const KSDATAFORMAT_SUBTYPE_PCM: [u8; 16] = [
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: [u8; 16] = [
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// The size of a 32-bit chunk or RIFF size field whose actual size is given by the `ds64` chunk.
+const DS64_SIZE: u32 = u32::MAX;
+
+/// The length of a `ds64` chunk without a table of chunk sizes: the RIFF, `data` and sample
+/// counts followed by the length of the table.
+const DS64_LEN: usize = 28;
+
+/// The number of samples decoded at a time by `WavReader::read_into`.
+const READ_BLOCK_LEN: usize = 1024;
+
+/// Whether or not the given 12 bytes begin a RIFF, RF64 or BW64 file of the `WAVE` form.
+pub fn is_wav_header(header: &[u8]) -> bool {
+    header.len() >= 12
+        && [&b"RIFF"[..], b"RF64", b"BW64"].contains(&&header[..4])
+        && &header[8..12] == b"WAVE"
+}
+
+// The identifier and contents of a chunk.
+type Chunk = ([u8; 4], Vec<u8>);
+
+// The chunks of a WAVE file.
+struct RiffChunks {
+    // Every chunk besides `data`, in the order of the file.
+    chunks: Vec<Chunk>,
+    // The position and length of the contents of the first `data` chunk, if any.
+    data: Option<(u64, u64)>,
+}
+
+/// The format of the audio of a WAV file, as given by its `fmt ` chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WavSpec {
+    /// The number of channels of audio.
+    pub channels: u16,
+    /// The number of frames per second.
+    pub sample_rate: u32,
+    /// The number of significant bits of each sample, which may be fewer than are used to store
+    /// it.
+    pub bits_per_sample: u16,
+    /// Whether samples are stored as integers or floating point numbers.
+    pub sample_format: WavSampleFormat,
+}
+
+/// The representation of the samples of a WAV file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WavSampleFormat {
+    /// Integer samples, with 8-bit samples alone stored unsigned.
+    Int,
+    /// IEEE floating point samples.
+    Float,
+}
+
+/// Errors that might occur while reading a WAV file.
+#[derive(Debug)]
+pub enum WavError {
+    /// An I/O error, including the file ending part way through the audio.
+    Io(io::Error),
+    /// The file is not a well-formed WAVE file, for the given reason.
+    Malformed(&'static str),
+    /// The encoding of the audio is not supported.
+    Unsupported,
+}
+
+// The encodings of samples that can be decoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    U8,
+    I16,
+    I24,
+    // 24-bit samples held by the low bytes of 4.
+    I24In32,
+    I32,
+    F32,
+}
+
+/// Decodes the samples of a RIFF, RF64 or BW64 WAVE file, retaining the contents of the file's
+/// other chunks.
+pub struct WavReader<R>
+where
+    R: Read + Seek,
+{
+    reader: R,
+    // Every chunk besides `data`, in the order of the file.
+    chunks: Vec<Chunk>,
+    spec: WavSpec,
+    // `None` where samples of the spec's format and bit depth cannot be decoded.
+    encoding: Option<Encoding>,
+    bytes_per_sample: usize,
+    // Position of the contents of the `data` chunk.
+    data_position: u64,
+    sample_count: u64,
+    samples_read: u64,
+}
+
+impl<R> WavReader<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the chunks of the file, leaving the reader at the start of the audio.
+    ///
+    /// The sizes of RF64 and BW64 files are read from the `ds64` chunk where their 32-bit sizes
+    /// are `0xFFFFFFFF`.
+    pub fn new(mut reader: R) -> Result<Self, WavError> {
+        let RiffChunks { chunks, data } = read_chunks(&mut reader)?;
+        let fmt = chunks
+            .iter()
+            .find(|(id, _)| id == b"fmt ")
+            .map(|(_, contents)| &contents[..])
+            .ok_or(WavError::Malformed("no fmt chunk found"))?;
+        let (spec, bytes_per_sample) = read_fmt(fmt)?;
+        let (data_position, data_len) = data.ok_or(WavError::Malformed("no data chunk found"))?;
+
+        let bytes_per_sample = usize::from(bytes_per_sample);
+        let block_align = bytes_per_sample as u64 * u64::from(spec.channels);
+        if data_len % block_align != 0 {
+            return Err(WavError::Malformed("invalid data chunk length"));
+        }
+        let encoding = match (spec.sample_format, bytes_per_sample, spec.bits_per_sample) {
+            (WavSampleFormat::Int, 1, 8) => Some(Encoding::U8),
+            (WavSampleFormat::Int, 2, 16) => Some(Encoding::I16),
+            (WavSampleFormat::Int, 3, 24) => Some(Encoding::I24),
+            (WavSampleFormat::Int, 4, 24) => Some(Encoding::I24In32),
+            (WavSampleFormat::Int, 4, 32) => Some(Encoding::I32),
+            (WavSampleFormat::Float, 4, 32) => Some(Encoding::F32),
+            _ => None,
+        };
+
+        reader.seek(SeekFrom::Start(data_position))?;
+        Ok(WavReader {
+            reader,
+            chunks,
+            spec,
+            encoding,
+            bytes_per_sample,
+            data_position,
+            sample_count: data_len / bytes_per_sample as u64,
+            samples_read: 0,
+        })
+    }
+
+    /// The sample format, channel count and sample rate of the audio.
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Whether or not samples of the spec's format and bit depth can be decoded.
+    ///
+    /// These are 8, 16, 24 and 32-bit integer samples and 32-bit float samples.
+    pub fn has_supported_encoding(&self) -> bool {
+        self.encoding.is_some()
+    }
+
+    /// The number of frames of audio held by the `data` chunk.
+    pub fn frame_count(&self) -> u64 {
+        self.sample_count / u64::from(self.spec.channels)
+    }
+
+    /// Reads and converts the next sample, or returns `None` once every sample has been read.
+    pub fn next_sample<S>(&mut self) -> Option<Result<S, WavError>>
+    where
+        S: Sample,
+    {
+        if self.samples_read == self.sample_count {
+            return None;
+        }
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => return Some(Err(WavError::Unsupported)),
+        };
+        let mut bytes = [0; 4];
+        let bytes = &mut bytes[..self.bytes_per_sample];
+        if let Err(err) = self.reader.read_exact(bytes) {
+            // The remaining samples cannot be located once a read fails part way through one.
+            self.samples_read = self.sample_count;
+            return Some(Err(err.into()));
+        }
+        self.samples_read += 1;
+        Some(Ok(decode(encoding, bytes)))
+    }
+
+    /// Reads and converts samples into `buf` a block at a time, returning the number of samples
+    /// read.
+    pub fn read_into<S>(&mut self, buf: &mut [S]) -> Result<usize, WavError>
+    where
+        S: Sample,
+    {
+        let encoding = self.encoding.ok_or(WavError::Unsupported)?;
+        let remaining = self.sample_count - self.samples_read;
+        let len = remaining.min(buf.len() as u64) as usize;
+        let mut bytes = [0; READ_BLOCK_LEN * 4];
+        for block in buf[..len].chunks_mut(READ_BLOCK_LEN) {
+            let bytes = &mut bytes[..block.len() * self.bytes_per_sample];
+            if let Err(err) = self.reader.read_exact(bytes) {
+                self.samples_read = self.sample_count;
+                return Err(err.into());
+            }
+            for (sample, bytes) in block
+                .iter_mut()
+                .zip(bytes.chunks_exact(self.bytes_per_sample))
+            {
+                *sample = decode(encoding, bytes);
+            }
+            self.samples_read += block.len() as u64;
+        }
+        Ok(len)
+    }
+
+    /// Seeks to the frame at the given index, or to the end of the audio if it lies beyond it.
+    pub fn seek(&mut self, frame_index: u64) -> io::Result<()> {
+        let frame_index = frame_index.min(self.frame_count());
+        let sample_index = frame_index * u64::from(self.spec.channels);
+        let offset = sample_index * self.bytes_per_sample as u64;
+        self.reader
+            .seek(SeekFrom::Start(self.data_position + offset))?;
+        self.samples_read = sample_index;
+        Ok(())
+    }
+
+    /// The contents of the first chunk with the given identifier, e.g. `b"fmt "`.
+    ///
+    /// The contents of the `data` chunk are not retained.
+    pub fn chunk(&self, id: &[u8; 4]) -> Option<&[u8]> {
+        self.chunks
+            .iter()
+            .find(|(chunk_id, _)| chunk_id == id)
+            .map(|(_, contents)| &contents[..])
+    }
+
+    /// The contents of every chunk with the given identifier, in the order of the file.
+    pub fn chunks<'a>(&'a self, id: &[u8; 4]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let id = *id;
+        self.chunks
+            .iter()
+            .filter(move |(chunk_id, _)| *chunk_id == id)
+            .map(|(_, contents)| &contents[..])
+    }
+
+    /// The tags of every `LIST` chunk of the `INFO` type.
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+        for list in self.chunks(b"LIST") {
+            metadata::read_riff_list(list, &mut metadata);
+        }
+        metadata
+    }
+
+    /// The points of the `cue ` chunk, labelled by the `labl` chunks of any `LIST` chunks of the
+    /// `adtl` type.
+    pub fn markers(&self) -> Vec<Marker> {
+        match self.chunk(b"cue ") {
+            Some(cue) => marker::read_riff_cue_points(cue, self.chunks(b"LIST")),
+            None => Vec::new(),
+        }
+    }
+
+    /// The pitch and loop points of the `smpl` chunk, if any.
+    pub fn sampler_info(&self) -> Option<SamplerInfo> {
+        self.chunk(b"smpl").and_then(sampler::read_riff_smpl)
+    }
+
+    /// The contents of the Broadcast Wave Format `bext` chunk, if any.
+    pub fn broadcast_extension(&self) -> Option<BroadcastExtension> {
+        self.chunk(b"bext").and_then(broadcast::read_bext)
+    }
+
+    /// The text of the `iXML` chunk, if any.
+    pub fn ixml(&self) -> Option<String> {
+        self.chunk(b"iXML").map(broadcast::read_ixml)
+    }
+
+    /// The pictures of the ID3v2 tags of every `id3 ` chunk.
+    pub fn pictures(&self) -> Vec<Picture> {
+        let mut pictures = Vec::new();
+        // The identifier is written in upper case by some software.
+        for (_, tag) in self
+            .chunks
+            .iter()
+            .filter(|(id, _)| id == b"id3 " || id == b"ID3 ")
+        {
+            picture::read_id3_pictures(tag, &mut pictures);
+        }
+        pictures
+    }
+
+    /// The channel mask of a `WAVE_FORMAT_EXTENSIBLE` header, or `None` for other headers.
+    pub fn channel_mask(&self) -> Option<u32> {
+        let fmt = self.chunk(b"fmt ")?;
+        if fmt.len() < 24 || u16::from_le_bytes([fmt[0], fmt[1]]) != WAVE_FORMAT_EXTENSIBLE {
+            return None;
+        }
+        Some(u32::from_le_bytes([fmt[20], fmt[21], fmt[22], fmt[23]]))
+    }
+
+    /// The speaker position of each channel, as described by the channel mask if present or by
+    /// the default for the channel count otherwise.
+    pub fn channel_layout(&self) -> ChannelLayout {
+        let channel_count = usize::from(self.spec().channels);
+        match self.channel_mask() {
+            Some(mask) => ChannelLayout::from_channel_mask(mask, channel_count),
+            None => ChannelLayout::default_for(channel_count),
+        }
+    }
+}
+
+/// Encodes interleaved samples as a RIFF WAVE file.
+///
+/// The `fmt ` chunk is written on creation, while any Broadcast Wave Format extension, tags,
+/// sampler info and iXML are written in `bext`, `LIST`, `smpl` and `iXML` chunks before the
+/// `data` chunk once the first samples are written.
+///
+/// A `JUNK` chunk reserves space for a `ds64` chunk following the RIFF header, such that a file
+/// growing beyond the 4 GiB that RIFF sizes can describe is promoted to RF64 on being flushed, as
+/// per EBU Tech 3306.
+pub struct WavWriter<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    spec: Spec,
+    metadata: Metadata,
+    sampler_info: Option<SamplerInfo>,
+    broadcast_extension: Option<BroadcastExtension>,
+    ixml: Option<String>,
+    // The encoded samples of the frame that is currently being filled.
+    frame: Vec<u8>,
+    block_align: usize,
+    // Position of the contents of the `data` chunk, or `None` until its header has been written.
+    data_position: Option<u64>,
+    data_len: u64,
+    finalized: bool,
+}
+
+impl<W> WavWriter<W>
+where
+    W: Write + Seek,
+{
+    /// Writes the RIFF header, the `JUNK` chunk reserving space for a `ds64` chunk and the `fmt `
+    /// chunk.
+    ///
+    /// The `WAVE_FORMAT_EXTENSIBLE` header is used for audio of more than 2 channels or more than
+    /// 16 bits per sample, with the channel mask of the default layout for the channel count.
+    /// The `spec` is expected to have been validated via `WavWriter::supports`.
+    pub(crate) fn new(mut writer: W, spec: Spec) -> io::Result<Self> {
+        let bits_per_sample = spec.sample_format.bits_per_sample() as u16;
+        let block_align = block_align(&spec) as u16;
+        let (format_tag, subformat) = match spec.sample_format {
+            SampleFormat::F32 => (WAVE_FORMAT_IEEE_FLOAT, KSDATAFORMAT_SUBTYPE_IEEE_FLOAT),
+            _ => (WAVE_FORMAT_PCM, KSDATAFORMAT_SUBTYPE_PCM),
+        };
+        let extensible = spec.channel_count > 2 || bits_per_sample > 16;
+
+        let mut fmt = Vec::with_capacity(40);
+        let format_tag = if extensible {
+            WAVE_FORMAT_EXTENSIBLE
+        } else {
+            format_tag
+        };
+        fmt.extend_from_slice(&format_tag.to_le_bytes());
+        fmt.extend_from_slice(&(spec.channel_count as u16).to_le_bytes());
+        fmt.extend_from_slice(&spec.sample_rate.to_le_bytes());
+        let bytes_per_second = spec.sample_rate * u32::from(block_align);
+        fmt.extend_from_slice(&bytes_per_second.to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits_per_sample.to_le_bytes());
+        if extensible {
+            // The size of the extension, the valid bits per sample and the channel mask.
+            let channel_mask = ChannelLayout::default_for(spec.channel_count as usize)
+                .channel_mask()
+                .unwrap_or(0);
+            fmt.extend_from_slice(&22u16.to_le_bytes());
+            fmt.extend_from_slice(&bits_per_sample.to_le_bytes());
+            fmt.extend_from_slice(&channel_mask.to_le_bytes());
+            fmt.extend_from_slice(&subformat);
+        }
+
+        // The RIFF size is updated as samples are written.
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        write_chunk(&mut writer, b"JUNK", &[0; DS64_LEN])?;
+        write_chunk(&mut writer, b"fmt ", &fmt)?;
+
+        Ok(WavWriter {
+            writer,
+            spec,
+            metadata: Metadata::new(),
+            sampler_info: None,
+            broadcast_extension: None,
+            ixml: None,
+            frame: Vec::with_capacity(usize::from(block_align)),
+            block_align: usize::from(block_align),
+            data_position: None,
+            data_len: 0,
+            finalized: false,
+        })
+    }
+
+    /// Whether or not the encoder can represent audio of the given `spec`.
+    pub fn supports(spec: &Spec) -> bool {
+        let sample_format = match spec.sample_format {
+            SampleFormat::I8
+            | SampleFormat::I16
+            | SampleFormat::I24
+            | SampleFormat::I32
+            | SampleFormat::F32 => true,
+            SampleFormat::F64 | SampleFormat::LossyFloat => false,
+        };
+        // The size of a frame and the number of bytes per second are 16 and 32-bit fields.
+        let block_align = block_align(spec);
+        sample_format
+            && spec.channel_count > 0
+            && spec.sample_rate > 0
+            && block_align <= u64::from(u16::MAX)
+            && block_align * u64::from(spec.sample_rate) <= u64::from(u32::MAX)
+    }
+
+    /// A description of the audio being written.
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// Sets the tags that are written before the audio.
+    ///
+    /// This is expected to be called before any samples are written, see `WavWriter::has_started`.
+    pub fn set_metadata(&mut self, metadata: &Metadata) {
+        self.metadata = metadata.clone();
+    }
+
+    /// Sets the pitch and loop points that are written in a `smpl` chunk before the audio.
+    ///
+    /// This is expected to be called before any samples are written, see `WavWriter::has_started`.
+    pub fn set_sampler_info(&mut self, sampler_info: &SamplerInfo) {
+        self.sampler_info = Some(sampler_info.clone());
+    }
+
+    /// Sets the Broadcast Wave Format extension that is written in a `bext` chunk before the
+    /// audio.
+    ///
+    /// This is expected to be called before any samples are written, see `WavWriter::has_started`.
+    pub fn set_broadcast_extension(&mut self, extension: &BroadcastExtension) {
+        self.broadcast_extension = Some(extension.clone());
+    }
+
+    /// Sets the text that is written in an `iXML` chunk before the audio.
+    ///
+    /// This is expected to be called before any samples are written, see `WavWriter::has_started`.
+    pub fn set_ixml(&mut self, ixml: &str) {
+        self.ixml = Some(ixml.to_string());
+    }
+
+    /// Whether or not any samples have been written or the writer has been flushed, after which
+    /// the chunks preceding the audio can no longer be changed.
+    pub fn has_started(&self) -> bool {
+        self.data_position.is_some() || !self.frame.is_empty()
+    }
+
+    /// Writes the given integer sample, which is expected to be within the range of the spec's
+    /// sample format.
+    pub fn write_sample(&mut self, sample: i32) -> io::Result<()> {
+        let bytes = sample.to_le_bytes();
+        match self.spec.sample_format {
+            // 8-bit samples alone are stored as unsigned integers.
+            SampleFormat::I8 => self.frame.push((sample + 128) as u8),
+            SampleFormat::I16 => self.frame.extend_from_slice(&bytes[..2]),
+            SampleFormat::I24 => self.frame.extend_from_slice(&bytes[..3]),
+            _ => self.frame.extend_from_slice(&bytes),
+        }
+        self.write_frame_if_full()
+    }
+
+    /// Writes the given floating point sample of a `SampleFormat::F32` spec.
+    pub fn write_float_sample(&mut self, sample: f32) -> io::Result<()> {
+        self.frame.extend_from_slice(&sample.to_le_bytes());
+        self.write_frame_if_full()
+    }
+
+    /// Updates the RIFF and `data` chunk sizes to reflect the frames written so far and flushes
+    /// the inner writer.
+    ///
+    /// Where the RIFF size no longer fits in 32 bits, the file is promoted to RF64, replacing the
+    /// reserved `JUNK` chunk with a `ds64` chunk holding the sizes. Samples of an incomplete frame
+    /// remain buffered.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let data_position = self.data_position()?;
+        let end = self.writer.stream_position()?;
+        let riff_len = end - 8;
+        let (riff_id, riff_size, data_size) = if riff_len >= u64::from(DS64_SIZE) {
+            let frame_count = self.data_len / self.block_align as u64;
+            let mut ds64 = Vec::with_capacity(DS64_LEN);
+            ds64.extend_from_slice(&riff_len.to_le_bytes());
+            ds64.extend_from_slice(&self.data_len.to_le_bytes());
+            ds64.extend_from_slice(&frame_count.to_le_bytes());
+            // The table of the sizes of other chunks, of which there are none.
+            ds64.extend_from_slice(&0u32.to_le_bytes());
+            self.writer.seek(SeekFrom::Start(12))?;
+            write_chunk(&mut self.writer, b"ds64", &ds64)?;
+            (b"RF64", DS64_SIZE, DS64_SIZE)
+        } else {
+            (b"RIFF", riff_len as u32, self.data_len as u32)
+        };
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(riff_id)?;
+        self.writer.write_all(&riff_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(data_position - 4))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// Pads the `data` chunk to an even length and completes the file.
+    ///
+    /// Any samples of an incomplete frame are discarded.
+    pub fn finalize(&mut self) -> io::Result<()> {
+        if self.finalized {
+            return Ok(());
+        }
+        self.finalized = true;
+        self.frame.clear();
+        self.data_position()?;
+        if self.data_len % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        self.flush()
+    }
+
+    // The position of the contents of the `data` chunk, writing the chunks that precede it if
+    // they have not yet been written.
+    fn data_position(&mut self) -> io::Result<u64> {
+        if let Some(position) = self.data_position {
+            return Ok(position);
+        }
+        if let Some(ref extension) = self.broadcast_extension {
+            write_chunk(&mut self.writer, b"bext", &broadcast::bext(extension))?;
+        }
+        if !self.metadata.is_empty() {
+            let list = metadata::riff_info_list(&self.metadata);
+            write_chunk(&mut self.writer, b"LIST", &list)?;
+        }
+        if let Some(ref sampler_info) = self.sampler_info {
+            let smpl = sampler::riff_smpl(sampler_info, self.spec.sample_rate);
+            write_chunk(&mut self.writer, b"smpl", &smpl)?;
+        }
+        if let Some(ref ixml) = self.ixml {
+            write_chunk(&mut self.writer, b"iXML", &broadcast::ixml(ixml))?;
+        }
+        // The size of the data chunk is updated as samples are written.
+        self.writer.write_all(b"data")?;
+        self.writer.write_all(&0u32.to_le_bytes())?;
+        let position = self.writer.stream_position()?;
+        self.data_position = Some(position);
+        Ok(position)
+    }
+
+    // Writes the buffered frame once it holds a sample for every channel.
+    fn write_frame_if_full(&mut self) -> io::Result<()> {
+        if self.frame.len() < self.block_align {
+            return Ok(());
+        }
+        self.data_position()?;
+        self.writer.write_all(&self.frame)?;
+        self.data_len += self.frame.len() as u64;
+        self.frame.clear();
+        Ok(())
+    }
+}
+
+impl<W> Drop for WavWriter<W>
+where
+    W: Write + Seek,
+{
+    fn drop(&mut self) {
+        // Errors cannot be reported from `drop`, so they are ignored.
+        let _ = self.finalize();
+    }
+}
+
+impl From<io::Error> for WavError {
+    fn from(err: io::Error) -> Self {
+        WavError::Io(err)
+    }
+}
+
+impl std::error::Error for WavError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            WavError::Io(ref err) => Some(err),
+            WavError::Malformed(_) | WavError::Unsupported => None,
+        }
+    }
+}
+
+impl std::fmt::Display for WavError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            WavError::Io(ref err) => err.fmt(f),
+            WavError::Malformed(reason) => write!(f, "ill-formed WAVE file: {}", reason),
+            WavError::Unsupported => write!(f, "the WAVE sample format is not supported"),
+        }
+    }
+}
+
+// The size in bytes of a frame of the given `spec`, computed without overflow so that
+// `WavWriter::supports` may reject specs whose frames exceed the 16-bit `nBlockAlign` field.
+fn block_align(spec: &Spec) -> u64 {
+    u64::from(spec.channel_count) * u64::from(spec.sample_format.bits_per_sample() / 8)
+}
+
+// Writes a chunk of the given identifier and contents, padded to an even length.
+fn write_chunk<W>(writer: &mut W, id: &[u8; 4], contents: &[u8]) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(id)?;
+    writer.write_all(&(contents.len() as u32).to_le_bytes())?;
+    writer.write_all(contents)?;
+    if contents.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+// Reads every chunk of a RIFF, RF64 or BW64 WAVE file, retaining the contents of every chunk
+// besides `data`.
+//
+// Chunk sizes of `0xFFFFFFFF` within RF64 and BW64 files are read from the `ds64` chunk, which
+// precedes every other chunk. Reading stops at the end of the file, including part way through a
+// chunk.
+fn read_chunks<R>(reader: &mut R) -> Result<RiffChunks, WavError>
+where
+    R: Read + Seek,
+{
+    let mut header = [0; 12];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    if !is_wav_header(&header) {
+        return Err(WavError::Malformed("no RIFF WAVE header found"));
+    }
+    let is_rf64 = &header[..4] != b"RIFF";
+
+    let mut chunks = Vec::new();
+    let mut data = None;
+    let mut ds64_sizes = Vec::new();
+    loop {
+        let mut header = [0; 8];
+        match reader.read_exact(&mut header) {
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            result => result?,
+        }
+        let mut id = [0; 4];
+        id.copy_from_slice(&header[0..4]);
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let len = match ds64_sizes.iter().find(|(ds64_id, _)| *ds64_id == id) {
+            Some(&(_, ds64_len)) if is_rf64 && len == DS64_SIZE => ds64_len,
+            _ => u64::from(len),
+        };
+        // Chunks are padded to an even length.
+        let padding = len % 2;
+        if &id == b"data" {
+            let position = reader.stream_position()?;
+            let end = position
+                .checked_add(len)
+                .and_then(|end| end.checked_add(padding))
+                .ok_or(WavError::Malformed("data chunk length overflows"))?;
+            if data.is_none() {
+                data = Some((position, len));
+            }
+            reader.seek(SeekFrom::Start(end))?;
+            continue;
+        }
+        let mut contents = Vec::new();
+        reader.by_ref().take(len).read_to_end(&mut contents)?;
+        if (contents.len() as u64) < len {
+            break;
+        }
+        reader.seek(SeekFrom::Current(padding as i64))?;
+        if is_rf64 && &id == b"ds64" {
+            ds64_sizes = read_ds64(&contents);
+        }
+        chunks.push((id, contents));
+    }
+    Ok(RiffChunks { chunks, data })
+}
+
+// The sizes given by a `ds64` chunk of the `data` chunk, followed by those of its table.
+//
+// The RIFF size and sample count are not needed, as the file is read until its end and the sample
+// count is implied by the size of the `data` chunk.
+fn read_ds64(ds64: &[u8]) -> Vec<([u8; 4], u64)> {
+    let size = |bytes: &[u8]| {
+        let mut size = [0; 8];
+        size.copy_from_slice(bytes);
+        u64::from_le_bytes(size)
+    };
+
+    let mut sizes = Vec::new();
+    if ds64.len() < DS64_LEN {
+        return sizes;
+    }
+    sizes.push((*b"data", size(&ds64[8..16])));
+    let table_len = u32::from_le_bytes([ds64[24], ds64[25], ds64[26], ds64[27]]);
+    for entry in ds64[DS64_LEN..].chunks_exact(12).take(table_len as usize) {
+        let mut id = [0; 4];
+        id.copy_from_slice(&entry[..4]);
+        sizes.push((id, size(&entry[4..])));
+    }
+    sizes
+}
+
+// Reads the spec of a `fmt ` chunk along with the number of bytes holding each sample.
+fn read_fmt(fmt: &[u8]) -> Result<(WavSpec, u16), WavError> {
+    if fmt.len() < 16 {
+        return Err(WavError::Malformed("invalid fmt chunk size"));
+    }
+    let u16_at = |position: usize| u16::from_le_bytes([fmt[position], fmt[position + 1]]);
+    let u32_at = |position: usize| {
+        u32::from_le_bytes([
+            fmt[position],
+            fmt[position + 1],
+            fmt[position + 2],
+            fmt[position + 3],
+        ])
+    };
+
+    let format_tag = u16_at(0);
+    let channels = u16_at(2);
+    let sample_rate = u32_at(4);
+    let bytes_per_second = u32_at(8);
+    let block_align = u16_at(12);
+    let mut bits_per_sample = u16_at(14);
+    if channels == 0 {
+        return Err(WavError::Malformed("file contains zero channels"));
+    }
+    let bytes_per_sample = block_align / channels;
+    if u32::from(bits_per_sample) > u32::from(bytes_per_sample) * 8 {
+        return Err(WavError::Malformed("sample bits exceeds size of sample"));
+    }
+    if Some(bytes_per_second) != u32::from(block_align).checked_mul(sample_rate) {
+        return Err(WavError::Malformed("inconsistent fmt chunk"));
+    }
+    if bits_per_sample % 8 != 0 {
+        return Err(WavError::Malformed(
+            "bits per sample is not a multiple of 8",
+        ));
+    }
+    if bits_per_sample == 0 {
+        return Err(WavError::Malformed("bits per sample is 0"));
+    }
+
+    let sample_format = match format_tag {
+        WAVE_FORMAT_PCM => WavSampleFormat::Int,
+        WAVE_FORMAT_IEEE_FLOAT if bits_per_sample == 32 => WavSampleFormat::Float,
+        WAVE_FORMAT_IEEE_FLOAT => {
+            return Err(WavError::Malformed("bits per sample is not 32"));
+        }
+        WAVE_FORMAT_EXTENSIBLE => {
+            if fmt.len() < 40 {
+                return Err(WavError::Malformed("unexpected fmt chunk size"));
+            }
+            let valid_bits_per_sample = u16_at(18);
+            if valid_bits_per_sample > 0 {
+                bits_per_sample = valid_bits_per_sample;
+            }
+            if fmt[24..40] == KSDATAFORMAT_SUBTYPE_PCM {
+                WavSampleFormat::Int
+            } else if fmt[24..40] == KSDATAFORMAT_SUBTYPE_IEEE_FLOAT {
+                WavSampleFormat::Float
+            } else {
+                return Err(WavError::Unsupported);
+            }
+        }
+        _ => return Err(WavError::Unsupported),
+    };
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    };
+    Ok((spec, bytes_per_sample))
+}
+
+// Decodes a little-endian sample of the given encoding, converting it to the sample type `S`.
+fn decode<S>(encoding: Encoding, bytes: &[u8]) -> S
+where
+    S: Sample,
+{
+    use dasp_sample::{Sample as _, I24};
+    match encoding {
+        // 8-bit samples alone are stored as unsigned integers.
+        Encoding::U8 => (bytes[0].wrapping_sub(128) as i8).to_sample(),
+        Encoding::I16 => i16::from_le_bytes([bytes[0], bytes[1]]).to_sample(),
+        Encoding::I24 => {
+            let sample = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+            I24::new_unchecked(sample).to_sample()
+        }
+        Encoding::I24In32 => {
+            // The most significant byte is ignored, with the sign extended from the 24th bit.
+            let sample = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) << 8 >> 8;
+            I24::new_unchecked(sample).to_sample()
+        }
+        Encoding::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_sample(),
+        Encoding::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_sample(),
+    }
+}
@@ -1 +1,493 @@
+//! Items for writing and creating audio files of the supported formats.
 
+use crate::broadcast::BroadcastExtension;
+use crate::metadata::Metadata;
+use crate::read::FormatError;
+use crate::sampler::SamplerInfo;
+use crate::{Format, SampleFormat};
+
+#[cfg(feature = "caf_alac")]
+pub use crate::caf_alac::AlacWriter;
+#[cfg(feature = "flac")]
+pub use crate::flac::FlacWriter;
+#[cfg(feature = "wav")]
+pub use crate::wav::WavWriter;
+
+/// Types from which samples may be converted when written via the `Writer::write_sample` method.
+pub trait Sample:
+    dasp_sample::Sample
+    + dasp_sample::ToSample<i8>
+    + dasp_sample::ToSample<i16>
+    + dasp_sample::ToSample<dasp_sample::I24>
+    + dasp_sample::ToSample<i32>
+    + dasp_sample::ToSample<f32>
+{
+}
+
+impl<T> Sample for T where
+    T: dasp_sample::Sample
+        + dasp_sample::ToSample<i8>
+        + dasp_sample::ToSample<i16>
+        + dasp_sample::ToSample<dasp_sample::I24>
+        + dasp_sample::ToSample<i32>
+        + dasp_sample::ToSample<f32>
+{
+}
+
+/// Returned by the `create` function, enumerates the various supported writers.
+pub enum Writer<W>
+where
+    W: std::io::Write + std::io::Seek,
+{
+    #[cfg(feature = "flac")]
+    Flac(FlacWriter<W>),
+    #[cfg(feature = "caf_alac")]
+    CafAlac(AlacWriter<W>),
+    #[cfg(feature = "wav")]
+    Wav(WavWriter<W>),
+}
+
+/// An alias for the buffered, file `Writer` type returned from the `create` function.
+pub type BufFileWriter = Writer<std::io::BufWriter<std::fs::File>>;
+
+/// A description of the audio format that is to be written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Spec {
+    /// The number of channels of audio.
+    pub channel_count: u32,
+    /// The rate in Hertz at which each channel of audio is sampled.
+    pub sample_rate: u32,
+    /// The encoding in which samples are stored.
+    pub sample_format: SampleFormat,
+}
+
+/// Options for the FLAC encoder, used via the `Writer::new_flac` function.
+#[cfg(feature = "flac")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FlacOptions {
+    /// The compression level from `0` (fastest) to `8` (smallest), defaulting to `5`.
+    ///
+    /// Levels greater than `8` are treated as `8`.
+    pub compression_level: u8,
+    /// The number of frames encoded within each FLAC block.
+    ///
+    /// If `None`, the block size is determined by the `compression_level`. Block sizes less than
+    /// `16` are treated as `16`.
+    pub block_size: Option<u16>,
+    /// The number of bits per sample to encode, from `4` up to the bits of the spec's sample
+    /// format.
+    ///
+    /// If `None`, the bits of the spec's sample format are used. Otherwise samples are reduced to
+    /// the given bit depth by discarding their least significant bits, allowing for any bit depth
+    /// permitted by FLAC, e.g. 20-bit audio from `SampleFormat::I24` samples.
+    pub bits_per_sample: Option<u32>,
+}
+
+/// Errors that might be returned from the `Writer::new` function or while writing.
+#[derive(Debug)]
+pub enum WriteError {
+    Io(std::io::Error),
+    Writer(FormatError),
+    UnsupportedFormat,
+    UnsupportedSpec(Spec),
+    AudioStarted,
+}
+
+/// Attempts to create an audio `Writer` that writes to a new file at the specified `Path`.
+///
+/// The format is determined from the path's file extension.
+pub fn create<P>(file_path: P, spec: Spec) -> Result<BufFileWriter, WriteError>
+where
+    P: AsRef<std::path::Path>,
+{
+    BufFileWriter::create(file_path, spec)
+}
+
+impl BufFileWriter {
+    /// Attempts to create an audio `Writer` that writes to a new file at the specified `Path`.
+    ///
+    /// This function is a convenience wrapper around the `Writer::new` function.
+    ///
+    /// The `Format` is determined from the `file_path`'s extension. If a file already exists at
+    /// the given path, it will be truncated.
+    pub fn create<P>(file_path: P, spec: Spec) -> Result<Self, WriteError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = file_path.as_ref();
+        let format = format_from_path(path).ok_or(WriteError::UnsupportedFormat)?;
+        // The spec is checked before the file is created, leaving any existing file untouched.
+        if !Self::supports(format, &spec) {
+            return Err(WriteError::UnsupportedSpec(spec));
+        }
+        let file = std::fs::File::create(path)?;
+        let writer = std::io::BufWriter::new(file);
+        Writer::new(writer, format, spec)
+    }
+}
+
+impl<W> Writer<W>
+where
+    W: std::io::Write + std::io::Seek,
+{
+    /// Attempts to create a `Writer` that encodes audio of the given `format` and `spec` to the
+    /// given `writer`.
+    ///
+    /// Returns `WriteError::UnsupportedFormat` if there is no encoder available for `format` and
+    /// `WriteError::UnsupportedSpec` if the encoder cannot represent the given `spec`.
+    pub fn new(writer: W, format: Format, spec: Spec) -> Result<Self, WriteError> {
+        match format {
+            #[cfg(feature = "flac")]
+            Format::Flac => Self::new_flac(writer, spec, FlacOptions::default()),
+
+            #[cfg(feature = "caf_alac")]
+            Format::CafAlac => {
+                if !AlacWriter::<W>::supports(&spec) {
+                    return Err(WriteError::UnsupportedSpec(spec));
+                }
+                Ok(Writer::CafAlac(AlacWriter::new(writer, spec)?))
+            }
+
+            #[cfg(feature = "wav")]
+            Format::Wav => {
+                if !WavWriter::<W>::supports(&spec) {
+                    return Err(WriteError::UnsupportedSpec(spec));
+                }
+                Ok(Writer::Wav(WavWriter::new(writer, spec)?))
+            }
+
+            #[allow(unreachable_patterns)]
+            _ => Err(WriteError::UnsupportedFormat),
+        }
+    }
+
+    /// Whether or not a `Writer` of the given `format` is able to encode audio of the given `spec`.
+    ///
+    /// Always returns `false` for formats for which there is no encoder available.
+    pub fn supports(format: Format, spec: &Spec) -> bool {
+        match format {
+            #[cfg(feature = "flac")]
+            Format::Flac => FlacWriter::<W>::supports(spec, None),
+            #[cfg(feature = "caf_alac")]
+            Format::CafAlac => AlacWriter::<W>::supports(spec),
+            #[cfg(feature = "wav")]
+            Format::Wav => WavWriter::<W>::supports(spec),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Attempts to create a `Writer` that encodes FLAC audio of the given `spec` to the given
+    /// `writer` using the given encoder `options`.
+    ///
+    /// FLAC supports 8, 16, 24 and 32-bit integer sample formats of up to 8 channels. If the
+    /// `bits_per_sample` of the `options` is invalid for the `spec`, `UnsupportedSpec` is returned.
+    #[cfg(feature = "flac")]
+    pub fn new_flac(writer: W, spec: Spec, options: FlacOptions) -> Result<Self, WriteError> {
+        if !FlacWriter::<W>::supports(&spec, options.bits_per_sample) {
+            return Err(WriteError::UnsupportedSpec(spec));
+        }
+        let writer = FlacWriter::new(writer, spec, options)?;
+        Ok(Writer::Flac(writer))
+    }
+
+    /// The format to which the audio will be written.
+    pub fn format(&self) -> Format {
+        match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(_) => Format::Flac,
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(_) => Format::CafAlac,
+            #[cfg(feature = "wav")]
+            Writer::Wav(_) => Format::Wav,
+        }
+    }
+
+    /// A description of the audio being written.
+    pub fn spec(&self) -> Spec {
+        match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(ref writer) => writer.spec(),
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(ref writer) => writer.spec(),
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref writer) => writer.spec(),
+        }
+    }
+
+    /// Sets the tags to be written along with the audio, e.g. those read via `Reader::metadata`.
+    ///
+    /// Tags are written as a VORBIS_COMMENT block for FLAC, a `LIST` chunk of the `INFO` type for
+    /// WAV and an `info` chunk for CAF, with the equivalent names of each format for tags such as
+    /// `TITLE` and `ARTIST`. Tags of WAV files without an equivalent are omitted unless their key
+    /// is an `INFO` identifier such as `ISRC`.
+    ///
+    /// Writing Ogg Vorbis comments is deferred until audrey gains an Ogg Vorbis encoder, so tags
+    /// read from Ogg Vorbis audio can only be carried over into the formats above for now.
+    ///
+    /// As tags precede the audio, this must be called before any samples are written or the
+    /// `Writer` is flushed, otherwise `WriteError::AudioStarted` is returned. Calling it again
+    /// replaces the tags that were set previously.
+    pub fn set_metadata(&mut self, metadata: &Metadata) -> Result<(), WriteError> {
+        self.check_not_started()?;
+        match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(ref mut writer) => writer.set_metadata(metadata),
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(ref mut writer) => writer.set_metadata(metadata),
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref mut writer) => writer.set_metadata(metadata),
+        }
+        Ok(())
+    }
+
+    /// Sets the pitch and loop points to be written along with the audio, e.g. those read via
+    /// `Reader::sampler_info`.
+    ///
+    /// These are written as a `smpl` chunk for WAV and as `inst` and `regn` chunks for CAF. FLAC
+    /// can only represent the first loop, which is written as `LOOPSTART` and `LOOPLENGTH` Vorbis
+    /// comments in place of any such tags given to `set_metadata`.
+    ///
+    /// As with `set_metadata`, this must be called before any samples are written or the `Writer`
+    /// is flushed, otherwise `WriteError::AudioStarted` is returned.
+    pub fn set_sampler_info(&mut self, sampler_info: &SamplerInfo) -> Result<(), WriteError> {
+        self.check_not_started()?;
+        match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(ref mut writer) => writer.set_sampler_info(sampler_info),
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(ref mut writer) => writer.set_sampler_info(sampler_info),
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref mut writer) => writer.set_sampler_info(sampler_info),
+        }
+        Ok(())
+    }
+
+    /// Sets the Broadcast Wave Format extension to be written in a `bext` chunk, e.g. that read
+    /// via `Reader::broadcast_extension`.
+    ///
+    /// The `bext` chunk is specific to WAV, so nothing is written for other formats. As with
+    /// `set_metadata`, this must be called before any samples are written or the `Writer` is
+    /// flushed, otherwise `WriteError::AudioStarted` is returned.
+    #[cfg_attr(not(feature = "wav"), allow(unused_variables))]
+    pub fn set_broadcast_extension(
+        &mut self,
+        extension: &BroadcastExtension,
+    ) -> Result<(), WriteError> {
+        self.check_not_started()?;
+        match *self {
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref mut writer) => writer.set_broadcast_extension(extension),
+            #[allow(unreachable_patterns)]
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Sets the text to be written in an `iXML` chunk, e.g. that read via `Reader::ixml`.
+    ///
+    /// The `iXML` chunk is specific to WAV, so nothing is written for other formats. As with
+    /// `set_metadata`, this must be called before any samples are written or the `Writer` is
+    /// flushed, otherwise `WriteError::AudioStarted` is returned.
+    #[cfg_attr(not(feature = "wav"), allow(unused_variables))]
+    pub fn set_ixml(&mut self, ixml: &str) -> Result<(), WriteError> {
+        self.check_not_started()?;
+        match *self {
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref mut writer) => writer.set_ixml(ixml),
+            #[allow(unreachable_patterns)]
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // Fails with `WriteError::AudioStarted` once the chunks preceding the audio have been written.
+    fn check_not_started(&self) -> Result<(), WriteError> {
+        let has_started = match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(ref writer) => writer.has_started(),
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(ref writer) => writer.has_started(),
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref writer) => writer.has_started(),
+        };
+        if has_started {
+            return Err(WriteError::AudioStarted);
+        }
+        Ok(())
+    }
+
+    /// Converts the given sample to the sample format of the underlying encoding and writes it.
+    ///
+    /// When writing multiple channels, samples are expected to be **interleaved**.
+    pub fn write_sample<S>(&mut self, sample: S) -> Result<(), WriteError>
+    where
+        S: Sample,
+    {
+        match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(ref mut writer) => {
+                let sample = match writer.spec().sample_format {
+                    SampleFormat::I8 => i32::from(sample.to_sample::<i8>()),
+                    SampleFormat::I16 => i32::from(sample.to_sample::<i16>()),
+                    SampleFormat::I24 => to_i24(sample),
+                    _ => sample.to_sample::<i32>(),
+                };
+                writer.write_sample(sample)?;
+                Ok(())
+            }
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(ref mut writer) => {
+                let sample = match writer.spec().sample_format {
+                    SampleFormat::I16 => i32::from(sample.to_sample::<i16>()),
+                    SampleFormat::I24 => to_i24(sample),
+                    _ => sample.to_sample::<i32>(),
+                };
+                writer.write_sample(sample)?;
+                Ok(())
+            }
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref mut writer) => {
+                match writer.spec().sample_format {
+                    SampleFormat::F32 => writer.write_float_sample(sample.to_sample::<f32>())?,
+                    SampleFormat::I8 => writer.write_sample(i32::from(sample.to_sample::<i8>()))?,
+                    SampleFormat::I16 => {
+                        writer.write_sample(i32::from(sample.to_sample::<i16>()))?
+                    }
+                    SampleFormat::I24 => writer.write_sample(to_i24(sample))?,
+                    _ => writer.write_sample(sample.to_sample::<i32>())?,
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Updates any headers to reflect the audio written so far and flushes the underlying writer.
+    ///
+    /// This allows for producing a valid file while writing is still in progress. It is not
+    /// necessary to call this before `finalize`.
+    pub fn flush(&mut self) -> Result<(), WriteError> {
+        match *self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(ref mut writer) => writer.flush()?,
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(ref mut writer) => writer.flush()?,
+            #[cfg(feature = "wav")]
+            Writer::Wav(ref mut writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+
+    /// Writes each channel of the given frame as an interleaved sample.
+    ///
+    /// This method expects that the frame type `F` has the same number of channels as the `Spec`
+    /// with which the `Writer` was created.
+    pub fn write_frame<F>(&mut self, frame: F) -> Result<(), WriteError>
+    where
+        F: dasp_frame::Frame,
+        F::Sample: Sample,
+    {
+        for sample in frame.channels() {
+            self.write_sample(sample)?;
+        }
+        Ok(())
+    }
+
+    /// Completes the encoding, updating any headers that depend on the amount of audio written,
+    /// and flushes the underlying writer.
+    ///
+    /// If this is not called, the `Writer` will attempt to finalize when dropped, however any
+    /// errors that occur in the process will be ignored.
+    pub fn finalize(self) -> Result<(), WriteError> {
+        match self {
+            #[cfg(feature = "flac")]
+            Writer::Flac(mut writer) => writer.finalize()?,
+            #[cfg(feature = "caf_alac")]
+            Writer::CafAlac(mut writer) => writer.finalize()?,
+            #[cfg(feature = "wav")]
+            Writer::Wav(mut writer) => writer.finalize()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "flac")]
+impl Default for FlacOptions {
+    fn default() -> Self {
+        FlacOptions {
+            compression_level: 5,
+            block_size: None,
+            bits_per_sample: None,
+        }
+    }
+}
+
+// Determines the `Format` associated with the extension of the given path.
+pub(crate) fn format_from_path(path: &std::path::Path) -> Option<Format> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .and_then(|ext| Format::from_extension(&ext))
+}
+
+// Converts the sample to a signed 24-bit integer stored within an `i32`.
+fn to_i24<S>(sample: S) -> i32
+where
+    S: Sample,
+{
+    // Conversions to `I24` are not saturating, so clamp to the 24-bit range.
+    let sample = sample.to_sample::<dasp_sample::I24>().inner();
+    let max = dasp_sample::types::i24::MAX.inner();
+    let min = dasp_sample::types::i24::MIN.inner();
+    sample.max(min).min(max)
+}
+
+impl<T> From<T> for WriteError
+where
+    T: Into<FormatError>,
+{
+    fn from(err: T) -> Self {
+        WriteError::Writer(err.into())
+    }
+}
+
+impl From<std::io::Error> for WriteError {
+    fn from(err: std::io::Error) -> Self {
+        WriteError::Io(err)
+    }
+}
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            WriteError::Io(ref err) => Some(err),
+            WriteError::Writer(ref err) => Some(err),
+            WriteError::UnsupportedFormat => None,
+            WriteError::UnsupportedSpec(_) => None,
+            WriteError::AudioStarted => None,
+        }
+    }
+}
+
+impl std::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            WriteError::Io(ref err) => err.fmt(f),
+            WriteError::Writer(ref err) => err.fmt(f),
+            WriteError::UnsupportedFormat => write!(f, "no encoder is available for the format"),
+            WriteError::UnsupportedSpec(ref spec) => {
+                write!(
+                    f,
+                    "the format cannot be encoded with the given spec: {:?}",
+                    spec
+                )
+            }
+            WriteError::AudioStarted => {
+                write!(
+                    f,
+                    "the header cannot be changed once audio has been written"
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+extern crate audrey;
+
+#[cfg(feature = "wav")]
+use audrey::{write::Spec, BroadcastExtension, Format, SampleFormat};
+#[cfg(feature = "wav")]
+use std::io::Cursor;
+#[cfg(feature = "wav")]
+use std::time::Duration;
+
+#[cfg(feature = "wav")]
+const IXML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                    <BWFXML><IXML_VERSION>2.10</IXML_VERSION><SCENE>12A</SCENE></BWFXML>";
+
+#[cfg(feature = "wav")]
+fn extension() -> BroadcastExtension {
+    let mut umid = [0; 64];
+    for (i, byte) in umid.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    BroadcastExtension {
+        description: "Scene 12A, take 3".to_string(),
+        originator: "audrey".to_string(),
+        originator_reference: "AUDREY0123456789".to_string(),
+        origination_date: "2024-03-01".to_string(),
+        origination_time: "10:00:00".to_string(),
+        // Ten hours at 48kHz.
+        time_reference: 1_728_000_000,
+        version: 2,
+        umid,
+        loudness_value: Some(-23.0),
+        loudness_range: Some(7.5),
+        max_true_peak_level: Some(-1.0),
+        max_momentary_loudness: Some(-20.25),
+        max_short_term_loudness: None,
+        coding_history: "A=PCM,F=48000,W=24,M=stereo,T=audrey\r\n".to_string(),
+    }
+}
+
+// Encodes a tenth of a second of stereo silence with the given extension and iXML.
+#[cfg(feature = "wav")]
+fn encode_wav(extension: &BroadcastExtension, ixml: Option<&str>) -> Vec<u8> {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 48_000,
+        sample_format: SampleFormat::I24,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        writer.set_broadcast_extension(extension).unwrap();
+        if let Some(ixml) = ixml {
+            writer.set_ixml(ixml).unwrap();
+        }
+        for _ in 0..9_600 {
+            writer.write_sample(0i32).unwrap();
+        }
+        match writer.set_ixml("") {
+            Err(audrey::write::WriteError::AudioStarted) => (),
+            result => panic!("unexpected result: {:?}", result.map_err(|e| e.to_string())),
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.into_inner()
+}
+
+#[test]
+fn samples_broadcast_extension() {
+    let paths: &[&str] = &[
+        #[cfg(feature = "flac")]
+        "samples/sine_440hz_stereo.flac",
+        #[cfg(feature = "ogg_vorbis")]
+        "samples/sine_440hz_stereo.ogg",
+        #[cfg(feature = "caf_alac")]
+        "samples/sine_440hz_stereo.caf",
+    ];
+    for path in paths {
+        let reader = audrey::open(path).unwrap();
+        assert_eq!(reader.broadcast_extension(), None, "{}", path);
+        assert_eq!(reader.ixml(), None);
+    }
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_sample_bext_chunk() {
+    let reader = audrey::open("samples/sine_440hz_stereo.wav").unwrap();
+    let extension = reader.broadcast_extension().unwrap();
+    assert_eq!(extension.description, "");
+    assert_eq!(extension.originator, "Logic Pro X");
+    assert_eq!(extension.originator_reference, "");
+    assert_eq!(extension.origination_date, "2017-01-09");
+    assert_eq!(extension.origination_time, "16:25:08");
+    assert_eq!(extension.time_reference, 158_760_000);
+    assert_eq!(extension.version, 1);
+    // Loudness is not read from version 1 extensions.
+    assert_eq!(extension.loudness_value, None);
+    assert_eq!(extension.coding_history, "");
+    assert_eq!(
+        extension.time_reference_duration(44_100),
+        Some(Duration::from_secs(3_600))
+    );
+    assert_eq!(reader.ixml(), None);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_broadcast_round_trip() {
+    let mut reader =
+        audrey::Reader::new(Cursor::new(encode_wav(&extension(), Some(IXML)))).unwrap();
+    assert_eq!(reader.broadcast_extension(), Some(extension()));
+    assert_eq!(reader.ixml().as_deref(), Some(IXML));
+    let sample_rate = reader.description().sample_rate();
+    assert_eq!(
+        reader
+            .broadcast_extension()
+            .unwrap()
+            .time_reference_duration(sample_rate),
+        Some(Duration::from_secs(36_000))
+    );
+    assert_eq!(reader.samples::<i32>().count(), 9_600);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_bext_limits() {
+    // Text is truncated to the length of each field, and the version raised to that required for
+    // the loudness fields.
+    let long = BroadcastExtension {
+        description: "d".repeat(300),
+        originator: "o".repeat(40),
+        version: 1,
+        loudness_range: Some(4.0),
+        ..BroadcastExtension::default()
+    };
+    let reader = audrey::Reader::new(Cursor::new(encode_wav(&long, None))).unwrap();
+    let extension = reader.broadcast_extension().unwrap();
+    assert_eq!(extension.description, "d".repeat(256));
+    assert_eq!(extension.originator, "o".repeat(32));
+    assert_eq!(extension.version, 2);
+    assert_eq!(extension.loudness_value, None);
+    assert_eq!(extension.loudness_range, Some(4.0));
+    assert_eq!(reader.ixml(), None);
+
+    let version_0 = BroadcastExtension {
+        version: 0,
+        ..BroadcastExtension::default()
+    };
+    let reader = audrey::Reader::new(Cursor::new(encode_wav(&version_0, None))).unwrap();
+    assert_eq!(reader.broadcast_extension(), Some(version_0));
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn convert_preserves_broadcast_chunks() {
+    let dir = std::env::temp_dir();
+    let input = dir.join("audrey_convert_broadcast_input.wav");
+    let output = dir.join("audrey_convert_broadcast_output.wav");
+    std::fs::write(&input, encode_wav(&extension(), Some(IXML))).unwrap();
+    audrey::Converter::new()
+        .sample_format(SampleFormat::I16)
+        .convert(&input, &output)
+        .unwrap();
+    let reader = audrey::open(&output).unwrap();
+    assert_eq!(reader.broadcast_extension(), Some(extension()));
+    assert_eq!(reader.ixml().as_deref(), Some(IXML));
+
+    // Other formats hold no `bext` or `iXML` chunk, so these are not written.
+    #[cfg(feature = "flac")]
+    {
+        let flac = dir.join("audrey_convert_broadcast_output.flac");
+        audrey::convert(&input, &flac).unwrap();
+        assert_eq!(audrey::open(&flac).unwrap().broadcast_extension(), None);
+        std::fs::remove_file(flac).unwrap();
+    }
+
+    for path in &[input, output] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
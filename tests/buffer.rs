@@ -0,0 +1,105 @@
+#![cfg(all(
+    feature = "flac",
+    feature = "ogg_vorbis",
+    feature = "wav",
+    feature = "caf_alac"
+))]
+
+extern crate audrey;
+
+use audrey::{Format, SampleFormat};
+
+const FLAC: &str = "samples/sine_440hz_stereo.flac";
+const OGG_VORBIS: &str = "samples/sine_440hz_stereo.ogg";
+const WAV: &str = "samples/sine_440hz_stereo.wav";
+const CAF_ALAC: &str = "samples/sine_440hz_stereo.caf";
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("audrey_buffer_{}", name))
+}
+
+#[test]
+fn read_to_buffer() {
+    for &path in &[FLAC, OGG_VORBIS, WAV, CAF_ALAC] {
+        let mut reader = audrey::open(path).unwrap();
+        let frames = reader
+            .frames::<[f32; 2]>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        let buffer = audrey::read_to_buffer::<f32, _>(path).unwrap();
+        assert_eq!(buffer.description(), reader.description(), "{}", path);
+        assert_eq!(buffer.channel_count(), 2);
+        assert_eq!(buffer.sample_rate(), 44_100);
+        assert_eq!(buffer.frame_count(), frames.len(), "{}", path);
+        for (frame, expected) in buffer.frames().zip(&frames) {
+            assert_eq!(frame, &expected[..]);
+        }
+
+        // The interleaved and planar views agree.
+        let planar = buffer.to_planar();
+        assert_eq!(planar.len(), 2);
+        for (channel, samples) in planar.iter().enumerate() {
+            assert!(buffer.channel(channel).eq(samples.iter()));
+            for (sample, frame) in samples.iter().zip(&frames) {
+                assert_eq!(*sample, frame[channel]);
+            }
+        }
+        assert_eq!(buffer.clone().into_samples(), buffer.samples());
+    }
+}
+
+#[test]
+fn read_buffer_from_reader() {
+    let mut reader = audrey::open(WAV).unwrap();
+    reader.seek(40_000).unwrap();
+    let buffer = audrey::AudioBuffer::<i16>::read(&mut reader).unwrap();
+    assert_eq!(buffer.frame_count(), 4_100);
+    let full = audrey::read_to_buffer::<i16, _>(WAV).unwrap();
+    assert_eq!(buffer.samples(), &full.samples()[80_000..]);
+}
+
+#[test]
+fn write_buffer() {
+    let buffer = audrey::read_to_buffer::<i16, _>(WAV).unwrap();
+    for &format in &[Format::Flac, Format::CafAlac, Format::Wav] {
+        let path = temp_path(&format!("lossless.{}", format.extension()));
+        audrey::write_buffer(&buffer, &path, format, None).unwrap();
+        let written = audrey::read_to_buffer::<i16, _>(&path).unwrap();
+        assert_eq!(written.description().format(), format);
+        assert_eq!(written.description().sample_format(), SampleFormat::I16);
+        assert_eq!(written.samples(), buffer.samples(), "{:?}", format);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Lossy audio is written as floating point where possible.
+    let buffer = audrey::read_to_buffer::<f32, _>(OGG_VORBIS).unwrap();
+    let path = temp_path("lossy.wav");
+    audrey::write_buffer(&buffer, &path, Format::Wav, None).unwrap();
+    let written = audrey::read_to_buffer::<f32, _>(&path).unwrap();
+    assert_eq!(written.description().sample_format(), SampleFormat::F32);
+    assert_eq!(written.samples(), buffer.samples());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn write_buffer_sample_format() {
+    // The requested sample format takes precedence over that of the buffer's source.
+    let buffer = audrey::read_to_buffer::<i16, _>(WAV).unwrap();
+    let path = temp_path("widened.flac");
+    audrey::write_buffer(&buffer, &path, Format::Flac, Some(SampleFormat::I24)).unwrap();
+    let written = audrey::read_to_buffer::<i16, _>(&path).unwrap();
+    assert_eq!(written.description().sample_format(), SampleFormat::I24);
+    assert_eq!(written.samples(), buffer.samples());
+    std::fs::remove_file(&path).unwrap();
+
+    // A sample format that the format cannot represent is refused before the file is created.
+    let path = temp_path("float.flac");
+    match audrey::write_buffer(&buffer, &path, Format::Flac, Some(SampleFormat::F32)) {
+        Err(audrey::write::WriteError::UnsupportedSpec(spec)) => {
+            assert_eq!(spec.sample_format, SampleFormat::F32);
+        }
+        _ => panic!("expected an unsupported spec error"),
+    }
+    assert!(!path.exists());
+}
@@ -0,0 +1,181 @@
+extern crate audrey;
+
+#[cfg(any(all(feature = "caf_alac", feature = "wav"), feature = "ogg_vorbis"))]
+use audrey::layout::ChannelLayout;
+#[cfg(feature = "caf_alac")]
+use audrey::write::Spec;
+#[cfg(feature = "caf_alac")]
+use audrey::{Format, SampleFormat};
+#[cfg(any(feature = "caf_alac", feature = "ogg_vorbis"))]
+use std::io::Cursor;
+
+#[cfg(feature = "ogg_vorbis")]
+mod common;
+
+// Reads every frame of the given reader.
+#[cfg(any(feature = "caf_alac", feature = "ogg_vorbis"))]
+fn read_frames<R>(reader: &mut audrey::Reader<R>) -> Vec<Vec<f32>>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    let channels = reader.description().channel_count() as usize;
+    let samples = reader
+        .samples::<f32>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    samples
+        .chunks(channels)
+        .map(|frame| frame.to_vec())
+        .collect()
+}
+
+// Asserts that channel `i` of each canonical frame is channel `order[i]` of the native frame,
+// and that every channel differs from the others so that the order is observable.
+#[cfg(any(feature = "caf_alac", feature = "ogg_vorbis"))]
+fn assert_reordered(native: &[Vec<f32>], canonical: &[Vec<f32>], order: &[usize]) {
+    assert_eq!(native.len(), canonical.len());
+    let peak = |channel: usize| {
+        native
+            .iter()
+            .map(|frame| frame[channel].abs())
+            .fold(0.0, f32::max)
+    };
+    for a in 0..order.len() {
+        assert!(peak(a) > 0.0);
+        for b in 0..a {
+            assert_ne!(peak(a), peak(b));
+        }
+    }
+    for (native, canonical) in native.iter().zip(canonical) {
+        let expected = order.iter().map(|&c| native[c]).collect::<Vec<_>>();
+        assert_eq!(canonical, &expected);
+    }
+}
+
+#[cfg(feature = "ogg_vorbis")]
+#[test]
+fn ogg_vorbis_canonical_order() {
+    // The index within the Vorbis order of each channel in canonical order.
+    let orders: &[&[usize]] = &[
+        &[0, 2, 1],
+        &[0, 1, 2, 3],
+        &[0, 2, 1, 3, 4],
+        &[0, 2, 1, 5, 3, 4],
+        &[0, 2, 1, 6, 5, 3, 4],
+        &[0, 2, 1, 7, 5, 6, 3, 4],
+    ];
+    for order in orders {
+        let channels = order.len();
+        let levels = (0..channels as u64).map(|i| 63 - 7 * i).collect::<Vec<_>>();
+        let stream = common::vorbis_stream(&levels, &[]);
+
+        let mut native = audrey::Reader::new(Cursor::new(stream.clone())).unwrap();
+        assert_eq!(native.channel_layout(), ChannelLayout::vorbis(channels));
+        let native_frames = read_frames(&mut native);
+
+        let mut canonical = audrey::Reader::new(Cursor::new(stream))
+            .unwrap()
+            .with_canonical_channel_order();
+        assert_eq!(
+            canonical.channel_layout(),
+            ChannelLayout::default_for(channels)
+        );
+        let canonical_frames = read_frames(&mut canonical);
+        assert_eq!(canonical_frames.len(), 5_120);
+        assert_reordered(&native_frames, &canonical_frames, order);
+
+        // Reordering persists across seeks and applies to every means of reading.
+        canonical.seek(1_000).unwrap();
+        let mut frame = vec![0.0f32; channels];
+        assert_eq!(canonical.read_into(&mut frame).unwrap(), channels);
+        assert_eq!(frame, canonical_frames[1_000]);
+    }
+}
+
+// Encodes a tone of the given number of channels with a distinct level per channel, renaming the
+// `chan` chunk so that the ALAC default layout applies.
+#[cfg(feature = "caf_alac")]
+fn caf_without_layout(channels: u32) -> Vec<u8> {
+    let spec = Spec {
+        channel_count: channels,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::CafAlac, spec).unwrap();
+        for i in 0..5_000 {
+            let tone = (i as f32 * 0.05).sin();
+            for channel in 0..channels {
+                let level = 1.0 - channel as f32 * 0.1;
+                writer.write_sample(tone * level).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+    }
+    let mut bytes = cursor.into_inner();
+    let chan = bytes.windows(4).position(|id| id == b"chan").unwrap();
+    bytes[chan..chan + 4].copy_from_slice(b"free");
+    bytes
+}
+
+#[cfg(feature = "caf_alac")]
+#[test]
+fn caf_canonical_order() {
+    // The index within the ALAC default order of each channel in canonical order.
+    let orders: &[&[usize]] = &[
+        &[1, 2, 0],
+        &[1, 2, 0, 3],
+        &[1, 2, 0, 3, 4],
+        &[1, 2, 0, 5, 3, 4],
+        &[1, 2, 0, 6, 5, 3, 4],
+        &[3, 4, 0, 7, 5, 6, 1, 2],
+    ];
+    for order in orders {
+        let channels = order.len();
+        let bytes = caf_without_layout(channels as u32);
+
+        let mut native = audrey::Reader::new(Cursor::new(bytes.clone())).unwrap();
+        let native_layout = native.channel_layout();
+        let native_frames = read_frames(&mut native);
+
+        let mut canonical = audrey::Reader::new(Cursor::new(bytes))
+            .unwrap()
+            .with_canonical_channel_order();
+        let layout = canonical.channel_layout();
+        assert_eq!(layout, native_layout.to_canonical());
+        assert_eq!(layout.canonical_order(), (0..channels).collect::<Vec<_>>());
+        assert_reordered(&native_frames, &read_frames(&mut canonical), order);
+    }
+}
+
+#[cfg(all(feature = "caf_alac", feature = "wav"))]
+#[test]
+fn canonical_order_unchanged() {
+    // CAF files written by the crate describe their WAV channel order via the `chan` chunk.
+    let spec = Spec {
+        channel_count: 6,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    for &format in &[Format::CafAlac, Format::Wav] {
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = audrey::Writer::new(&mut cursor, format, spec).unwrap();
+            for i in 0..6_000 {
+                writer.write_sample(i as i16).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        cursor.set_position(0);
+        let mut reader = audrey::Reader::new(cursor)
+            .unwrap()
+            .with_canonical_channel_order();
+        assert_eq!(reader.channel_layout(), ChannelLayout::default_for(6));
+        let samples = reader
+            .samples::<i16>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(samples, (0..6_000).collect::<Vec<i16>>());
+    }
+}
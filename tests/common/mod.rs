@@ -0,0 +1,286 @@
+//! Helpers shared between the integration tests.
+//!
+//! Includes a minimal Vorbis encoder, as no Ogg Vorbis encoder is available to the tests.
+
+#![allow(dead_code)]
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use std::io::Cursor;
+
+// The number of audio packets, each but the first yielding 128 frames.
+const PACKET_COUNT: u64 = 41;
+
+// The number of frames within each generated Ogg Vorbis stream.
+pub const VORBIS_FRAME_COUNT: u64 = (PACKET_COUNT - 1) * 128;
+
+// Writes values least significant bit first, as read by Vorbis decoders.
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            bit: 0,
+        }
+    }
+
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in 0..bits {
+            if self.bit == 0 {
+                self.bytes.push(0);
+            }
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= ((value >> i & 1) as u8) << self.bit;
+            self.bit = (self.bit + 1) % 8;
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write(u64::from(byte), 8);
+        }
+    }
+}
+
+// The identification header, with both block sizes 256 samples.
+fn ident_header(channels: u8, sample_rate: u32) -> Vec<u8> {
+    let mut packet = vec![1];
+    packet.extend_from_slice(b"vorbis");
+    packet.extend_from_slice(&0u32.to_le_bytes());
+    packet.push(channels);
+    packet.extend_from_slice(&sample_rate.to_le_bytes());
+    packet.extend_from_slice(&[0; 12]);
+    packet.extend_from_slice(&[0x88, 1]);
+    packet
+}
+
+// The comment header of the given `NAME=value` comments.
+fn comment_header(comments: &[&str]) -> Vec<u8> {
+    let mut packet = vec![3];
+    packet.extend_from_slice(b"vorbis");
+    packet.extend_from_slice(&6u32.to_le_bytes());
+    packet.extend_from_slice(b"audrey");
+    packet.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        packet.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        packet.extend_from_slice(comment.as_bytes());
+    }
+    packet.push(1);
+    packet
+}
+
+// The setup header of a single mode using a floor of only its two end points and a residue
+// coding the two lowest frequency bins with the values 0 or 1.
+fn setup_header() -> Vec<u8> {
+    let mut w = BitWriter::new();
+    w.write(5, 8);
+    w.write_bytes(b"vorbis");
+    // Two codebooks of two one-bit entries, the second mapping entries to 0.0 and 1.0.
+    w.write(1, 8);
+    for &lookup in &[false, true] {
+        w.write(0x56_4342, 24);
+        w.write(1, 16);
+        w.write(2, 24);
+        w.write(0, 2);
+        w.write(0, 5);
+        w.write(0, 5);
+        if lookup {
+            w.write(1, 4);
+            // A minimum of 0.0 and delta of 1.0, followed by the multiplicands 0 and 1.
+            w.write(0, 32);
+            w.write(788 << 21 | 1, 32);
+            w.write(0, 4);
+            w.write(0, 1);
+            w.write(0, 1);
+            w.write(1, 1);
+        } else {
+            w.write(0, 4);
+        }
+    }
+    // A single unused time domain transform.
+    w.write(0, 6);
+    w.write(0, 16);
+    // A type 1 floor without partitions, a multiplier of 4 and range of 128.
+    w.write(0, 6);
+    w.write(1, 16);
+    w.write(0, 5);
+    w.write(3, 2);
+    w.write(7, 4);
+    // A type 1 residue of one classification spanning bins 0 and 1.
+    w.write(0, 6);
+    w.write(1, 16);
+    w.write(0, 24);
+    w.write(2, 24);
+    w.write(1, 24);
+    w.write(0, 6);
+    w.write(0, 8);
+    w.write(1, 3);
+    w.write(0, 1);
+    w.write(1, 8);
+    // A single mapping of one submap without coupling.
+    w.write(0, 6);
+    w.write(0, 16);
+    w.write(0, 4);
+    w.write(0, 24);
+    // A single mode of short blocks.
+    w.write(0, 6);
+    w.write(0, 1);
+    w.write(0, 32);
+    w.write(0, 8);
+    w.write(1, 1);
+    w.bytes
+}
+
+// An audio packet with the floor of each channel at the given level.
+fn audio_packet(levels: &[u64]) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    w.write(0, 1);
+    for &level in levels {
+        w.write(1, 1);
+        w.write(level, 6);
+        w.write(level, 6);
+    }
+    // The classification of each channel, followed by both residue values of each channel.
+    for _ in levels {
+        w.write(0, 1);
+    }
+    for _ in levels {
+        w.write(0b11, 2);
+    }
+    w.bytes
+}
+
+fn crc(bytes: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                crc << 1 ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn write_page(out: &mut Vec<u8>, packets: &[Vec<u8>], flags: u8, granule: u64, seq: u32) {
+    let mut page = b"OggS\0".to_vec();
+    page.push(flags);
+    page.extend_from_slice(&granule.to_le_bytes());
+    page.extend_from_slice(&0x4175_6472u32.to_le_bytes());
+    page.extend_from_slice(&seq.to_le_bytes());
+    page.extend_from_slice(&[0; 4]);
+    let mut lacing = Vec::new();
+    for packet in packets {
+        lacing.resize(lacing.len() + packet.len() / 255, 255);
+        lacing.push((packet.len() % 255) as u8);
+    }
+    page.push(lacing.len() as u8);
+    page.extend_from_slice(&lacing);
+    for packet in packets {
+        page.extend_from_slice(packet);
+    }
+    let crc = crc(&page);
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+    out.extend_from_slice(&page);
+}
+
+// An Ogg Vorbis stream of one channel per level, with each level within 1 to 63, producing a low
+// frequency tone with a distinct level per channel.
+pub fn vorbis_stream(levels: &[u64], comments: &[&str]) -> Vec<u8> {
+    vorbis_stream_with(levels, comments, 44_100, 0)
+}
+
+// As `vorbis_stream`, at the given sample rate and with the first frame at the given granule
+// position.
+pub fn vorbis_stream_with(
+    levels: &[u64],
+    comments: &[&str],
+    sample_rate: u32,
+    first_granule: u64,
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    let ident_header = ident_header(levels.len() as u8, sample_rate);
+    write_page(&mut out, &[ident_header], 2, 0, 0);
+    write_page(
+        &mut out,
+        &[comment_header(comments), setup_header()],
+        0,
+        0,
+        1,
+    );
+    for i in 0..PACKET_COUNT {
+        let flags = if i + 1 == PACKET_COUNT { 4 } else { 0 };
+        write_page(
+            &mut out,
+            &[audio_packet(levels)],
+            flags,
+            first_granule + i * 128,
+            i as u32 + 2,
+        );
+    }
+    out
+}
+
+// Interleaved samples of the given bit depth, right-aligned, cycling every 3000 frames through a
+// tone with a little noise, silence, white noise, a full scale square wave and a tone with wasted
+// low bits.
+pub fn test_signal(channel_count: usize, len: usize, bits: u32) -> Vec<i32> {
+    let max = ((1i64 << (bits - 1)) - 1) as f64;
+    let mut noise = 0x1234_5678u32;
+    let mut samples = Vec::with_capacity(channel_count * len);
+    for i in 0..len {
+        for ch in 0..channel_count {
+            noise ^= noise << 13;
+            noise ^= noise >> 17;
+            noise ^= noise << 5;
+            let t = i as f64 / 44_100.0;
+            let tone = (t * 440.0 * (ch + 1) as f64 * 2.0 * std::f64::consts::PI).sin();
+            let noise = (noise as f64 / u32::MAX as f64) * 2.0 - 1.0;
+            let sample = match (i / 3_000) % 5 {
+                0 => tone * 0.8 + noise * 0.1,
+                1 => 0.0,
+                2 => noise,
+                3 => tone.signum(),
+                _ => ((tone * 0.5 * max) as i64 & !0xF) as f64 / max,
+            };
+            samples.push((sample * max).round().max(-max - 1.0).min(max) as i32);
+        }
+    }
+    samples
+}
+
+// Writes the given right-aligned samples of the given bit depth, left-aligning them so that the
+// writer discards any unused low bits.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub fn write_samples<W>(writer: &mut audrey::Writer<W>, bits: u32, samples: &[i32])
+where
+    W: std::io::Write + std::io::Seek,
+{
+    for &sample in samples {
+        writer.write_sample(sample << (32 - bits)).unwrap();
+    }
+}
+
+// Encodes the given right-aligned samples of the given bit depth using the writer returned by
+// `new`, returning the encoded bytes.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+pub fn encode<F>(bits: u32, samples: &[i32], new: F) -> Vec<u8>
+where
+    F: FnOnce(
+        &mut Cursor<Vec<u8>>,
+    ) -> Result<audrey::Writer<&mut Cursor<Vec<u8>>>, audrey::write::WriteError>,
+{
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = new(&mut cursor).unwrap();
+        write_samples(&mut writer, bits, samples);
+        writer.finalize().unwrap();
+    }
+    cursor.into_inner()
+}
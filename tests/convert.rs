@@ -0,0 +1,114 @@
+#![cfg(all(
+    feature = "flac",
+    feature = "ogg_vorbis",
+    feature = "wav",
+    feature = "caf_alac"
+))]
+
+extern crate audrey;
+
+use audrey::convert::ConvertError;
+use audrey::write::WriteError;
+use audrey::{Converter, Format, SampleFormat};
+
+const OGG_VORBIS: &str = "samples/sine_440hz_stereo.ogg";
+const WAV: &str = "samples/sine_440hz_stereo.wav";
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("audrey_convert_{}", name))
+}
+
+fn read_samples<P>(path: P) -> Vec<i16>
+where
+    P: AsRef<std::path::Path>,
+{
+    let mut reader = audrey::open(path).unwrap();
+    reader.samples::<i16>().map(Result::unwrap).collect()
+}
+
+#[test]
+fn convert_lossless() {
+    let original = read_samples(WAV);
+    for &format in &[Format::Flac, Format::CafAlac, Format::Wav] {
+        let path = temp_path(&format!("lossless.{}", format.extension()));
+        audrey::convert(WAV, &path).unwrap();
+        let reader = audrey::open(&path).unwrap();
+        let description = reader.description();
+        assert_eq!(description.format(), format);
+        assert_eq!(description.channel_count(), 2);
+        assert_eq!(read_samples(&path), original, "{:?}", format);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn convert_lossy_to_float() {
+    let path = temp_path("lossy.wav");
+    audrey::convert(OGG_VORBIS, &path).unwrap();
+    let mut reader = audrey::open(&path).unwrap();
+    match reader {
+        audrey::Reader::Wav(ref wav) => {
+            let spec = wav.spec();
+            assert_eq!(spec.sample_format, audrey::read::WavSampleFormat::Float);
+            assert_eq!(spec.channels, 2);
+            assert_eq!(spec.sample_rate, 44_100);
+        }
+        _ => panic!("Incorrect audio format"),
+    }
+    let num_samples = reader.samples::<f32>().map(Result::unwrap).count();
+    assert_eq!(num_samples, read_samples(OGG_VORBIS).len());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn convert_with_sample_format() {
+    let path = temp_path("i24.wav");
+    Converter::new()
+        .sample_format(SampleFormat::I24)
+        .convert(WAV, &path)
+        .unwrap();
+    match audrey::open(&path).unwrap() {
+        audrey::Reader::Wav(wav) => assert_eq!(wav.spec().bits_per_sample, 24),
+        _ => panic!("Incorrect audio format"),
+    }
+    assert_eq!(read_samples(&path), read_samples(WAV));
+    std::fs::remove_file(&path).unwrap();
+
+    // FLAC cannot store floating point samples, which is found before any existing output is
+    // truncated.
+    let path = temp_path("f32.flac");
+    std::fs::write(&path, b"existing").unwrap();
+    match Converter::new()
+        .sample_format(SampleFormat::F32)
+        .convert(WAV, &path)
+    {
+        Err(ConvertError::Write(WriteError::UnsupportedSpec(spec))) => {
+            assert_eq!(spec.sample_format, SampleFormat::F32)
+        }
+        _ => panic!("expected an unsupported spec error"),
+    }
+    assert_eq!(std::fs::read(&path).unwrap(), b"existing");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn convert_float_to_flac() {
+    // Without a requested sample format, float input is stored with FLAC's largest bit depth.
+    let path = temp_path("float.flac");
+    audrey::convert(OGG_VORBIS, &path).unwrap();
+    match audrey::open(&path).unwrap() {
+        audrey::Reader::Flac(flac) => assert_eq!(flac.streaminfo().bits_per_sample, 24),
+        _ => panic!("Incorrect audio format"),
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn convert_unsupported_extension() {
+    let path = temp_path("unsupported.xyz");
+    match audrey::convert(WAV, &path) {
+        Err(ConvertError::Write(WriteError::UnsupportedFormat)) => (),
+        _ => panic!("expected an unsupported format error"),
+    }
+    assert!(!path.exists());
+}
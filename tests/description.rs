@@ -0,0 +1,117 @@
+#![cfg(all(
+    feature = "flac",
+    feature = "ogg_vorbis",
+    feature = "wav",
+    feature = "caf_alac"
+))]
+
+extern crate audrey;
+
+use audrey::write::Spec;
+use audrey::{Format, SampleFormat};
+use std::io::Cursor;
+
+mod common;
+
+const FLAC: &str = "samples/sine_440hz_stereo.flac";
+const OGG_VORBIS: &str = "samples/sine_440hz_stereo.ogg";
+const WAV: &str = "samples/sine_440hz_stereo.wav";
+const CAF_ALAC: &str = "samples/sine_440hz_stereo.caf";
+
+// The channel counts and sample rates of the generated fixtures.
+const VARIANTS: [(u32, u32); 4] = [(1, 44_100), (6, 44_100), (2, 48_000), (2, 96_000)];
+
+// Deliberately not a multiple of any encoder's block or packet size.
+const FRAME_COUNT: u64 = 10_007;
+
+// Checks every field of the description against the given expectations, along with the number
+// of frames actually decoded.
+fn check_description(
+    mut reader: audrey::Reader<Cursor<Vec<u8>>>,
+    format: Format,
+    channel_count: u32,
+    sample_rate: u32,
+    frame_count: u64,
+) {
+    let description = reader.description();
+    let context = format!("{:?} {}ch {}Hz", format, channel_count, sample_rate);
+    assert_eq!(description.format(), format, "{}", context);
+    assert_eq!(description.channel_count(), channel_count, "{}", context);
+    assert_eq!(description.sample_rate(), sample_rate, "{}", context);
+    assert_eq!(description.frame_count(), Some(frame_count), "{}", context);
+    let num_samples = reader.samples::<i16>().map(Result::unwrap).count() as u64;
+    assert_eq!(
+        num_samples,
+        frame_count * u64::from(channel_count),
+        "{}",
+        context
+    );
+}
+
+fn open(path: &str) -> audrey::Reader<Cursor<Vec<u8>>> {
+    audrey::Reader::new(Cursor::new(std::fs::read(path).unwrap())).unwrap()
+}
+
+// Encodes `FRAME_COUNT` frames of a quiet ramp with the given channel count and sample rate.
+fn encode(format: Format, channel_count: u32, sample_rate: u32) -> Cursor<Vec<u8>> {
+    let spec = Spec {
+        channel_count,
+        sample_rate,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, format, spec).unwrap();
+        for i in 0..FRAME_COUNT {
+            for channel in 0..channel_count {
+                let sample = ((i + u64::from(channel) * 100) % 1_000) as i16 - 500;
+                writer.write_sample(sample).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.set_position(0);
+    cursor
+}
+
+#[test]
+fn description_samples() {
+    let samples = [
+        (FLAC, Format::Flac),
+        (OGG_VORBIS, Format::OggVorbis),
+        (WAV, Format::Wav),
+        (CAF_ALAC, Format::CafAlac),
+    ];
+    for &(path, format) in samples.iter() {
+        check_description(open(path), format, 2, 44_100, 44_100);
+    }
+}
+
+#[test]
+fn description_generated() {
+    for &format in &[Format::Flac, Format::Wav, Format::CafAlac] {
+        for &(channel_count, sample_rate) in VARIANTS.iter() {
+            let reader = audrey::Reader::new(encode(format, channel_count, sample_rate)).unwrap();
+            assert_eq!(reader.description().sample_format(), SampleFormat::I16);
+            check_description(reader, format, channel_count, sample_rate, FRAME_COUNT);
+        }
+    }
+}
+
+#[test]
+fn description_generated_ogg_vorbis() {
+    for &(channel_count, sample_rate) in VARIANTS.iter() {
+        let levels = (0..u64::from(channel_count))
+            .map(|channel| 10 + channel * 5)
+            .collect::<Vec<_>>();
+        let stream = common::vorbis_stream_with(&levels, &[], sample_rate, 0);
+        let reader = audrey::Reader::new(Cursor::new(stream)).unwrap();
+        check_description(
+            reader,
+            Format::OggVorbis,
+            channel_count,
+            sample_rate,
+            common::VORBIS_FRAME_COUNT,
+        );
+    }
+}
@@ -0,0 +1,117 @@
+#![cfg(all(feature = "flac", feature = "wav"))]
+
+extern crate audrey;
+
+use audrey::write::{FlacOptions, Spec};
+use audrey::{Format, SampleFormat};
+use std::io::Cursor;
+
+mod common;
+
+// The number of frames within each generated fixture, spanning several blocks.
+const FRAME_COUNT: usize = 5_000;
+
+// The smallest sample format able to hold samples of the given bit depth.
+fn container(bits: u32) -> SampleFormat {
+    match bits {
+        0..=8 => SampleFormat::I8,
+        9..=16 => SampleFormat::I16,
+        17..=24 => SampleFormat::I24,
+        _ => SampleFormat::I32,
+    }
+}
+
+// Stereo samples of the given bit depth, right-aligned, beginning with full scale frames that
+// require 33 bits within the side channel of 32-bit audio.
+fn test_signal(bits: u32) -> Vec<i32> {
+    let max = ((1i64 << (bits - 1)) - 1) as i32;
+    let min = -max - 1;
+    let mut samples = vec![min, max, max, min, min, min, max, max, 0, -1];
+    samples.extend(common::test_signal(
+        2,
+        FRAME_COUNT - samples.len() / 2,
+        bits,
+    ));
+    samples
+}
+
+// Encodes the given right-aligned samples at the given bit depth using the given format, within
+// the smallest sample format able to hold them.
+fn encode(format: Format, bits: u32, samples: &[i32]) -> Cursor<Vec<u8>> {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: container(bits),
+    };
+    // Samples are written left-aligned, leaving the writer to discard the unused low bits.
+    Cursor::new(common::encode(bits, samples, |cursor| match format {
+        Format::Flac => {
+            let options = FlacOptions {
+                bits_per_sample: Some(bits),
+                ..Default::default()
+            };
+            audrey::Writer::new_flac(cursor, spec, options)
+        }
+        _ => audrey::Writer::new(cursor, format, spec),
+    }))
+}
+
+#[test]
+fn flac_bit_depths_round_trip() {
+    for bits in 4..=32 {
+        let signal = test_signal(bits);
+        let mut reader = audrey::Reader::new(encode(Format::Flac, bits, &signal)).unwrap();
+        let description = reader.description();
+        assert_eq!(description.bits_per_sample(), Some(bits));
+        assert_eq!(description.sample_format(), container(bits));
+        assert_eq!(description.frame_count(), Some(FRAME_COUNT as u64));
+
+        let samples = reader
+            .samples::<i32>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let expected = signal
+            .iter()
+            .map(|&sample| sample << (32 - bits))
+            .collect::<Vec<_>>();
+        assert_eq!(samples, expected, "{} bits", bits);
+
+        match audrey::Reader::new(encode(Format::Flac, bits, &signal)).unwrap() {
+            audrey::Reader::Flac(mut flac) => {
+                assert_eq!(flac.streaminfo().bits_per_sample, bits);
+                let samples = std::iter::from_fn(|| flac.next_sample())
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>();
+                assert_eq!(samples, signal, "{} bits", bits);
+            }
+            _ => panic!("Incorrect audio format"),
+        }
+    }
+}
+
+#[test]
+fn flac_bit_depths_match_wav() {
+    for bits in 4..=32 {
+        let signal = test_signal(bits);
+        let mut flac = audrey::Reader::new(encode(Format::Flac, bits, &signal)).unwrap();
+        let mut wav = audrey::Reader::new(encode(Format::Wav, bits, &signal)).unwrap();
+        let flac_samples = flac
+            .samples::<f32>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let wav_samples = wav.samples::<f32>().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(flac_samples, wav_samples, "{} bits", bits);
+        assert_eq!(flac_samples[0], -1.0, "{} bits", bits);
+        // The largest positive sample of 4-bit audio is 7/8 of full scale.
+        assert!(
+            flac_samples[1] >= 0.875 && flac_samples[1] <= 1.0,
+            "{} bits",
+            bits
+        );
+
+        let mut flac = audrey::Reader::new(encode(Format::Flac, bits, &signal)).unwrap();
+        let mut buf = vec![0.0f32; signal.len() + 1];
+        assert_eq!(flac.read_into(&mut buf).unwrap(), signal.len());
+        assert_eq!(&buf[..signal.len()], &wav_samples[..], "{} bits", bits);
+    }
+}
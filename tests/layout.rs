@@ -0,0 +1,189 @@
+extern crate audrey;
+
+use audrey::layout::{ChannelLayout, Speaker};
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use audrey::write::Spec;
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use audrey::{Format, SampleFormat};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use std::io::Cursor;
+
+// Encodes a tenth of a second of silence with the given number of channels.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn encode(format: Format, channel_count: u32) -> Cursor<Vec<u8>> {
+    let spec = Spec {
+        channel_count,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, format, spec).unwrap();
+        for _ in 0..4_410 * channel_count {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.set_position(0);
+    cursor
+}
+
+#[test]
+fn default_layouts() {
+    use audrey::layout::Speaker::*;
+    assert_eq!(ChannelLayout::default_for(1).speakers(), &[FrontCenter]);
+    assert_eq!(
+        ChannelLayout::default_for(4).speakers(),
+        &[FrontLeft, FrontRight, BackLeft, BackRight]
+    );
+    let surround = ChannelLayout::default_for(6);
+    assert_eq!(surround.channel_of(LowFrequency), Some(3));
+    assert_eq!(surround.channel_mask(), Some(0x3F));
+    let surround = ChannelLayout::default_for(8);
+    assert_eq!(surround.channel_mask(), Some(0x63F));
+    assert_eq!(ChannelLayout::default_for(9).speakers(), &[Unknown; 9][..]);
+}
+
+#[test]
+fn vorbis_layouts() {
+    use audrey::layout::Speaker::*;
+    assert_eq!(
+        ChannelLayout::vorbis(3).speakers(),
+        &[FrontLeft, FrontCenter, FrontRight]
+    );
+    let surround = ChannelLayout::vorbis(6);
+    assert_eq!(surround.channel_of(LowFrequency), Some(5));
+    assert_eq!(surround.channel_of(FrontCenter), Some(1));
+    // Vorbis channels are not in the order of the WAV channel mask.
+    assert_eq!(surround.channel_mask(), None);
+    assert_eq!(ChannelLayout::vorbis(8).channel_of(LowFrequency), Some(7));
+}
+
+#[test]
+fn channel_mask_round_trip() {
+    for &mask in &[0x4u32, 0x3, 0x7, 0x33, 0x3F, 0x60F, 0x63F, 0x3FFFF] {
+        let channel_count = mask.count_ones() as usize;
+        let layout = ChannelLayout::from_channel_mask(mask, channel_count);
+        assert_eq!(layout.channel_count(), channel_count);
+        assert_eq!(layout.channel_mask(), Some(mask));
+        for (index, &speaker) in layout.speakers().iter().enumerate() {
+            assert_eq!(layout.channel_of(speaker), Some(index));
+        }
+    }
+    assert_eq!(Speaker::LowFrequency.channel_mask_bit(), Some(0x8));
+    assert_eq!(Speaker::Unknown.channel_mask_bit(), None);
+
+    // Channels beyond the bits of the mask have no designated position.
+    let layout = ChannelLayout::from_channel_mask(0x3, 3);
+    assert_eq!(
+        layout.speakers(),
+        &[Speaker::FrontLeft, Speaker::FrontRight, Speaker::Unknown]
+    );
+    assert_eq!(layout.channel_mask(), None);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_channel_mask() {
+    // Audio of more than 2 channels is written with the channel mask of the default layout.
+    let reader = audrey::Reader::new(encode(Format::Wav, 6)).unwrap();
+    let layout = reader.channel_layout();
+    assert_eq!(layout, ChannelLayout::default_for(6));
+    assert_eq!(layout.channel_of(Speaker::LowFrequency), Some(3));
+
+    let reader = audrey::open("samples/sine_440hz_stereo.wav").unwrap();
+    assert_eq!(reader.channel_layout(), ChannelLayout::default_for(2));
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_custom_channel_mask() {
+    // 5.1 with side rather than back surround speakers.
+    const MASK: u32 = 0x60F;
+    let mut bytes = encode(Format::Wav, 6).into_inner();
+    // The channel mask lies 20 bytes into the `fmt ` chunk, following the RIFF header and the
+    // `JUNK` chunk reserving space for a `ds64` chunk.
+    assert_eq!(&bytes[12..16], b"JUNK");
+    assert_eq!(&bytes[48..52], b"fmt ");
+    assert_eq!(&bytes[56..58], &0xFFFEu16.to_le_bytes());
+    bytes[76..80].copy_from_slice(&MASK.to_le_bytes());
+
+    let reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    let layout = reader.channel_layout();
+    assert_eq!(layout.channel_mask(), Some(MASK));
+    assert_eq!(layout.channel_of(Speaker::SideLeft), Some(4));
+    assert_eq!(layout.channel_of(Speaker::BackLeft), None);
+}
+
+#[cfg(feature = "flac")]
+#[test]
+fn flac_default_layouts() {
+    for channel_count in 1..=8 {
+        let reader = audrey::Reader::new(encode(Format::Flac, channel_count)).unwrap();
+        assert_eq!(
+            reader.channel_layout(),
+            ChannelLayout::default_for(channel_count as usize)
+        );
+    }
+}
+
+#[cfg(feature = "flac")]
+#[test]
+fn flac_channel_mask_comment() {
+    let mut bytes = encode(Format::Flac, 4).into_inner();
+    // Append a VORBIS_COMMENT block after the final metadata block, assumed to be STREAMINFO.
+    assert_eq!(&bytes[0..4], b"fLaC");
+    assert_eq!(bytes[4], 0x80);
+    bytes[4] = 0;
+    let vendor = b"audrey";
+    let comment = b"WAVEFORMATEXTENSIBLE_CHANNEL_MASK=0x0107";
+    let mut block = Vec::new();
+    block.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    block.extend_from_slice(vendor);
+    block.extend_from_slice(&1u32.to_le_bytes());
+    block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+    block.extend_from_slice(comment);
+    let mut header = vec![0x84];
+    header.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    let end = 8 + 34;
+    bytes.splice(end..end, header.into_iter().chain(block));
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(
+        reader.channel_layout().speakers(),
+        &[
+            Speaker::FrontLeft,
+            Speaker::FrontRight,
+            Speaker::FrontCenter,
+            Speaker::BackCenter
+        ]
+    );
+    assert_eq!(reader.samples::<i16>().count(), 4_410 * 4);
+}
+
+#[cfg(feature = "ogg_vorbis")]
+#[test]
+fn ogg_vorbis_layout() {
+    let reader = audrey::open("samples/sine_440hz_stereo.ogg").unwrap();
+    assert_eq!(reader.channel_layout(), ChannelLayout::vorbis(2));
+}
+
+#[cfg(feature = "caf_alac")]
+#[test]
+fn caf_channel_layouts() {
+    // Audio of more than 2 channels is written with a `chan` chunk describing the WAV order.
+    for channel_count in 1..=8 {
+        let reader = audrey::Reader::new(encode(Format::CafAlac, channel_count)).unwrap();
+        assert_eq!(
+            reader.channel_layout(),
+            ChannelLayout::default_for(channel_count as usize)
+        );
+    }
+
+    let reader = audrey::open("samples/sine_440hz_stereo.caf").unwrap();
+    assert_eq!(
+        reader.channel_layout().speakers(),
+        &[Speaker::FrontLeft, Speaker::FrontRight]
+    );
+}
@@ -0,0 +1,205 @@
+extern crate audrey;
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use audrey::{write::Spec, Format, Marker, SampleFormat};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use std::io::Cursor;
+
+// Encodes a second of stereo silence.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn encode(format: Format) -> Vec<u8> {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, format, spec).unwrap();
+        for _ in 0..88_200 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.into_inner()
+}
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn marker(frame: u64, label: &str) -> Marker {
+    Marker {
+        frame,
+        label: label.to_string(),
+    }
+}
+
+#[test]
+fn samples_markers() {
+    let paths: &[&str] = &[
+        #[cfg(feature = "flac")]
+        "samples/sine_440hz_stereo.flac",
+        #[cfg(feature = "ogg_vorbis")]
+        "samples/sine_440hz_stereo.ogg",
+        #[cfg(feature = "caf_alac")]
+        "samples/sine_440hz_stereo.caf",
+    ];
+    for path in paths {
+        let reader = audrey::open(path).unwrap();
+        assert!(reader.markers().is_empty(), "{}", path);
+        assert_eq!(reader.cue_sheet(), None);
+    }
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_cue_points() {
+    // The sample holds a single cue point, labelled by a `LIST` chunk of the `adtl` type.
+    let reader = audrey::open("samples/sine_440hz_stereo.wav").unwrap();
+    assert_eq!(reader.markers(), vec![marker(0, "Tempo: 120.0")]);
+    assert_eq!(reader.cue_sheet(), None);
+
+    let mut cue = 3u32.to_le_bytes().to_vec();
+    for &(id, frame) in &[(1u32, 22_050u32), (2, 100), (3, 44_100)] {
+        cue.extend_from_slice(&id.to_le_bytes());
+        cue.extend_from_slice(&id.to_le_bytes());
+        cue.extend_from_slice(b"data");
+        cue.extend_from_slice(&[0; 8]);
+        cue.extend_from_slice(&frame.to_le_bytes());
+    }
+    let mut list = b"adtl".to_vec();
+    for &(id, label) in &[(3u32, &b"Chorus\0"[..]), (1, b"Verse\0")] {
+        list.extend_from_slice(b"labl");
+        list.extend_from_slice(&(label.len() as u32 + 4).to_le_bytes());
+        list.extend_from_slice(&id.to_le_bytes());
+        list.extend_from_slice(label);
+        if label.len() % 2 == 1 {
+            list.push(0);
+        }
+    }
+    let mut bytes = encode(Format::Wav);
+    for &(id, ref contents) in &[(b"cue ", cue), (b"LIST", list)] {
+        bytes.extend_from_slice(id);
+        bytes.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(contents);
+    }
+    let riff_len = bytes.len() as u32 - 8;
+    bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(
+        reader.markers(),
+        vec![
+            marker(100, ""),
+            marker(22_050, "Verse"),
+            marker(44_100, "Chorus")
+        ]
+    );
+    assert_eq!(reader.samples::<i16>().count(), 88_200);
+}
+
+// The offset, number and index points of a track, with index offsets relative to the track.
+#[cfg(feature = "flac")]
+type Track = (u64, u8, &'static [(u64, u8)]);
+
+#[cfg(feature = "flac")]
+#[test]
+fn flac_cue_sheet() {
+    let tracks: &[Track] = &[
+        (0, 1, &[(0, 1)]),
+        (29_400, 2, &[(0, 0), (588, 1)]),
+        (88_200, 170, &[]),
+    ];
+    let mut block = b"1234567890123".to_vec();
+    block.resize(128, 0);
+    block.extend_from_slice(&88_200u64.to_be_bytes());
+    block.push(0x80);
+    block.resize(block.len() + 258, 0);
+    block.push(tracks.len() as u8);
+    for &(offset, number, indices) in tracks {
+        block.extend_from_slice(&offset.to_be_bytes());
+        block.push(number);
+        if number == 2 {
+            block.extend_from_slice(b"USRC17607839");
+        } else {
+            block.extend_from_slice(&[0; 12]);
+        }
+        // The pre-emphasis flag is set for the second track alone.
+        block.push(if number == 2 { 0x40 } else { 0 });
+        block.extend_from_slice(&[0; 13]);
+        block.push(indices.len() as u8);
+        for &(offset, number) in indices {
+            block.extend_from_slice(&offset.to_be_bytes());
+            block.push(number);
+            block.extend_from_slice(&[0; 3]);
+        }
+    }
+
+    // Insert the CUESHEET block following STREAMINFO, which is the last block.
+    let mut bytes = encode(Format::Flac);
+    assert_eq!(bytes[4], 0x80);
+    bytes[4] = 0;
+    let mut header = vec![0x85];
+    header.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    let end = 8 + 34;
+    bytes.splice(end..end, header.into_iter().chain(block));
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    let cue_sheet = reader.cue_sheet().unwrap();
+    assert_eq!(cue_sheet.media_catalog_number, "1234567890123");
+    assert_eq!(cue_sheet.lead_in, 88_200);
+    assert!(cue_sheet.is_cd);
+    assert_eq!(cue_sheet.tracks.len(), 3);
+    let track = &cue_sheet.tracks[1];
+    assert_eq!(track.frame, 29_400);
+    assert_eq!(track.number, 2);
+    assert_eq!(track.isrc, "USRC17607839");
+    assert!(track.is_audio);
+    assert!(track.pre_emphasis);
+    assert_eq!(track.indices[1].frame, 29_988);
+    assert_eq!(track.start_frame(), 29_988);
+    assert!(!cue_sheet.tracks[0].pre_emphasis);
+    assert!(cue_sheet.tracks[2].is_lead_out());
+
+    assert_eq!(
+        reader.markers(),
+        vec![marker(0, "Track 01"), marker(29_988, "Track 02")]
+    );
+    assert_eq!(reader.samples::<i16>().count(), 88_200);
+}
+
+#[cfg(feature = "caf_alac")]
+#[test]
+fn caf_markers() {
+    let mut mark = Vec::new();
+    // No SMPTE time type, followed by the number of markers.
+    mark.extend_from_slice(&0u32.to_be_bytes());
+    mark.extend_from_slice(&3u32.to_be_bytes());
+    for &(frame, id) in &[(44_100.0f64, 2u32), (0.0, 1), (1_000.0, 7)] {
+        mark.extend_from_slice(b"rbeg");
+        mark.extend_from_slice(&frame.to_be_bytes());
+        mark.extend_from_slice(&id.to_be_bytes());
+        mark.extend_from_slice(&[0xFF; 8]);
+        mark.extend_from_slice(&0u32.to_be_bytes());
+    }
+    let mut strings = Vec::new();
+    strings.extend_from_slice(&2u32.to_be_bytes());
+    for &(id, offset) in &[(1u32, 0i64), (2, 6)] {
+        strings.extend_from_slice(&id.to_be_bytes());
+        strings.extend_from_slice(&offset.to_be_bytes());
+    }
+    strings.extend_from_slice(b"Start\0End\0");
+
+    let mut bytes = encode(Format::CafAlac);
+    for &(chunk_type, ref contents) in &[(b"mark", mark), (b"strg", strings)] {
+        bytes.extend_from_slice(chunk_type);
+        bytes.extend_from_slice(&(contents.len() as i64).to_be_bytes());
+        bytes.extend_from_slice(contents);
+    }
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(
+        reader.markers(),
+        vec![marker(0, "Start"), marker(1_000, ""), marker(44_100, "End")]
+    );
+    assert_eq!(reader.cue_sheet(), None);
+    assert_eq!(reader.samples::<i16>().count(), 88_200);
+}
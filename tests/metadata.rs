@@ -0,0 +1,269 @@
+extern crate audrey;
+
+use audrey::Metadata;
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use audrey::{write::Spec, Format, SampleFormat};
+use std::io::Cursor;
+
+#[cfg(feature = "ogg_vorbis")]
+mod common;
+
+#[test]
+fn metadata_accessors() {
+    let mut metadata = Metadata::new();
+    assert!(metadata.is_empty());
+    metadata.push("Title", "Sine");
+    metadata.push("ARTIST", "First");
+    metadata.push("artist", "Second");
+    metadata.push("TRACKNUMBER", "3/12");
+    assert_eq!(metadata.len(), 4);
+    assert_eq!(metadata.title(), Some("Sine"));
+    assert_eq!(metadata.get("title"), Some("Sine"));
+    assert_eq!(metadata.artist(), Some("First"));
+    assert_eq!(metadata.get_all("Artist"), vec!["First", "Second"]);
+    assert_eq!(metadata.track_number(), Some(3));
+    assert_eq!(metadata.album(), None);
+
+    metadata.set("artist", "Only");
+    assert_eq!(metadata.get_all("ARTIST"), vec!["Only"]);
+    metadata.remove("tracknumber");
+    assert_eq!(metadata.track_number(), None);
+    assert_eq!(
+        metadata.iter().collect::<Vec<_>>(),
+        vec![("Title", "Sine"), ("artist", "Only")]
+    );
+}
+
+#[cfg(feature = "flac")]
+#[test]
+fn flac_vorbis_comments() {
+    // The reference encoder writes a VORBIS_COMMENT block holding only the vendor string.
+    let reader = audrey::open("samples/sine_440hz_stereo.flac").unwrap();
+    assert!(reader.metadata().is_empty());
+
+    let comments = [
+        "TITLE=Sine",
+        "ARTIST=First",
+        "ARTIST=Second",
+        "ALBUM=Tones",
+        "TRACKNUMBER=7",
+        "GENRE=Test",
+    ];
+    let mut block = Vec::new();
+    block.extend_from_slice(&6u32.to_le_bytes());
+    block.extend_from_slice(b"audrey");
+    block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in &comments {
+        block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        block.extend_from_slice(comment.as_bytes());
+    }
+    // Replace the contents of the existing VORBIS_COMMENT block.
+    let mut bytes = std::fs::read("samples/sine_440hz_stereo.flac").unwrap();
+    let mut position = 4;
+    loop {
+        let header = &bytes[position..position + 4];
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        if header[0] & 0x7F == 4 {
+            let len_bytes = (block.len() as u32).to_be_bytes();
+            bytes[position + 1..position + 4].copy_from_slice(&len_bytes[1..]);
+            bytes.splice(position + 4..position + 4 + len, block);
+            break;
+        }
+        assert_eq!(header[0] & 0x80, 0, "missing VORBIS_COMMENT block");
+        position += 4 + len;
+    }
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    let metadata = reader.metadata();
+    assert_eq!(metadata.title(), Some("Sine"));
+    assert_eq!(metadata.get_all("artist"), vec!["First", "Second"]);
+    assert_eq!(metadata.album(), Some("Tones"));
+    assert_eq!(metadata.track_number(), Some(7));
+    assert_eq!(metadata.genre(), Some("Test"));
+    assert!(reader.samples::<i16>().all(|sample| sample.is_ok()));
+}
+
+#[cfg(feature = "ogg_vorbis")]
+#[test]
+fn ogg_vorbis_comments() {
+    let reader = audrey::open("samples/sine_440hz_stereo.ogg").unwrap();
+    assert!(reader.metadata().is_empty());
+
+    let stream = common::vorbis_stream(&[40, 20], &["TITLE=Sine", "tracknumber=2/9", "Date=2020"]);
+    let reader = audrey::Reader::new(Cursor::new(stream)).unwrap();
+    let metadata = reader.metadata();
+    assert_eq!(metadata.len(), 3);
+    assert_eq!(metadata.title(), Some("Sine"));
+    assert_eq!(metadata.track_number(), Some(2));
+    assert_eq!(metadata.date(), Some("2020"));
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_list_info() {
+    // The sample holds a `LIST` chunk of the `adtl` type, which holds no tags.
+    let reader = audrey::open("samples/sine_440hz_stereo.wav").unwrap();
+    assert!(reader.metadata().is_empty());
+
+    let mut list = b"INFO".to_vec();
+    for &(id, value) in &[
+        (b"INAM", &b"Sine\0"[..]),
+        (b"IART", b"Artist\0"),
+        (b"IPRD", b"Tones\0"),
+        (b"ITRK", b"4\0"),
+        (b"ICMT", b"Odd\0"),
+        (b"ISRC", b"Source\0"),
+    ] {
+        list.extend_from_slice(id);
+        list.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        list.extend_from_slice(value);
+        if value.len() % 2 == 1 {
+            list.push(0);
+        }
+    }
+    let mut bytes = std::fs::read("samples/sine_440hz_stereo.wav").unwrap();
+    bytes.extend_from_slice(b"LIST");
+    bytes.extend_from_slice(&(list.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&list);
+    let riff_len = bytes.len() as u32 - 8;
+    bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    let metadata = reader.metadata();
+    assert_eq!(metadata.title(), Some("Sine"));
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(metadata.album(), Some("Tones"));
+    assert_eq!(metadata.track_number(), Some(4));
+    assert_eq!(metadata.comment(), Some("Odd"));
+    assert_eq!(metadata.get("ISRC"), Some("Source"));
+    assert_eq!(reader.samples::<i16>().count(), 88_200);
+}
+
+#[cfg(feature = "caf_alac")]
+#[test]
+fn caf_info() {
+    let reader = audrey::open("samples/sine_440hz_stereo.caf").unwrap();
+    let metadata = reader.metadata();
+    assert_eq!(metadata.date(), Some("2017-01-09"));
+    assert_eq!(metadata.get("encoded_by"), Some("Logic Pro X"));
+    assert_eq!(metadata.get("ENCODER"), Some("Lavf57.25.100"));
+}
+
+// Tags with an equivalent in every format that can be written.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn common_tags() -> Metadata {
+    vec![
+        ("TITLE", "Sine"),
+        ("ARTIST", "Tones Inc."),
+        ("ALBUM", "Test Tones"),
+        ("TRACKNUMBER", "3"),
+        ("DATE", "2017"),
+        ("GENRE", "Test"),
+        ("COMMENT", "A 440Hz sine"),
+    ]
+    .into_iter()
+    .collect()
+}
+
+// Encodes a tenth of a second of stereo silence with the given tags.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn encode_with_metadata(format: Format, metadata: &Metadata) -> Cursor<Vec<u8>> {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, format, spec).unwrap();
+        writer.set_metadata(&Metadata::new()).unwrap();
+        writer.set_metadata(metadata).unwrap();
+        for _ in 0..8_820 {
+            writer.write_sample(0i16).unwrap();
+        }
+        match writer.set_metadata(metadata) {
+            Err(audrey::write::WriteError::AudioStarted) => (),
+            _ => panic!("expected an error setting metadata after writing audio"),
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.set_position(0);
+    cursor
+}
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+#[test]
+fn write_metadata_round_trip() {
+    let formats = [
+        #[cfg(feature = "flac")]
+        Format::Flac,
+        #[cfg(feature = "wav")]
+        Format::Wav,
+        #[cfg(feature = "caf_alac")]
+        Format::CafAlac,
+    ];
+    for &format in &formats {
+        let metadata = common_tags();
+        let mut reader = audrey::Reader::new(encode_with_metadata(format, &metadata)).unwrap();
+        assert_eq!(reader.metadata(), metadata, "{:?}", format);
+        assert_eq!(reader.samples::<i16>().count(), 8_820);
+
+        // Without tags, no tag block or chunk is written.
+        let reader = audrey::Reader::new(encode_with_metadata(format, &Metadata::new())).unwrap();
+        assert!(reader.metadata().is_empty());
+    }
+}
+
+#[cfg(feature = "flac")]
+#[test]
+fn write_flac_arbitrary_tags() {
+    let mut metadata = common_tags();
+    metadata.push("ARTIST", "Second Artist");
+    metadata.push("REPLAYGAIN_TRACK_GAIN", "-1.00 dB");
+    metadata.push("Description", "Üñíçødé");
+    let reader = audrey::Reader::new(encode_with_metadata(Format::Flac, &metadata)).unwrap();
+    assert_eq!(reader.metadata(), metadata);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn write_wav_info_identifiers() {
+    let mut metadata = common_tags();
+    metadata.push("ISRC", "Source");
+    metadata.push("REPLAYGAIN_TRACK_GAIN", "-1.00 dB");
+    let mut reader = audrey::Reader::new(encode_with_metadata(Format::Wav, &metadata)).unwrap();
+    // Tags without a RIFF `INFO` identifier cannot be written.
+    metadata.remove("REPLAYGAIN_TRACK_GAIN");
+    assert_eq!(reader.metadata(), metadata);
+    match reader {
+        audrey::Reader::Wav(ref wav) => {
+            let list = wav.chunk(b"LIST").unwrap();
+            assert_eq!(&list[..8], b"INFOINAM");
+        }
+        #[allow(unreachable_patterns)]
+        _ => panic!("Incorrect audio format"),
+    }
+    assert_eq!(reader.samples::<i16>().count(), 8_820);
+}
+
+#[cfg(all(feature = "caf_alac", feature = "flac", feature = "wav"))]
+#[test]
+fn convert_preserves_metadata() {
+    let dir = std::env::temp_dir();
+    let flac = dir.join("audrey_convert_metadata.flac");
+    let wav = dir.join("audrey_convert_metadata.wav");
+    let caf = dir.join("audrey_convert_metadata.caf");
+    std::fs::write(
+        &flac,
+        encode_with_metadata(Format::Flac, &common_tags()).into_inner(),
+    )
+    .unwrap();
+    audrey::convert(&flac, &wav).unwrap();
+    audrey::convert(&wav, &caf).unwrap();
+    for path in &[&wav, &caf] {
+        assert_eq!(audrey::open(path).unwrap().metadata(), common_tags());
+    }
+    for path in &[flac, wav, caf] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+#![cfg(feature = "wav")]
+
+extern crate audrey;
+
+use audrey::mix::MixMatrix;
+use audrey::write::Spec;
+use audrey::{Format, SampleFormat};
+use std::io::Cursor;
+
+const WAV: &str = "samples/sine_440hz_stereo.wav";
+
+// Encodes the given interleaved float samples as a WAV of `channel_count` channels.
+fn encode(channel_count: u32, samples: &[f32]) -> audrey::Reader<Cursor<Vec<u8>>> {
+    let spec = Spec {
+        channel_count,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::F32,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.set_position(0);
+    audrey::Reader::new(cursor).unwrap()
+}
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+}
+
+#[test]
+fn frames_matching_channels() {
+    let mut reader = audrey::open(WAV).unwrap();
+    let samples = reader
+        .samples::<i16>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let mut reader = audrey::open(WAV).unwrap();
+    let frames = reader
+        .frames::<[i16; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames.len() * 2, samples.len());
+    for (frame, samples) in frames.iter().zip(samples.chunks(2)) {
+        assert_eq!(&frame[..], samples);
+    }
+}
+
+#[test]
+fn frames_mono_to_stereo() {
+    let mut reader = encode(1, &[0.5, -0.25, 0.125]);
+    let frames = reader
+        .frames::<[f32; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames, vec![[0.5, 0.5], [-0.25, -0.25], [0.125, 0.125]]);
+}
+
+#[test]
+fn frames_stereo_to_mono() {
+    let mut reader = encode(2, &[0.5, 0.25, -1.0, 1.0]);
+    let frames = reader
+        .frames::<[f32; 1]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames, vec![[0.375], [0.0]]);
+
+    let mut reader = audrey::open(WAV).unwrap();
+    let stereo = reader
+        .frames::<[i16; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let mut reader = audrey::open(WAV).unwrap();
+    let mono = reader
+        .frames::<[i16; 1]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(mono.len(), stereo.len());
+    for (mono, stereo) in mono.iter().zip(&stereo) {
+        let average = (i32::from(stereo[0]) + i32::from(stereo[1])) / 2;
+        assert!((i32::from(mono[0]) - average).abs() <= 1);
+    }
+}
+
+#[test]
+fn frames_surround_to_stereo() {
+    // Front left, front right, front centre, LFE, back left, back right.
+    let mut reader = encode(6, &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6]);
+    let frames = reader
+        .frames::<[f32; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames.len(), 1);
+    let c = std::f32::consts::FRAC_1_SQRT_2;
+    let norm = 1.0 + 2.0 * c;
+    assert_close(frames[0][0], (0.1 + c * 0.3 + c * 0.5) / norm);
+    assert_close(frames[0][1], (0.2 + c * 0.3 + c * 0.6) / norm);
+
+    // Full scale in every channel must not clip.
+    let mut reader = encode(8, &[1.0; 8]);
+    let frames = reader
+        .frames::<[f32; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_close(frames[0][0], 1.0);
+    assert_close(frames[0][1], 1.0);
+}
+
+#[test]
+fn frames_with_mix() {
+    // Swap the channels and silence the third output channel.
+    let matrix = MixMatrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]);
+    let mut reader = encode(2, &[0.5, -0.5, 0.25, 0.75]);
+    let frames = reader
+        .frames_with_mix::<[f32; 3]>(matrix)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames, vec![[-0.5, 0.5, 0.0], [0.75, 0.25, 0.0]]);
+}
+
+#[test]
+fn mix_matrix_default() {
+    assert_eq!(MixMatrix::default_for(3, 3), MixMatrix::identity(3));
+
+    let stereo_to_surround = MixMatrix::default_for(2, 6);
+    for output in 0..6 {
+        for input in 0..2 {
+            let gain = if output == input { 1.0 } else { 0.0 };
+            assert_eq!(stereo_to_surround.get(output, input), gain);
+        }
+    }
+
+    let mono_to_surround = MixMatrix::default_for(1, 6);
+    for output in 0..6 {
+        let gain = if output == 2 { 1.0 } else { 0.0 };
+        assert_eq!(mono_to_surround.get(output, 0), gain);
+    }
+
+    let surround_to_mono = MixMatrix::default_for(6, 1);
+    assert_eq!(surround_to_mono.get(0, 3), 0.0);
+    assert_close(
+        (0..6).map(|input| surround_to_mono.get(0, input)).sum(),
+        1.0,
+    );
+}
+
+#[test]
+#[should_panic]
+fn mix_matrix_wrong_len() {
+    MixMatrix::new(2, 2, vec![1.0; 3]);
+}
+
+#[test]
+fn frames_with_mix_partial_frame() {
+    // Two stereo frames read as though they were three channel audio.
+    let matrix = MixMatrix::identity(3);
+    let mut reader = encode(2, &[0.5, -0.5, 0.25, 0.75]);
+    let mut frames = reader.frames_with_mix::<[f32; 3]>(matrix);
+    assert_eq!(frames.next().unwrap().unwrap(), [0.5, -0.5, 0.25]);
+    match frames.next() {
+        Some(Err(audrey::read::FormatError::PartialFrame {
+            expected, found, ..
+        })) => {
+            assert_eq!(expected, 3);
+            assert_eq!(found, 1);
+        }
+        _ => panic!("expected a partial frame error"),
+    }
+    assert!(frames.next().is_none());
+}
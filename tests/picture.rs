@@ -0,0 +1,295 @@
+extern crate audrey;
+
+use audrey::PictureType;
+#[cfg(any(feature = "flac", feature = "wav"))]
+use audrey::{write::Spec, Format, SampleFormat};
+#[cfg(any(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+use std::io::Cursor;
+
+#[cfg(feature = "ogg_vorbis")]
+mod common;
+
+// The signature and `IHDR` chunk of a PNG file of the given dimensions.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&13u32.to_be_bytes());
+    png.extend_from_slice(b"IHDR");
+    png.extend_from_slice(&width.to_be_bytes());
+    png.extend_from_slice(&height.to_be_bytes());
+    // The bit depth, colour type, compression, filter and interlace methods, and the CRC.
+    png.extend_from_slice(&[8, 2, 0, 0, 0, 0x12, 0x34, 0x56, 0x78]);
+    png
+}
+
+// The start of a baseline JPEG file of the given dimensions, with a JFIF segment preceding the
+// start of frame.
+#[cfg(any(feature = "flac", feature = "wav"))]
+fn jpeg(width: u16, height: u16) -> Vec<u8> {
+    let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+    jpeg.extend_from_slice(b"JFIF\0\x01\x02\x00\x00\x01\x00\x01\x00\x00");
+    jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+    jpeg.extend_from_slice(&height.to_be_bytes());
+    jpeg.extend_from_slice(&width.to_be_bytes());
+    jpeg.extend_from_slice(&[0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+    jpeg.extend_from_slice(&[0xFF, 0xD9]);
+    jpeg
+}
+
+// The contents of a FLAC PICTURE block.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+fn flac_picture(
+    picture_type: u32,
+    mime_type: &str,
+    description: &str,
+    (width, height): (u32, u32),
+    data: &[u8],
+) -> Vec<u8> {
+    let mut block = Vec::new();
+    block.extend_from_slice(&picture_type.to_be_bytes());
+    block.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    block.extend_from_slice(mime_type.as_bytes());
+    block.extend_from_slice(&(description.len() as u32).to_be_bytes());
+    block.extend_from_slice(description.as_bytes());
+    block.extend_from_slice(&width.to_be_bytes());
+    block.extend_from_slice(&height.to_be_bytes());
+    // The colour depth and number of colours.
+    block.extend_from_slice(&24u32.to_be_bytes());
+    block.extend_from_slice(&0u32.to_be_bytes());
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    block.extend_from_slice(data);
+    block
+}
+
+// Encodes the given bytes as padded base64.
+#[cfg(any(feature = "flac", feature = "ogg_vorbis"))]
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let value = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(char::from(
+                    ALPHABET[(value >> (18 - 6 * i)) as usize & 0x3F],
+                ));
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[test]
+fn samples_have_no_pictures() {
+    let paths = [
+        #[cfg(feature = "flac")]
+        "samples/sine_440hz_stereo.flac",
+        #[cfg(feature = "ogg_vorbis")]
+        "samples/sine_440hz_stereo.ogg",
+        #[cfg(feature = "wav")]
+        "samples/sine_440hz_stereo.wav",
+        #[cfg(feature = "caf_alac")]
+        "samples/sine_440hz_stereo.caf",
+    ];
+    for path in &paths {
+        assert!(
+            audrey::open(path).unwrap().pictures().is_empty(),
+            "{}",
+            path
+        );
+    }
+}
+
+#[cfg(feature = "flac")]
+#[test]
+fn flac_pictures() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    // A picture within a `METADATA_BLOCK_PICTURE` comment, whose dimensions are left unspecified.
+    let back = jpeg(300, 200);
+    let comment = flac_picture(4, "image/jpeg", "", (0, 0), &back);
+    let metadata = vec![("METADATA_BLOCK_PICTURE", base64(&comment))]
+        .into_iter()
+        .collect();
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Flac, spec).unwrap();
+        writer.set_metadata(&metadata).unwrap();
+        for _ in 0..8_820 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    // Insert a PICTURE block following STREAMINFO.
+    let front = png(640, 480);
+    let block = flac_picture(3, "image/png", "Front", (640, 480), &front);
+    let mut header = vec![6];
+    header.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    let mut bytes = cursor.into_inner();
+    let end = 8 + 34;
+    bytes.splice(end..end, header.into_iter().chain(block));
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    let pictures = reader.pictures();
+    assert_eq!(pictures.len(), 2);
+    assert_eq!(pictures[0].picture_type(), PictureType::FrontCover);
+    assert_eq!(pictures[0].mime_type(), "image/png");
+    assert_eq!(pictures[0].description(), "Front");
+    assert_eq!((pictures[0].width(), pictures[0].height()), (640, 480));
+    assert_eq!(pictures[0].data(), &front[..]);
+    assert_eq!(pictures[1].picture_type(), PictureType::BackCover);
+    assert_eq!(pictures[1].mime_type(), "image/jpeg");
+    assert_eq!((pictures[1].width(), pictures[1].height()), (300, 200));
+    assert_eq!(pictures[1].data(), &back[..]);
+    assert_eq!(reader.samples::<i16>().count(), 8_820);
+}
+
+#[cfg(feature = "ogg_vorbis")]
+#[test]
+fn ogg_vorbis_pictures() {
+    let front = png(32, 32);
+    let block = flac_picture(1, "image/png", "Icon", (0, 0), &front);
+    let comment = format!("METADATA_BLOCK_PICTURE={}", base64(&block));
+    let stream = common::vorbis_stream(&[40, 20], &["TITLE=Sine", &comment]);
+    let reader = audrey::Reader::new(Cursor::new(stream)).unwrap();
+    let pictures = reader.pictures();
+    assert_eq!(pictures.len(), 1);
+    assert_eq!(pictures[0].picture_type(), PictureType::FileIcon);
+    assert_eq!(pictures[0].description(), "Icon");
+    assert_eq!((pictures[0].width(), pictures[0].height()), (32, 32));
+    assert_eq!(pictures[0].data(), &front[..]);
+}
+
+// An ID3v2 tag of the given version and flags holding the given frames.
+#[cfg(feature = "wav")]
+fn id3_tag(version: u8, flags: u8, frames: &[u8]) -> Vec<u8> {
+    let mut tag = b"ID3".to_vec();
+    tag.extend_from_slice(&[version, 0, flags]);
+    // The size of the tag excluding its header, followed by the frames and padding.
+    let len = frames.len() as u32 + 16;
+    tag.extend((0..4).rev().map(|i| (len >> (7 * i)) as u8 & 0x7F));
+    tag.extend_from_slice(frames);
+    tag.extend_from_slice(&[0; 16]);
+    tag
+}
+
+// Inserts a zero byte after every `0xFF` byte, as the ID3v2 unsynchronisation scheme.
+#[cfg(feature = "wav")]
+fn unsynchronise(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for &byte in bytes {
+        out.push(byte);
+        if byte == 0xFF {
+            out.push(0);
+        }
+    }
+    out
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_id3_pictures() {
+    let spec = Spec {
+        channel_count: 1,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for _ in 0..4_410 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    let front = jpeg(500, 500);
+    let artist = png(100, 150);
+    let logo = png(64, 16);
+
+    // An unsynchronised ID3v2.3 tag, with a description in UTF-16 with a byte order mark.
+    let mut apic = vec![1];
+    apic.extend_from_slice(b"image/jpeg\0");
+    apic.push(3);
+    apic.extend_from_slice(&[0xFF, 0xFE]);
+    for unit in "Cövér".encode_utf16() {
+        apic.extend_from_slice(&unit.to_le_bytes());
+    }
+    apic.extend_from_slice(&[0, 0]);
+    apic.extend_from_slice(&front);
+    let mut frames = b"APIC".to_vec();
+    frames.extend_from_slice(&(apic.len() as u32).to_be_bytes());
+    frames.extend_from_slice(&[0, 0]);
+    frames.extend_from_slice(&apic);
+    let v3 = id3_tag(3, 0x80, &unsynchronise(&frames));
+
+    // An ID3v2.4 tag with a text frame preceding a frame with a data length indicator.
+    let mut frames = b"TIT2".to_vec();
+    frames.extend_from_slice(&[0, 0, 0, 5, 0, 0, 3]);
+    frames.extend_from_slice(b"Sine");
+    let mut apic = vec![3];
+    apic.extend_from_slice(b"image/png\0");
+    apic.push(8);
+    apic.extend_from_slice("Artïst\0".as_bytes());
+    apic.extend_from_slice(&artist);
+    frames.extend_from_slice(b"APIC");
+    frames.extend_from_slice(&[0, 0, 0, apic.len() as u8 + 4, 0, 1]);
+    frames.extend_from_slice(&[0, 0, 0, apic.len() as u8]);
+    frames.extend_from_slice(&apic);
+    let v4 = id3_tag(4, 0, &frames);
+
+    // An ID3v2.2 tag, whose `PIC` frame gives the image format rather than a MIME type.
+    let mut pic = b"\0PNG\x13Logo\0".to_vec();
+    pic.extend_from_slice(&logo);
+    let mut frames = b"PIC".to_vec();
+    frames.extend_from_slice(&(pic.len() as u32).to_be_bytes()[1..]);
+    frames.extend_from_slice(&pic);
+    let v2 = id3_tag(2, 0, &frames);
+
+    let mut bytes = cursor.into_inner();
+    for &(id, ref tag) in &[(b"id3 ", v3), (b"ID3 ", v4), (b"id3 ", v2)] {
+        bytes.extend_from_slice(id);
+        bytes.extend_from_slice(&(tag.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(tag);
+        if tag.len() % 2 == 1 {
+            bytes.push(0);
+        }
+    }
+    let riff_len = bytes.len() as u32 - 8;
+    bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    let pictures = reader.pictures();
+    assert_eq!(pictures.len(), 3);
+    assert_eq!(pictures[0].picture_type(), PictureType::FrontCover);
+    assert_eq!(pictures[0].mime_type(), "image/jpeg");
+    assert_eq!(pictures[0].description(), "Cövér");
+    assert_eq!((pictures[0].width(), pictures[0].height()), (500, 500));
+    assert_eq!(pictures[0].data(), &front[..]);
+    assert_eq!(pictures[2].picture_type(), PictureType::BandLogo);
+    assert_eq!(pictures[2].mime_type(), "image/png");
+    assert_eq!(pictures[2].description(), "Logo");
+    assert_eq!((pictures[2].width(), pictures[2].height()), (64, 16));
+    assert_eq!(pictures[2].data(), &logo[..]);
+    // Pictures are read from the chunks in the order of the file.
+    assert_eq!(pictures[1].picture_type(), PictureType::Artist);
+    assert_eq!(pictures[1].description(), "Artïst");
+    assert_eq!((pictures[1].width(), pictures[1].height()), (100, 150));
+    assert_eq!(pictures[1].data(), &artist[..]);
+    assert_eq!(reader.samples::<i16>().count(), 4_410);
+}
+
+#[test]
+fn picture_type_codes() {
+    assert_eq!(PictureType::from_code(3), PictureType::FrontCover);
+    assert_eq!(PictureType::FrontCover.code(), 3);
+    assert_eq!(PictureType::from_code(20), PictureType::PublisherLogo);
+    assert_eq!(PictureType::from_code(21), PictureType::Other);
+}
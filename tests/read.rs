@@ -1,7 +1,10 @@
 #![cfg(all(feature = "flac", feature = "ogg_vorbis", feature = "wav"))]
+#![allow(clippy::redundant_static_lifetimes)]
 
 extern crate audrey;
 
+mod common;
+
 const FLAC: &'static str = "samples/sine_440hz_stereo.flac";
 const OGG_VORBIS: &'static str = "samples/sine_440hz_stereo.ogg";
 const WAV: &'static str = "samples/sine_440hz_stereo.wav";
@@ -68,3 +71,241 @@ fn open_and_read_samples() {
     // Ogg Vorbis is lossy.
     read_samples(OGG_VORBIS);
 }
+
+#[test]
+fn description_frame_count() {
+    for &path in &[FLAC, OGG_VORBIS, WAV, CAF_ALAC] {
+        let mut reader = audrey::open(path).unwrap();
+        let description = reader.description();
+        let num_frames = reader.frames::<[i16; 2]>().map(Result::unwrap).count();
+        assert_eq!(
+            description.frame_count(),
+            Some(num_frames as u64),
+            "{}",
+            path
+        );
+    }
+
+    for &path in &[FLAC, OGG_VORBIS, WAV] {
+        let description = audrey::open(path).unwrap().description();
+        let frame_count = description.frame_count().unwrap();
+        let secs = frame_count as f64 / f64::from(description.sample_rate());
+        let duration = description.duration().unwrap();
+        assert!((duration.as_secs_f64() - secs).abs() < 1e-9, "{}", path);
+    }
+}
+
+#[test]
+fn description_frame_count_ogg_vorbis_start() {
+    // Streams may begin at a nonzero granule position, such as those cut from a longer stream.
+    let stream = common::vorbis_stream_with(&[40, 20], &[], 44_100, 100_000);
+    let mut reader = audrey::Reader::new(std::io::Cursor::new(stream)).unwrap();
+    let frames = reader
+        .frames::<[f32; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames.len() as u64, common::VORBIS_FRAME_COUNT);
+    assert_eq!(
+        reader.description().frame_count(),
+        Some(common::VORBIS_FRAME_COUNT)
+    );
+
+    // Frames are numbered from the start of the stream when seeking.
+    for &frame in &[0, 1, 1_000, frames.len() - 1] {
+        reader.seek(frame as u64).unwrap();
+        let read = reader
+            .frames::<[f32; 2]>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(&read[..], &frames[frame..], "frame {}", frame);
+    }
+}
+
+#[test]
+fn description_sample_format() {
+    for &path in &[FLAC, WAV, CAF_ALAC] {
+        let description = audrey::open(path).unwrap().description();
+        assert_eq!(
+            description.sample_format(),
+            audrey::SampleFormat::I16,
+            "{}",
+            path
+        );
+        assert_eq!(description.bits_per_sample(), Some(16), "{}", path);
+    }
+
+    let description = audrey::open(OGG_VORBIS).unwrap().description();
+    assert_eq!(
+        description.sample_format(),
+        audrey::SampleFormat::LossyFloat
+    );
+    assert!(description.sample_format().is_lossy());
+    assert!(description.sample_format().is_float());
+    assert_eq!(description.bits_per_sample(), None);
+}
+
+#[test]
+fn frames_strict() {
+    let mut reader = audrey::open(WAV).unwrap();
+    let strict = reader
+        .frames_strict::<[i16; 2]>()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let mut reader = audrey::open(WAV).unwrap();
+    let frames = reader
+        .frames::<[i16; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(strict, frames);
+
+    let mut reader = audrey::open(WAV).unwrap();
+    match reader.frames_strict::<[i16; 1]>() {
+        Err(audrey::read::FormatError::ChannelMismatch { expected, found }) => {
+            assert_eq!(expected, 1);
+            assert_eq!(found, 2);
+        }
+        _ => panic!("expected a channel mismatch error"),
+    }
+}
+
+#[test]
+fn read_ogg_vorbis_float() {
+    // The sample is a full scale 440hz sine, which the lossy encoding reproduces to within ~-36dB.
+    const TOLERANCE: f32 = 0.025;
+    let mut reader = audrey::open(OGG_VORBIS).unwrap();
+    let samples = reader
+        .samples::<f32>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(samples.len(), 44_100 * 2);
+    for (i, frame) in samples.chunks(2).enumerate() {
+        let t = i as f32 / 44_100.0;
+        let expected = (2.0 * std::f32::consts::PI * 440.0 * t).sin();
+        for &sample in frame {
+            assert!((sample - expected).abs() < TOLERANCE, "frame {}", i);
+        }
+    }
+
+    // Decoding via `f32` rather than `i16` retains precision and any overshoot of full scale.
+    assert!(samples.iter().any(|&s| (s * 32_768.0).fract() != 0.0));
+    assert!(samples.iter().any(|&s| s > 1.0));
+}
+
+#[test]
+fn read_into() {
+    for &path in &[FLAC, OGG_VORBIS, WAV, CAF_ALAC] {
+        let mut reader = audrey::open(path).unwrap();
+        let expected = reader
+            .samples::<f32>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        // An odd buffer length ensures reads straddle blocks and packets.
+        let mut reader = audrey::open(path).unwrap();
+        let mut buf = [0.0f32; 1_001];
+        let mut samples = Vec::new();
+        loop {
+            let read = reader.read_into(&mut buf).unwrap();
+            samples.extend_from_slice(&buf[..read]);
+            if read < buf.len() {
+                break;
+            }
+        }
+        assert_eq!(samples, expected, "{}", path);
+        assert_eq!(reader.read_into(&mut buf).unwrap(), 0, "{}", path);
+    }
+}
+
+#[test]
+fn read_frames_into() {
+    for &path in &[FLAC, OGG_VORBIS, WAV, CAF_ALAC] {
+        let mut reader = audrey::open(path).unwrap();
+        let expected = reader
+            .frames::<[i16; 2]>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        let mut reader = audrey::open(path).unwrap();
+        reader.seek(100).unwrap();
+        let mut buf = vec![[0i16; 2]; expected.len()];
+        let read = reader.read_frames_into(&mut buf).unwrap();
+        assert_eq!(read, expected.len() - 100, "{}", path);
+        assert_eq!(&buf[..read], &expected[100..], "{}", path);
+
+        match reader.read_frames_into(&mut [[0i16; 1]; 16]) {
+            Err(audrey::read::FormatError::ChannelMismatch { expected, found }) => {
+                assert_eq!(expected, 1);
+                assert_eq!(found, 2);
+            }
+            _ => panic!("expected a channel mismatch error"),
+        }
+    }
+}
+
+#[test]
+fn read_planar() {
+    for &path in &[FLAC, OGG_VORBIS, WAV, CAF_ALAC] {
+        let mut reader = audrey::open(path).unwrap();
+        let frames = reader
+            .frames::<[i16; 2]>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        let mut reader = audrey::open(path).unwrap();
+        let planar = reader.read_planar::<i16>().unwrap();
+        assert_eq!(planar.len(), 2, "{}", path);
+        for channel in 0..2 {
+            let expected = frames.iter().map(|f| f[channel]).collect::<Vec<_>>();
+            assert_eq!(planar[channel], expected, "{}", path);
+        }
+        assert_eq!(reader.read_planar::<i16>().unwrap(), vec![vec![]; 2]);
+    }
+}
+
+#[test]
+fn read_planar_into() {
+    let mut reader = audrey::open(FLAC).unwrap();
+    let frames = reader
+        .frames::<[f32; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    // Reads are limited by the shortest slice.
+    let mut reader = audrey::open(FLAC).unwrap();
+    let mut left = vec![0.0f32; 5_000];
+    let mut right = vec![0.0f32; 7_000];
+    let mut read = 0;
+    loop {
+        let n = reader
+            .read_planar_into(&mut [&mut left[read..], &mut right[read..5_000]])
+            .unwrap();
+        read += n;
+        if n == 0 || read == 5_000 {
+            break;
+        }
+    }
+    assert_eq!(read, 5_000);
+    for (i, frame) in frames[..5_000].iter().enumerate() {
+        assert_eq!(left[i], frame[0]);
+        assert_eq!(right[i], frame[1]);
+    }
+
+    // Reading resumes where the previous read finished.
+    let mut left = vec![0.0f32; frames.len()];
+    let mut right = vec![0.0f32; frames.len()];
+    let n = reader
+        .read_planar_into(&mut [&mut left[..], &mut right[..]])
+        .unwrap();
+    assert_eq!(n, frames.len() - 5_000);
+    assert_eq!(left[0], frames[5_000][0]);
+    assert_eq!(right[n - 1], frames[frames.len() - 1][1]);
+
+    match reader.read_planar_into(&mut [&mut left[..]]) {
+        Err(audrey::read::FormatError::ChannelMismatch { expected, found }) => {
+            assert_eq!(expected, 1);
+            assert_eq!(found, 2);
+        }
+        _ => panic!("expected a channel mismatch error"),
+    }
+}
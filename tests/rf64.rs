@@ -0,0 +1,329 @@
+extern crate audrey;
+
+#[cfg(feature = "wav")]
+use audrey::{write::Spec, Format, SampleFormat};
+#[cfg(feature = "wav")]
+use std::fs::{self, File};
+#[cfg(feature = "wav")]
+use std::io::{self, Seek, SeekFrom, Write};
+#[cfg(feature = "wav")]
+use std::path::PathBuf;
+
+// Six gigabytes of 16-bit stereo audio, or 1.5 billion frames.
+#[cfg(feature = "wav")]
+const DATA_LEN: u64 = 6 << 30;
+#[cfg(feature = "wav")]
+const FRAME_COUNT: u64 = DATA_LEN / 4;
+
+// Writes a sparse 16-bit stereo file of the given RIFF identifier and `DATA_LEN` bytes of audio
+// that is silent besides its first and last frames, followed by the given chunks.
+//
+// The sizes of the RIFF and `data` chunks are given by the `ds64` chunk, along with those of any
+// chunks named by `table`.
+#[cfg(feature = "wav")]
+fn write_sparse(
+    name: &str,
+    riff_id: &[u8; 4],
+    table: &[&[u8; 4]],
+    chunks: &[(&[u8; 4], &[u8])],
+) -> PathBuf {
+    let mut ds64 = Vec::new();
+    // The RIFF size is written once the length of the file is known.
+    ds64.extend_from_slice(&0u64.to_le_bytes());
+    ds64.extend_from_slice(&DATA_LEN.to_le_bytes());
+    ds64.extend_from_slice(&FRAME_COUNT.to_le_bytes());
+    ds64.extend_from_slice(&(table.len() as u32).to_le_bytes());
+    for id in table {
+        let len = chunks
+            .iter()
+            .find(|(chunk_id, _)| chunk_id == id)
+            .unwrap()
+            .1
+            .len();
+        ds64.extend_from_slice(*id);
+        ds64.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+    let mut fmt = Vec::new();
+    for &field in &[1u16, 2] {
+        fmt.extend_from_slice(&field.to_le_bytes());
+    }
+    for &field in &[48_000u32, 192_000] {
+        fmt.extend_from_slice(&field.to_le_bytes());
+    }
+    for &field in &[4u16, 16] {
+        fmt.extend_from_slice(&field.to_le_bytes());
+    }
+
+    let path = std::env::temp_dir().join(name);
+    let mut file = File::create(&path).unwrap();
+    file.write_all(riff_id).unwrap();
+    file.write_all(&u32::MAX.to_le_bytes()).unwrap();
+    file.write_all(b"WAVE").unwrap();
+    for &(id, contents) in &[(b"ds64", &ds64[..]), (b"fmt ", &fmt[..])] {
+        file.write_all(id).unwrap();
+        file.write_all(&(contents.len() as u32).to_le_bytes())
+            .unwrap();
+        file.write_all(contents).unwrap();
+    }
+    file.write_all(b"data").unwrap();
+    file.write_all(&u32::MAX.to_le_bytes()).unwrap();
+    let data_position = file.stream_position().unwrap();
+    file.write_all(&[1, 0, 2, 0]).unwrap();
+    file.seek(SeekFrom::Start(data_position + DATA_LEN - 4))
+        .unwrap();
+    file.write_all(&[3, 0, 4, 0]).unwrap();
+    for &(id, contents) in chunks {
+        let len = if table.contains(&id) {
+            u32::MAX
+        } else {
+            contents.len() as u32
+        };
+        file.write_all(id).unwrap();
+        file.write_all(&len.to_le_bytes()).unwrap();
+        file.write_all(contents).unwrap();
+    }
+    let riff_len = file.stream_position().unwrap() - 8;
+    file.seek(SeekFrom::Start(20)).unwrap();
+    file.write_all(&riff_len.to_le_bytes()).unwrap();
+    path
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn read_rf64() {
+    let path = write_sparse("audrey_read.rf64", b"RF64", &[], &[]);
+    let mut reader = audrey::open(&path).unwrap();
+    let description = reader.description();
+    assert_eq!(description.format(), Format::Wav);
+    assert_eq!(description.channel_count(), 2);
+    assert_eq!(description.sample_format(), SampleFormat::I16);
+    assert_eq!(description.frame_count(), Some(FRAME_COUNT));
+
+    let mut buf = [0i16; 4];
+    assert_eq!(reader.read_into(&mut buf).unwrap(), 4);
+    assert_eq!(buf, [1, 2, 0, 0]);
+
+    // The last frame lies beyond the 4 GiB that 32-bit sizes and offsets can describe.
+    reader.seek(FRAME_COUNT - 2).unwrap();
+    assert_eq!(reader.read_into(&mut buf).unwrap(), 4);
+    assert_eq!(buf, [0, 0, 3, 4]);
+    reader.seek(FRAME_COUNT - 1).unwrap();
+    let samples: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+    assert_eq!(samples, [3, 4]);
+    reader.seek(FRAME_COUNT + 1).unwrap();
+    assert_eq!(reader.samples::<i16>().count(), 0);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn read_bw64_chunks_following_data() {
+    // The size of the `iXML` chunk is given by the table of the `ds64` chunk.
+    let mut list = b"INFO".to_vec();
+    list.extend_from_slice(b"INAM");
+    list.extend_from_slice(&6u32.to_le_bytes());
+    list.extend_from_slice(b"Large\0");
+    let ixml = b"<BWFXML><SCENE>1</SCENE></BWFXML>\0\0";
+    let path = write_sparse(
+        "audrey_read.bw64",
+        b"BW64",
+        &[b"iXML"],
+        &[(b"LIST", &list), (b"iXML", ixml)],
+    );
+
+    let mut reader = audrey::open(&path).unwrap();
+    assert_eq!(reader.description().frame_count(), Some(FRAME_COUNT));
+    assert_eq!(reader.metadata().title(), Some("Large"));
+    assert_eq!(
+        reader.ixml().as_deref(),
+        Some("<BWFXML><SCENE>1</SCENE></BWFXML>")
+    );
+    reader.seek(FRAME_COUNT - 1).unwrap();
+    let frames: Vec<[i16; 2]> = reader.frames().map(Result::unwrap).collect();
+    assert_eq!(frames, [[3, 4]]);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn read_truncated_data() {
+    // The header describes 100 samples of which only 3 remain.
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = io::Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for i in 0..100 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    let mut bytes = cursor.into_inner();
+    bytes.truncate(bytes.len() - 97 * 2);
+
+    // Reading ends with the error rather than repeatedly retrying the failed read.
+    let mut reader = audrey::Reader::new(io::Cursor::new(bytes.clone())).unwrap();
+    let samples = reader.samples::<i16>().collect::<Vec<_>>();
+    assert_eq!(samples.len(), 4);
+    assert_eq!(samples[2].as_ref().unwrap(), &2);
+    assert!(samples[3].is_err());
+
+    let mut reader = audrey::Reader::new(io::Cursor::new(bytes)).unwrap();
+    let mut buf = [0i16; 100];
+    assert!(reader.read_into(&mut buf).is_err());
+    assert_eq!(reader.read_into(&mut buf).unwrap(), 0);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn read_ds64_overflowing_data_length() {
+    let mut ds64 = Vec::new();
+    for &size in &[0, u64::MAX - 1, 0] {
+        ds64.extend_from_slice(&size.to_le_bytes());
+    }
+    ds64.extend_from_slice(&0u32.to_le_bytes());
+    let mut bytes = b"RF64".to_vec();
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"ds64");
+    bytes.extend_from_slice(&(ds64.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&ds64);
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+
+    match audrey::Reader::new(io::Cursor::new(bytes)) {
+        Err(audrey::read::ReadError::Reader(audrey::read::FormatError::Wav(
+            audrey::read::WavError::Malformed(_),
+        ))) => (),
+        _ => panic!("expected a malformed WAVE error"),
+    }
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn write_reserves_ds64() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 48_000,
+        sample_format: SampleFormat::I16,
+    };
+    let mut cursor = io::Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for _ in 0..48_000 {
+            writer.write_frame([1i16, 2]).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    let bytes = cursor.into_inner();
+
+    // Files of less than 4 GiB remain RIFF files, with a `JUNK` chunk in place of the `ds64` chunk.
+    assert_eq!(&bytes[..4], b"RIFF");
+    assert_eq!(&bytes[4..8], &(bytes.len() as u32 - 8).to_le_bytes());
+    assert_eq!(&bytes[12..20], b"JUNK\x1c\0\0\0");
+    assert!(bytes[20..48].iter().all(|&byte| byte == 0));
+    let mut reader = audrey::Reader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.description().frame_count(), Some(48_000));
+    assert_eq!(reader.frames::<[i16; 2]>().last().unwrap().unwrap(), [1, 2]);
+}
+
+// Retains the first kilobyte written, discarding the rest while tracking the length of the
+// stream.
+#[cfg(feature = "wav")]
+struct HeaderSink {
+    header: Vec<u8>,
+    position: u64,
+    len: u64,
+}
+
+#[cfg(feature = "wav")]
+impl Write for HeaderSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.position < self.header.len() as u64 {
+            let header = &mut self.header[self.position as usize..];
+            let len = header.len().min(buf.len());
+            header[..len].copy_from_slice(&buf[..len]);
+        }
+        self.position += buf.len() as u64;
+        self.len = self.len.max(self.position);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "wav")]
+impl Seek for HeaderSink {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(position) => position,
+            SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+            SeekFrom::End(offset) => (self.len as i64 + offset) as u64,
+        };
+        Ok(self.position)
+    }
+}
+
+// Writing more than 4 GiB of audio takes minutes without optimisations, so this is run via
+// `cargo test --release -- --ignored`.
+#[cfg(feature = "wav")]
+#[test]
+#[ignore]
+fn write_promotes_to_rf64() {
+    let u32_at = |bytes: &[u8], position: usize| {
+        u32::from_le_bytes([
+            bytes[position],
+            bytes[position + 1],
+            bytes[position + 2],
+            bytes[position + 3],
+        ])
+    };
+    let u64_at = |bytes: &[u8], position: usize| {
+        u64::from(u32_at(bytes, position)) | u64::from(u32_at(bytes, position + 4)) << 32
+    };
+
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 48_000,
+        sample_format: SampleFormat::F32,
+    };
+    let mut sink = HeaderSink {
+        header: vec![0; 1024],
+        position: 0,
+        len: 0,
+    };
+    // Just over 4 GiB of audio.
+    let frame_count = (1u64 << 29) + 1;
+    {
+        let mut writer = audrey::Writer::new(&mut sink, Format::Wav, spec).unwrap();
+        writer.write_frame([0.0f32; 2]).unwrap();
+        writer.flush().unwrap();
+        for _ in 1..frame_count {
+            writer.write_frame([0.5f32, -0.5]).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    let header = &sink.header;
+    let data_len = frame_count * 8;
+    assert_eq!(&header[..4], b"RF64");
+    assert_eq!(u32_at(header, 4), u32::MAX);
+    assert_eq!(&header[8..16], b"WAVEds64");
+    assert_eq!(u32_at(header, 16), 28);
+    assert_eq!(u64_at(header, 20), sink.len - 8);
+    assert_eq!(u64_at(header, 28), data_len);
+    assert_eq!(u64_at(header, 36), frame_count);
+    assert_eq!(u32_at(header, 44), 0);
+    assert_eq!(&header[48..52], b"fmt ");
+    let data_position = sink.len - data_len;
+    assert_eq!(&header[data_position as usize - 8..][..4], b"data");
+    assert_eq!(u32_at(header, data_position as usize - 4), u32::MAX);
+}
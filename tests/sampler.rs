@@ -0,0 +1,228 @@
+extern crate audrey;
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use audrey::sampler::{LoopType, SampleLoop};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use audrey::{write::Spec, Format, Metadata, SampleFormat, SamplerInfo};
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+use std::io::Cursor;
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn sample_loop(start: u64, end: u64, loop_type: LoopType, play_count: u32) -> SampleLoop {
+    SampleLoop {
+        start,
+        end,
+        loop_type,
+        play_count,
+    }
+}
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn sampler_info() -> SamplerInfo {
+    SamplerInfo {
+        root_note: 62,
+        fine_tune: 25.0,
+        loops: vec![
+            sample_loop(1_000, 20_000, LoopType::Forward, 0),
+            sample_loop(30_000, 40_000, LoopType::Alternating, 3),
+            sample_loop(40_000, 44_100, LoopType::Other(40), 0),
+        ],
+    }
+}
+
+// Encodes a second of stereo silence.
+#[cfg(feature = "wav")]
+fn encode(format: Format) -> Vec<u8> {
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, format, spec()).unwrap();
+        for _ in 0..88_200 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.into_inner()
+}
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn spec() -> Spec {
+    Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    }
+}
+
+// Encodes a second of stereo silence with the given tags and sampler info.
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn encode_with_sampler_info(
+    format: Format,
+    metadata: &Metadata,
+    sampler_info: &SamplerInfo,
+) -> Vec<u8> {
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, format, spec()).unwrap();
+        writer.set_sampler_info(sampler_info).unwrap();
+        writer.set_metadata(metadata).unwrap();
+        for _ in 0..88_200 {
+            writer.write_sample(0i16).unwrap();
+        }
+        match writer.set_sampler_info(sampler_info) {
+            Err(audrey::write::WriteError::AudioStarted) => (),
+            result => panic!("unexpected result: {:?}", result.map_err(|e| e.to_string())),
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.into_inner()
+}
+
+#[cfg(any(feature = "caf_alac", feature = "flac", feature = "wav"))]
+fn read_sampler_info(bytes: Vec<u8>) -> Option<SamplerInfo> {
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.samples::<i16>().count(), 88_200);
+    reader.sampler_info()
+}
+
+#[test]
+fn samples_sampler_info() {
+    let paths: &[&str] = &[
+        #[cfg(feature = "flac")]
+        "samples/sine_440hz_stereo.flac",
+        #[cfg(feature = "ogg_vorbis")]
+        "samples/sine_440hz_stereo.ogg",
+        #[cfg(feature = "wav")]
+        "samples/sine_440hz_stereo.wav",
+        #[cfg(feature = "caf_alac")]
+        "samples/sine_440hz_stereo.caf",
+    ];
+    for path in paths {
+        assert_eq!(audrey::open(path).unwrap().sampler_info(), None, "{}", path);
+    }
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn wav_smpl_chunk() {
+    let bytes = encode_with_sampler_info(Format::Wav, &Metadata::new(), &sampler_info());
+    assert_eq!(read_sampler_info(bytes), Some(sampler_info()));
+
+    // A unity note of A4, 50 cents sharp, with a single alternating loop ending at frame 99
+    // inclusive.
+    let mut smpl = Vec::new();
+    for &field in &[0u32, 0, 22_676, 69, 0x8000_0000, 0, 0, 1, 0] {
+        smpl.extend_from_slice(&field.to_le_bytes());
+    }
+    for &field in &[0u32, 1, 10, 99, 0, 2] {
+        smpl.extend_from_slice(&field.to_le_bytes());
+    }
+    let mut wav = encode(Format::Wav);
+    wav.extend_from_slice(b"smpl");
+    wav.extend_from_slice(&(smpl.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&smpl);
+    let riff_len = wav.len() as u32 - 8;
+    wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    assert_eq!(
+        read_sampler_info(wav),
+        Some(SamplerInfo {
+            root_note: 69,
+            fine_tune: 50.0,
+            loops: vec![sample_loop(10, 100, LoopType::Alternating, 2)],
+        })
+    );
+}
+
+#[cfg(feature = "caf_alac")]
+#[test]
+fn caf_instrument_chunks() {
+    let bytes = encode_with_sampler_info(Format::CafAlac, &Metadata::new(), &sampler_info());
+    // Play counts and manufacturer-specific loop types cannot be represented.
+    let mut expected = sampler_info();
+    expected.loops[1].play_count = 0;
+    expected.loops[2].loop_type = LoopType::Forward;
+    assert_eq!(read_sampler_info(bytes), Some(expected));
+
+    // Sampler info without loops is written as an instrument alone.
+    let pitch_only = SamplerInfo {
+        root_note: 48,
+        ..SamplerInfo::default()
+    };
+    let bytes = encode_with_sampler_info(Format::CafAlac, &Metadata::new(), &pitch_only);
+    assert_eq!(read_sampler_info(bytes), Some(pitch_only));
+}
+
+#[cfg(feature = "flac")]
+#[test]
+fn flac_loop_comments() {
+    // Only the first loop is written, replacing any loop given by the tags.
+    let metadata: Metadata = vec![("TITLE", "Loop"), ("LOOPSTART", "5")]
+        .into_iter()
+        .collect();
+    let bytes = encode_with_sampler_info(Format::Flac, &metadata, &sampler_info());
+    let reader = audrey::Reader::new(Cursor::new(bytes.clone())).unwrap();
+    let metadata = reader.metadata();
+    assert_eq!(metadata.title(), Some("Loop"));
+    assert_eq!(metadata.get_all("LOOPSTART"), vec!["1000"]);
+    assert_eq!(metadata.get_all("LOOPLENGTH"), vec!["19000"]);
+    assert_eq!(
+        read_sampler_info(bytes),
+        Some(SamplerInfo {
+            loops: vec![sample_loop(1_000, 20_000, LoopType::Forward, 0)],
+            ..SamplerInfo::default()
+        })
+    );
+
+    // Loops may be given as tags alone, though both comments are required.
+    let metadata: Metadata = vec![("LOOPSTART", "44100"), ("LOOPLENGTH", "22050")]
+        .into_iter()
+        .collect();
+    for (tags, expected) in [
+        (
+            metadata,
+            Some(vec![sample_loop(44_100, 66_150, LoopType::Forward, 0)]),
+        ),
+        (vec![("LOOPSTART", "44100")].into_iter().collect(), None),
+    ] {
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = audrey::Writer::new(&mut cursor, Format::Flac, spec()).unwrap();
+            writer.set_metadata(&tags).unwrap();
+            writer.finalize().unwrap();
+        }
+        let reader = audrey::Reader::new(Cursor::new(cursor.into_inner())).unwrap();
+        assert_eq!(reader.sampler_info().map(|info| info.loops), expected);
+    }
+}
+
+#[cfg(all(feature = "caf_alac", feature = "wav"))]
+#[test]
+fn convert_preserves_sampler_info() {
+    let dir = std::env::temp_dir();
+    let wav = dir.join("audrey_convert_sampler.wav");
+    let caf = dir.join("audrey_convert_sampler.caf");
+    let expected = SamplerInfo {
+        root_note: 57,
+        fine_tune: 0.0,
+        loops: vec![sample_loop(100, 200, LoopType::Backward, 0)],
+    };
+    std::fs::write(
+        &wav,
+        encode_with_sampler_info(Format::Wav, &Metadata::new(), &expected),
+    )
+    .unwrap();
+    audrey::convert(&wav, &caf).unwrap();
+    assert_eq!(audrey::open(&caf).unwrap().sampler_info(), Some(expected));
+    for path in &[wav, caf] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn loop_type_codes() {
+    use audrey::sampler::LoopType;
+    for code in 0..64 {
+        assert_eq!(LoopType::from_code(code).code(), code);
+    }
+    assert_eq!(LoopType::from_code(1), LoopType::Alternating);
+}
@@ -0,0 +1,108 @@
+#![cfg(all(
+    feature = "flac",
+    feature = "ogg_vorbis",
+    feature = "wav",
+    feature = "caf_alac"
+))]
+
+extern crate audrey;
+
+use audrey::write::Spec;
+use audrey::{Format, SampleFormat};
+use std::io::Cursor;
+
+mod common;
+
+const FLAC: &str = "samples/sine_440hz_stereo.flac";
+const OGG_VORBIS: &str = "samples/sine_440hz_stereo.ogg";
+const WAV: &str = "samples/sine_440hz_stereo.wav";
+const CAF_ALAC: &str = "samples/sine_440hz_stereo.caf";
+
+fn open(path: &str) -> audrey::Reader<Cursor<Vec<u8>>> {
+    audrey::Reader::new(Cursor::new(std::fs::read(path).unwrap())).unwrap()
+}
+
+// Encodes a few seconds of the shared test signal, long enough for FLAC to bisect over frame
+// headers.
+fn encode(format: Format) -> audrey::Reader<Cursor<Vec<u8>>> {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let signal = common::test_signal(2, 44_100 * 6, 16);
+    let bytes = common::encode(16, &signal, |cursor| {
+        audrey::Writer::new(cursor, format, spec)
+    });
+    audrey::Reader::new(Cursor::new(bytes)).unwrap()
+}
+
+// Checks that seeking to each frame yields the same samples as reading from the start.
+fn check_seek(mut reader: audrey::Reader<Cursor<Vec<u8>>>) {
+    let frames = reader
+        .frames::<[i16; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let len = frames.len();
+    assert_eq!(reader.description().frame_count(), Some(len as u64));
+    let targets = [
+        len / 2,
+        0,
+        1,
+        575,
+        1_000,
+        4_095,
+        4_096,
+        4_097,
+        len / 3,
+        len - 1,
+        len / 4,
+        len - 4_000,
+        len,
+        len + 1_000,
+    ];
+    for &target in targets.iter() {
+        reader.seek(target as u64).unwrap();
+        let read = reader
+            .frames::<[i16; 2]>()
+            .take(2_000)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let expected = &frames[target.min(len)..(target + 2_000).min(len)];
+        assert_eq!(
+            &read[..],
+            expected,
+            "{:?} frame {}",
+            reader.format(),
+            target
+        );
+    }
+
+    // Reading may resume after a seek without losing any frames.
+    reader.seek(len as u64 / 2).unwrap();
+    reader.frames::<[i16; 2]>().take(10).count();
+    let remaining = reader.frames::<[i16; 2]>().map(Result::unwrap).count();
+    assert_eq!(remaining, len - len / 2 - 10);
+}
+
+#[test]
+fn seek_wav() {
+    check_seek(open(WAV));
+}
+
+#[test]
+fn seek_flac() {
+    check_seek(open(FLAC));
+    check_seek(encode(Format::Flac));
+}
+
+#[test]
+fn seek_ogg_vorbis() {
+    check_seek(open(OGG_VORBIS));
+}
+
+#[test]
+fn seek_caf_alac() {
+    check_seek(open(CAF_ALAC));
+    check_seek(encode(Format::CafAlac));
+}
@@ -0,0 +1,507 @@
+#![cfg(feature = "wav")]
+
+extern crate audrey;
+
+use audrey::write::Spec;
+use audrey::{Format, Metadata, SampleFormat, SamplerInfo};
+use std::io::Cursor;
+
+mod common;
+
+const WAV: &str = "samples/sine_440hz_stereo.wav";
+
+#[test]
+fn write_and_read_samples() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let frames = audrey::open(WAV)
+        .unwrap()
+        .frames::<[i16; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        assert_eq!(writer.format(), Format::Wav);
+        assert_eq!(writer.spec(), spec);
+        for &frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    cursor.set_position(0);
+    let mut reader = audrey::Reader::new(cursor).unwrap();
+    let description = reader.description();
+    assert_eq!(description.format(), Format::Wav);
+    assert_eq!(description.channel_count(), 2);
+    assert_eq!(description.sample_rate(), 44_100);
+    let read = reader
+        .frames::<[i16; 2]>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(frames, read);
+}
+
+#[test]
+fn create_unsupported_extension() {
+    let spec = Spec {
+        channel_count: 1,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let path = std::env::temp_dir().join("audrey_create_unsupported.xyz");
+    match audrey::create(&path, spec) {
+        Err(audrey::write::WriteError::UnsupportedFormat) => (),
+        _ => panic!("expected an unsupported format error"),
+    }
+}
+
+#[test]
+fn create_unsupported_spec() {
+    let spec = Spec {
+        channel_count: 0,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    // An existing file is left untouched.
+    let path = std::env::temp_dir().join("audrey_create_unsupported.wav");
+    std::fs::write(&path, b"existing").unwrap();
+    match audrey::create(&path, spec) {
+        Err(audrey::write::WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+        _ => panic!("expected an unsupported spec error"),
+    }
+    assert_eq!(std::fs::read(&path).unwrap(), b"existing");
+    std::fs::remove_file(&path).unwrap();
+}
+
+// Writes a short stereo ramp in the given sample format and returns the encoded bytes.
+fn write_ramp(sample_format: SampleFormat, finalize: bool) -> Vec<u8> {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 48_000,
+        sample_format,
+    };
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for i in 0..1_000 {
+            let sample = (i as f32 / 1_000.0) * 2.0 - 1.0;
+            writer.write_frame([sample, -sample]).unwrap();
+        }
+        if finalize {
+            writer.finalize().unwrap();
+        }
+    }
+    cursor.into_inner()
+}
+
+#[test]
+fn write_wav_sample_formats() {
+    let formats = [
+        (SampleFormat::I8, 1.0 / 128.0),
+        (SampleFormat::I16, 1.0 / 32_768.0),
+        (SampleFormat::I24, 1.0 / 8_388_608.0),
+        (SampleFormat::I32, 1.0 / 8_388_608.0),
+        (SampleFormat::F32, 0.0),
+    ];
+    for &(sample_format, tolerance) in formats.iter() {
+        let bytes = write_ramp(sample_format, true);
+        let mut reader = audrey::Reader::new(std::io::Cursor::new(bytes)).unwrap();
+        let description = reader.description();
+        assert_eq!(description.sample_format(), sample_format);
+        assert_eq!(
+            description.bits_per_sample(),
+            Some(sample_format.bits_per_sample())
+        );
+        match reader {
+            audrey::Reader::Wav(ref wav) => {
+                let spec = wav.spec();
+                assert_eq!(
+                    u32::from(spec.bits_per_sample),
+                    sample_format.bits_per_sample()
+                );
+                assert_eq!(spec.channels, 2);
+                assert_eq!(spec.sample_rate, 48_000);
+            }
+            #[allow(unreachable_patterns)]
+            _ => panic!("Incorrect audio format"),
+        }
+        let frames = reader
+            .frames::<[f32; 2]>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(frames.len(), 1_000);
+        for (i, frame) in frames.iter().enumerate() {
+            let sample = (i as f32 / 1_000.0) * 2.0 - 1.0;
+            assert!(
+                (frame[0] - sample).abs() <= tolerance,
+                "{:?}",
+                sample_format
+            );
+            assert!(
+                (frame[1] + sample).abs() <= tolerance,
+                "{:?}",
+                sample_format
+            );
+        }
+    }
+}
+
+#[test]
+fn write_wav_finalizes_on_drop() {
+    let finalized = write_ramp(SampleFormat::I16, true);
+    let dropped = write_ramp(SampleFormat::I16, false);
+    assert_eq!(finalized, dropped);
+    let mut reader = audrey::Reader::new(std::io::Cursor::new(dropped)).unwrap();
+    assert_eq!(reader.samples::<i16>().count(), 2_000);
+}
+
+#[test]
+fn write_wav_unsupported_spec() {
+    for &(channel_count, sample_rate) in &[(0, 44_100), (2, 0)] {
+        let spec = Spec {
+            channel_count,
+            sample_rate,
+            sample_format: SampleFormat::I16,
+        };
+        let cursor = std::io::Cursor::new(Vec::new());
+        match audrey::Writer::new(cursor, Format::Wav, spec) {
+            Err(audrey::write::WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+
+    // Only 32-bit floating point samples may be written.
+    for &sample_format in &[SampleFormat::F64, SampleFormat::LossyFloat] {
+        let spec = Spec {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_format,
+        };
+        let cursor = std::io::Cursor::new(Vec::new());
+        match audrey::Writer::new(cursor, Format::Wav, spec) {
+            Err(audrey::write::WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+}
+
+#[test]
+fn write_wav_largest_frame() {
+    // 32,767 channels of 16-bit samples is the widest frame that the 16-bit block align can hold.
+    let spec = Spec {
+        channel_count: 32_767,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    assert!(audrey::Writer::<std::io::Cursor<Vec<u8>>>::supports(
+        Format::Wav,
+        &spec
+    ));
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for i in 0..2 * 32_767 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.set_position(0);
+    let mut reader = audrey::Reader::new(cursor).unwrap();
+    let description = reader.description();
+    assert_eq!(description.channel_count(), 32_767);
+    assert_eq!(description.frame_count(), Some(2));
+    let samples = reader
+        .samples::<i16>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        samples,
+        (0..2 * 32_767).map(|i| i as i16).collect::<Vec<_>>()
+    );
+
+    // One more channel overflows the block align, while a wide enough frame at a high enough
+    // sample rate overflows the 32-bit bytes per second.
+    let unsupported = [
+        (32_768, 44_100, SampleFormat::I16),
+        (65_535, 96_000, SampleFormat::I8),
+    ];
+    for &(channel_count, sample_rate, sample_format) in &unsupported {
+        let spec = Spec {
+            channel_count,
+            sample_rate,
+            sample_format,
+        };
+        let cursor = std::io::Cursor::new(Vec::new());
+        match audrey::Writer::new(cursor, Format::Wav, spec) {
+            Err(audrey::write::WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+}
+
+fn u16_at(bytes: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([bytes[position], bytes[position + 1]])
+}
+
+fn u32_at(bytes: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[position],
+        bytes[position + 1],
+        bytes[position + 2],
+        bytes[position + 3],
+    ])
+}
+
+// The identifier, position and length of the contents of each chunk following the RIFF header.
+fn chunks(bytes: &[u8]) -> Vec<([u8; 4], usize, usize)> {
+    let mut chunks = Vec::new();
+    let mut position = 12;
+    while position + 8 <= bytes.len() {
+        let mut id = [0; 4];
+        id.copy_from_slice(&bytes[position..position + 4]);
+        let len = u32_at(bytes, position + 4) as usize;
+        chunks.push((id, position + 8, len));
+        position += 8 + len + len % 2;
+    }
+    chunks
+}
+
+// Encodes the given integer samples, returning the encoded bytes.
+fn write_wav(spec: Spec, samples: &[i32]) -> Vec<u8> {
+    let bits = spec.sample_format.bits_per_sample();
+    common::encode(bits, samples, |cursor| {
+        audrey::Writer::new(cursor, Format::Wav, spec)
+    })
+}
+
+#[test]
+fn write_wav_headers() {
+    // The expected format tag and channel mask of each spec, with `WAVE_FORMAT_EXTENSIBLE` used
+    // for audio of more than 2 channels or 16 bits per sample, including all floating point audio.
+    let specs = [
+        (1, SampleFormat::I8, 1, None),
+        (2, SampleFormat::I16, 1, None),
+        (2, SampleFormat::F32, 0xFFFE, Some(0x3)),
+        (1, SampleFormat::I24, 0xFFFE, Some(0x4)),
+        (2, SampleFormat::I32, 0xFFFE, Some(0x3)),
+        (6, SampleFormat::I16, 0xFFFE, Some(0x3F)),
+        (6, SampleFormat::F32, 0xFFFE, Some(0x3F)),
+    ];
+    for &(channel_count, sample_format, format_tag, channel_mask) in &specs {
+        let spec = Spec {
+            channel_count,
+            sample_rate: 48_000,
+            sample_format,
+        };
+        let bytes = write_wav(spec, &vec![0; channel_count as usize * 3]);
+        let bits = sample_format.bits_per_sample();
+        let block_align = channel_count * bits / 8;
+
+        assert_eq!(&bytes[..4], b"RIFF", "{:?}", spec);
+        assert_eq!(u32_at(&bytes, 4) as usize, bytes.len() - 8, "{:?}", spec);
+        assert_eq!(&bytes[8..12], b"WAVE", "{:?}", spec);
+        let chunks = chunks(&bytes);
+        let ids = chunks.iter().map(|&(id, _, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids, [*b"JUNK", *b"fmt ", *b"data"], "{:?}", spec);
+
+        let (_, fmt, fmt_len) = chunks[1];
+        let extensible = channel_mask.is_some();
+        assert_eq!(fmt_len, if extensible { 40 } else { 16 }, "{:?}", spec);
+        assert_eq!(u16_at(&bytes, fmt), format_tag, "{:?}", spec);
+        assert_eq!(u32::from(u16_at(&bytes, fmt + 2)), channel_count);
+        assert_eq!(u32_at(&bytes, fmt + 4), 48_000);
+        assert_eq!(u32_at(&bytes, fmt + 8), 48_000 * block_align);
+        assert_eq!(u32::from(u16_at(&bytes, fmt + 12)), block_align);
+        assert_eq!(u32::from(u16_at(&bytes, fmt + 14)), bits);
+        if let Some(channel_mask) = channel_mask {
+            assert_eq!(u16_at(&bytes, fmt + 16), 22);
+            assert_eq!(u32::from(u16_at(&bytes, fmt + 18)), bits);
+            assert_eq!(u32_at(&bytes, fmt + 20), channel_mask, "{:?}", spec);
+            // The `SubFormat` GUID begins with the format tag that it stands in for.
+            let subformat = if sample_format == SampleFormat::F32 {
+                3
+            } else {
+                1
+            };
+            assert_eq!(u16_at(&bytes, fmt + 24), subformat, "{:?}", spec);
+        }
+
+        let (_, data, data_len) = chunks[2];
+        assert_eq!(data_len as u32, 3 * block_align, "{:?}", spec);
+        assert_eq!(data + data_len + data_len % 2, bytes.len(), "{:?}", spec);
+    }
+}
+
+#[test]
+fn write_wav_sample_encoding() {
+    // The extremes and midpoint of each integer format, along with their encoding, with 8-bit
+    // samples alone stored as unsigned.
+    let formats: &[(SampleFormat, &[i32], &[u8])] = &[
+        (SampleFormat::I8, &[-128, 0, 127], &[0x00, 0x80, 0xFF]),
+        (
+            SampleFormat::I16,
+            &[-32_768, 0, 32_767],
+            &[0x00, 0x80, 0x00, 0x00, 0xFF, 0x7F],
+        ),
+        (
+            SampleFormat::I24,
+            &[-8_388_608, 1, 8_388_607],
+            &[0x00, 0x00, 0x80, 0x01, 0x00, 0x00, 0xFF, 0xFF, 0x7F],
+        ),
+        (
+            SampleFormat::I32,
+            &[i32::MIN, -2, i32::MAX],
+            &[
+                0x00, 0x00, 0x00, 0x80, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F,
+            ],
+        ),
+    ];
+    for &(sample_format, samples, encoded) in formats {
+        let spec = Spec {
+            channel_count: 1,
+            sample_rate: 44_100,
+            sample_format,
+        };
+        let bytes = write_wav(spec, samples);
+        let (_, data, data_len) = chunks(&bytes)[2];
+        assert_eq!(
+            &bytes[data..data + data_len],
+            encoded,
+            "{:?}",
+            sample_format
+        );
+
+        // Each sample reads back unchanged when converted to its own width.
+        let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+        let read = match sample_format {
+            SampleFormat::I8 => reader
+                .samples::<i8>()
+                .map(|s| i32::from(s.unwrap()))
+                .collect::<Vec<_>>(),
+            SampleFormat::I16 => reader
+                .samples::<i16>()
+                .map(|s| i32::from(s.unwrap()))
+                .collect(),
+            SampleFormat::I24 => reader.samples::<i32>().map(|s| s.unwrap() >> 8).collect(),
+            _ => reader.samples::<i32>().map(Result::unwrap).collect(),
+        };
+        assert_eq!(read, samples, "{:?}", sample_format);
+    }
+
+    // Floating point samples are stored as is, including those beyond full scale.
+    let spec = Spec {
+        channel_count: 1,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::F32,
+    };
+    let samples = [-1.5f32, -1.0, 0.0, 0.25, 1.0];
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for &sample in &samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    cursor.set_position(0);
+    let mut reader = audrey::Reader::new(cursor).unwrap();
+    let read = reader
+        .samples::<f32>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(read, samples);
+}
+
+#[test]
+fn write_wav_partial_frame() {
+    let spec = Spec {
+        channel_count: 3,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I8,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        for sample in 0..5 {
+            writer.write_sample(sample as i8).unwrap();
+        }
+        // Samples of the incomplete frame remain buffered on flushing.
+        writer.flush().unwrap();
+        writer.write_sample(5i8).unwrap();
+        writer.write_sample(6i8).unwrap();
+        writer.finalize().unwrap();
+    }
+    let bytes = cursor.into_inner();
+
+    // The trailing sample is discarded, while the two frames of 3 bytes are padded to an even
+    // length.
+    let (_, data, data_len) = chunks(&bytes)[2];
+    assert_eq!(data_len, 6);
+    assert_eq!(bytes.len(), data + 6);
+    assert_eq!(u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    let mut reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.description().frame_count(), Some(2));
+    let samples = reader
+        .samples::<i8>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(samples, [0, 1, 2, 3, 4, 5]);
+
+    // An odd length `data` chunk is padded to an even length.
+    let spec = Spec {
+        channel_count: 1,
+        ..spec
+    };
+    let bytes = write_wav(spec, &[1, 2, 3]);
+    let (_, data, data_len) = chunks(&bytes)[2];
+    assert_eq!(data_len, 3);
+    assert_eq!(bytes.len(), data + 4);
+    assert_eq!(u32_at(&bytes, 4) as usize, bytes.len() - 8);
+}
+
+#[test]
+fn write_wav_chunk_order() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let mut metadata = Metadata::new();
+    metadata.set("TITLE", "Order");
+    let sampler_info = SamplerInfo {
+        root_note: 60,
+        fine_tune: 0.0,
+        loops: Vec::new(),
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Wav, spec).unwrap();
+        writer.set_ixml("<BWFXML/>").unwrap();
+        writer.set_sampler_info(&sampler_info).unwrap();
+        writer.set_metadata(&metadata).unwrap();
+        writer.write_frame([1i16, 2]).unwrap();
+        writer.finalize().unwrap();
+    }
+    let bytes = cursor.into_inner();
+
+    // The chunks preceding the audio are written in a fixed order regardless of the order in
+    // which they were set.
+    let ids = chunks(&bytes)
+        .iter()
+        .map(|&(id, _, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        [*b"JUNK", *b"fmt ", *b"LIST", *b"smpl", *b"iXML", *b"data"]
+    );
+    let reader = audrey::Reader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.metadata().title(), Some("Order"));
+    assert_eq!(reader.sampler_info(), Some(sampler_info));
+    assert_eq!(reader.ixml().as_deref(), Some("<BWFXML/>"));
+}
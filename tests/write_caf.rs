@@ -0,0 +1,149 @@
+#![cfg(feature = "caf_alac")]
+
+extern crate audrey;
+
+use audrey::write::{Spec, WriteError};
+use audrey::{Format, SampleFormat};
+use std::io::Cursor;
+
+mod common;
+
+const FRAMES_PER_PACKET: usize = 4096;
+
+// The number of packets holding the given number of frames, the last of which may be partial.
+fn packet_count(frames: usize) -> usize {
+    (0..frames).step_by(FRAMES_PER_PACKET).count()
+}
+
+// Reads the sample rate, channel count, number of packets and left-justified samples of the ALAC
+// stream within the given reader.
+fn read_alac<R>(reader: R) -> (f64, u32, usize, Vec<i32>)
+where
+    R: std::io::Read + std::io::Seek,
+{
+    match audrey::Reader::new(reader).unwrap() {
+        audrey::Reader::CafAlac(mut alac) => {
+            let desc = alac.audio_desc.clone();
+            let mut packets = 0;
+            let mut samples = vec![];
+            while let Some(packet) = alac.read_packet().unwrap() {
+                packets += 1;
+                samples.extend(packet);
+            }
+            (desc.sample_rate, desc.channels_per_frame, packets, samples)
+        }
+        #[allow(unreachable_patterns)]
+        _ => panic!("Incorrect audio format"),
+    }
+}
+
+// Encodes the given interleaved samples, returning the encoded bytes.
+fn write_alac(spec: Spec, samples: &[i32], finalize: bool) -> Vec<u8> {
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::CafAlac, spec).unwrap();
+        assert_eq!(writer.format(), Format::CafAlac);
+        assert_eq!(writer.spec(), spec);
+        common::write_samples(&mut writer, spec.sample_format.bits_per_sample(), samples);
+        if finalize {
+            writer.finalize().unwrap();
+        }
+    }
+    cursor.into_inner()
+}
+
+// The sample is read from the WAV file, as the CAF sample is itself encoded as ALAC.
+#[cfg(feature = "wav")]
+#[test]
+fn write_caf_alac_sample_bit_exact() {
+    let mut wav = audrey::open("samples/sine_440hz_stereo.wav").unwrap();
+    let original = wav.samples::<i16>().map(Result::unwrap).collect::<Vec<_>>();
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let signal = original.iter().map(|&s| i32::from(s)).collect::<Vec<_>>();
+    let bytes = write_alac(spec, &signal, true);
+    // The tone should compress well below the size of the raw samples.
+    assert!(bytes.len() < signal.len());
+    let (sample_rate, channels, packets, samples) = read_alac(Cursor::new(bytes));
+    assert_eq!(sample_rate, 44_100.0);
+    assert_eq!(channels, 2);
+    assert_eq!(packets, packet_count(original.len() / 2));
+    let expected = signal.iter().map(|&s| s << 16).collect::<Vec<_>>();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn write_caf_alac_specs() {
+    let specs = [
+        (1, 8_000, SampleFormat::I16, 3_000),
+        (2, 44_100, SampleFormat::I16, 13_001),
+        (2, 48_000, SampleFormat::I24, 9_000),
+        (2, 96_000, SampleFormat::I32, 8_192),
+        (3, 44_100, SampleFormat::I16, 5_000),
+        (6, 48_000, SampleFormat::I24, 4_097),
+        (8, 22_050, SampleFormat::I16, 4_500),
+    ];
+    for &(channel_count, sample_rate, sample_format, frames) in specs.iter() {
+        let spec = Spec {
+            channel_count,
+            sample_rate,
+            sample_format,
+        };
+        let bits = sample_format.bits_per_sample();
+        let signal = common::test_signal(channel_count as usize, frames, bits);
+        let bytes = write_alac(spec, &signal, true);
+        assert!(bytes.len() < signal.len() * bits as usize / 8, "{:?}", spec);
+        let (rate, channels, packets, samples) = read_alac(Cursor::new(bytes));
+        assert_eq!(rate, f64::from(sample_rate));
+        assert_eq!(channels, channel_count);
+        assert_eq!(packets, packet_count(frames));
+        let expected = signal.iter().map(|&s| s << (32 - bits)).collect::<Vec<_>>();
+        assert_eq!(samples, expected, "{:?}", spec);
+    }
+}
+
+#[test]
+fn write_caf_alac_finalizes_on_drop() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let signal = common::test_signal(2, 5_000, 16);
+    let finalized = write_alac(spec, &signal, true);
+    let dropped = write_alac(spec, &signal, false);
+    assert_eq!(finalized, dropped);
+}
+
+#[test]
+fn write_caf_alac_unsupported_spec() {
+    for &sample_format in [SampleFormat::I8, SampleFormat::F32].iter() {
+        let spec = Spec {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_format,
+        };
+        match audrey::Writer::new(Cursor::new(Vec::new()), Format::CafAlac, spec) {
+            Err(WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+    for &(channel_count, sample_rate) in [(9, 44_100), (2, 0)].iter() {
+        let spec = Spec {
+            channel_count,
+            sample_rate,
+            sample_format: SampleFormat::I16,
+        };
+        assert!(!audrey::Writer::<Cursor<Vec<u8>>>::supports(
+            Format::CafAlac,
+            &spec
+        ));
+        match audrey::Writer::new(Cursor::new(Vec::new()), Format::CafAlac, spec) {
+            Err(WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+#![cfg(feature = "flac")]
+
+extern crate audrey;
+
+use audrey::write::{FlacOptions, Spec, WriteError};
+use audrey::{Format, SampleFormat};
+use std::io::Cursor;
+
+mod common;
+
+const FLAC: &str = "samples/sine_440hz_stereo.flac";
+
+// Reads the raw samples and STREAMINFO of the FLAC stream within the given reader.
+fn read_flac<R>(reader: R) -> (audrey::claxon::metadata::StreamInfo, Vec<i32>)
+where
+    R: std::io::Read + std::io::Seek,
+{
+    match audrey::Reader::new(reader).unwrap() {
+        audrey::Reader::Flac(mut flac) => {
+            let streaminfo = flac.streaminfo();
+            let samples = std::iter::from_fn(|| flac.next_sample())
+                .map(Result::unwrap)
+                .collect();
+            (streaminfo, samples)
+        }
+        #[allow(unreachable_patterns)]
+        _ => panic!("Incorrect audio format"),
+    }
+}
+
+// Encodes the given interleaved samples, returning the encoded bytes.
+fn write_flac(spec: Spec, options: FlacOptions, samples: &[i32]) -> Vec<u8> {
+    let bits = spec.sample_format.bits_per_sample();
+    common::encode(bits, samples, |cursor| {
+        let writer = audrey::Writer::new_flac(cursor, spec, options)?;
+        assert_eq!(writer.format(), Format::Flac);
+        assert_eq!(writer.spec(), spec);
+        Ok(writer)
+    })
+}
+
+#[test]
+fn write_flac_sample_bit_exact() {
+    let (original_info, original) = read_flac(std::fs::File::open(FLAC).unwrap());
+    let spec = Spec {
+        channel_count: original_info.channels,
+        sample_rate: original_info.sample_rate,
+        sample_format: SampleFormat::I16,
+    };
+    let bytes = write_flac(spec, FlacOptions::default(), &original);
+    let (info, samples) = read_flac(Cursor::new(bytes));
+    assert_eq!(info.channels, original_info.channels);
+    assert_eq!(info.sample_rate, original_info.sample_rate);
+    assert_eq!(info.bits_per_sample, 16);
+    assert_eq!(info.samples, original_info.samples);
+    assert_eq!(info.md5sum, original_info.md5sum);
+    assert_eq!(samples, original);
+}
+
+#[test]
+fn write_flac_compression_levels() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let signal = common::test_signal(2, 20_000, 16);
+    let mut sizes = vec![];
+    for compression_level in 0..=8 {
+        let options = FlacOptions {
+            compression_level,
+            ..Default::default()
+        };
+        let bytes = write_flac(spec, options, &signal);
+        sizes.push(bytes.len());
+        let (info, samples) = read_flac(Cursor::new(bytes));
+        assert_eq!(info.samples, Some(20_000));
+        assert_eq!(samples, signal, "compression level {}", compression_level);
+    }
+    assert!(sizes.iter().all(|&size| size < signal.len() * 2));
+}
+
+#[test]
+fn write_flac_specs() {
+    let specs = [
+        (1, 8_000, SampleFormat::I8, 16),
+        (1, 22_050, SampleFormat::I16, 192),
+        (2, 48_000, SampleFormat::I24, 1_000),
+        (3, 44_100, SampleFormat::I16, 4_608),
+        (6, 96_000, SampleFormat::I24, 4_096),
+        (8, 11_025, SampleFormat::I8, 65_535),
+        (2, 655_350, SampleFormat::I16, 300),
+        (2, 100_001, SampleFormat::I16, 4_096),
+        (2, 44_100, SampleFormat::I32, 4_096),
+    ];
+    for &(channel_count, sample_rate, sample_format, block_size) in specs.iter() {
+        let spec = Spec {
+            channel_count,
+            sample_rate,
+            sample_format,
+        };
+        let bits = sample_format.bits_per_sample();
+        let signal = common::test_signal(channel_count as usize, 13_001, bits);
+        let options = FlacOptions {
+            compression_level: 8,
+            block_size: Some(block_size),
+            ..Default::default()
+        };
+        let (info, samples) = read_flac(Cursor::new(write_flac(spec, options, &signal)));
+        assert_eq!(info.channels, channel_count);
+        assert_eq!(info.sample_rate, sample_rate);
+        assert_eq!(info.bits_per_sample, bits);
+        assert_eq!(info.max_block_size, block_size);
+        assert_eq!(samples, signal, "{:?}", spec);
+    }
+}
+
+#[test]
+fn write_flac_claxon_decode() {
+    // Decode the output with claxon directly, independent of audrey's own FLAC reading.
+    for &sample_format in [SampleFormat::I8, SampleFormat::I16, SampleFormat::I24].iter() {
+        let spec = Spec {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_format,
+        };
+        let bits = sample_format.bits_per_sample();
+        let signal = common::test_signal(2, 10_000, bits);
+        let bytes = write_flac(spec, FlacOptions::default(), &signal);
+        let mut reader = audrey::claxon::FlacReader::new(Cursor::new(bytes)).unwrap();
+        let info = reader.streaminfo();
+        assert_eq!(info.bits_per_sample, bits);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.samples, Some(10_000));
+        let samples = reader.samples().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(samples, signal, "{:?}", sample_format);
+    }
+}
+
+#[test]
+fn write_flac_finalizes_on_drop() {
+    let spec = Spec {
+        channel_count: 2,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    let signal = common::test_signal(2, 5_000, 16);
+    let finalized = write_flac(spec, FlacOptions::default(), &signal);
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = audrey::Writer::new(&mut cursor, Format::Flac, spec).unwrap();
+        common::write_samples(&mut writer, 16, &signal);
+    }
+    assert_eq!(finalized, cursor.into_inner());
+}
+
+#[test]
+fn write_flac_unsupported_spec() {
+    for &sample_format in [SampleFormat::F32, SampleFormat::F64].iter() {
+        let spec = Spec {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_format,
+        };
+        match audrey::Writer::new(Cursor::new(Vec::new()), Format::Flac, spec) {
+            Err(WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+    let spec = Spec {
+        channel_count: 9,
+        sample_rate: 44_100,
+        sample_format: SampleFormat::I16,
+    };
+    match audrey::Writer::new(Cursor::new(Vec::new()), Format::Flac, spec) {
+        Err(WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+        _ => panic!("expected an unsupported spec error"),
+    }
+
+    // Bit depths beyond the range of FLAC or of the spec's sample format.
+    for &bits_per_sample in [3, 17].iter() {
+        let spec = Spec {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_format: SampleFormat::I16,
+        };
+        let options = FlacOptions {
+            bits_per_sample: Some(bits_per_sample),
+            ..Default::default()
+        };
+        match audrey::Writer::new_flac(Cursor::new(Vec::new()), spec, options) {
+            Err(WriteError::UnsupportedSpec(s)) => assert_eq!(s, spec),
+            _ => panic!("expected an unsupported spec error"),
+        }
+    }
+}